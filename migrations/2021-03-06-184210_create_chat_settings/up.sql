@@ -0,0 +1,4 @@
+create table chat_settings (
+	chat     bigint not null primary key,
+	timezone text -- IANA timezone name. Null means the configured default.
+);
@@ -0,0 +1,21 @@
+-- This file should undo anything in `up.sql`
+
+-- SQLite can't drop columns, so rebuild the table.
+create table reminders_old (
+	id         integer not null primary key,
+	due        bigint  not null, -- Unix time
+	recurrying integer, -- Recurrence custom format.
+	chat       bigint  not null,
+	message    text    not null
+);
+
+insert into reminders_old (id, due, recurrying, chat, message)
+	select id, due, recurrying, chat, message from reminders;
+
+drop index reminders_due;
+
+drop table reminders;
+
+alter table reminders_old rename to reminders;
+
+create index reminders_due on reminders (due);
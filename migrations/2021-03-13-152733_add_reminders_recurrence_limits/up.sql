@@ -0,0 +1,3 @@
+alter table reminders add column recur_until bigint; -- Unix time, last possible occurrence.
+
+alter table reminders add column recur_remaining integer; -- Occurrences left, including the next.
@@ -0,0 +1,45 @@
+-- This file should undo anything in `up.sql`
+
+-- Weekday rules can't be represented in the packed format, so those reminders lose their
+-- recurrence.
+create table reminders_old (
+	id              integer not null primary key,
+	due             bigint  not null, -- Unix time
+	recurrying      integer, -- Recurrence custom format.
+	chat            bigint  not null,
+	message         text    not null,
+	recur_until     bigint, -- Unix time, last possible occurrence.
+	recur_remaining integer -- Occurrences left, including the next.
+);
+
+insert into reminders_old (id, due, recurrying, chat, message, recur_until, recur_remaining)
+	select
+		id,
+		due,
+		case
+			when recurrying glob '+[0-9][mhdwMy]'
+				or recurrying glob '+[0-9][0-9][mhdwMy]'
+				or recurrying glob '+[0-9][0-9][0-9][mhdwMy]'
+			then
+				case substr(recurrying, -1)
+					when 'm' then 1
+					when 'h' then 2
+					when 'd' then 3
+					when 'w' then 4
+					when 'M' then 5
+					when 'y' then 6
+				end << 24 | cast(substr(recurrying, 2, length(recurrying) - 2) as integer)
+		end,
+		chat,
+		message,
+		recur_until,
+		recur_remaining
+	from reminders;
+
+drop index reminders_due;
+
+drop table reminders;
+
+alter table reminders_old rename to reminders;
+
+create index reminders_due on reminders (due);
@@ -0,0 +1,37 @@
+-- The packed integer format can't hold weekday rules, so store the recurrence as text.
+-- SQLite can't change column types, so rebuild the table.
+create table reminders_new (
+	id              integer not null primary key,
+	due             bigint  not null, -- Unix time
+	recurrying      text, -- Recurrence rule, like +2d, +mon,wed,fri or +lasttue.
+	chat            bigint  not null,
+	message         text    not null,
+	recur_until     bigint, -- Unix time, last possible occurrence.
+	recur_remaining integer -- Occurrences left, including the next.
+);
+
+insert into reminders_new (id, due, recurrying, chat, message, recur_until, recur_remaining)
+	select
+		id,
+		due,
+		'+' || (recurrying & 255) || case (recurrying >> 24)
+			when 1 then 'm'
+			when 2 then 'h'
+			when 3 then 'd'
+			when 4 then 'w'
+			when 5 then 'M'
+			when 6 then 'y'
+		end,
+		chat,
+		message,
+		recur_until,
+		recur_remaining
+	from reminders;
+
+drop index reminders_due;
+
+drop table reminders;
+
+alter table reminders_new rename to reminders;
+
+create index reminders_due on reminders (due);
@@ -0,0 +1 @@
+alter table reminders add column recur_anchor integer; -- Day of the month of the first occurrence.
@@ -0,0 +1 @@
+alter table reminders add column source_message bigint; -- Message the reminder replies to.
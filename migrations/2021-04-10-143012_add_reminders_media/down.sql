@@ -0,0 +1,25 @@
+-- This file should undo anything in `up.sql`
+
+-- SQLite can't drop columns, so rebuild the table.
+create table reminders_old (
+	id              integer not null primary key,
+	due             bigint  not null, -- Unix time
+	recurrying      text, -- Recurrence rule, like +2d, +mon,wed,fri or +lasttue.
+	chat            bigint  not null,
+	message         text    not null,
+	recur_until     bigint, -- Unix time, last possible occurrence.
+	recur_remaining integer, -- Occurrences left, including the next.
+	recur_anchor    integer, -- Day of the month of the first occurrence.
+	source_message  bigint -- Message the reminder replies to.
+);
+
+insert into reminders_old (id, due, recurrying, chat, message, recur_until, recur_remaining, recur_anchor, source_message)
+	select id, due, recurrying, chat, message, recur_until, recur_remaining, recur_anchor, source_message from reminders;
+
+drop index reminders_due;
+
+drop table reminders;
+
+alter table reminders_old rename to reminders;
+
+create index reminders_due on reminders (due);
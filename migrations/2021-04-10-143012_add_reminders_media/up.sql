@@ -0,0 +1,2 @@
+alter table reminders add column media_kind text; -- photo or document.
+alter table reminders add column media_file_id text; -- Telegram's id of the uploaded file.
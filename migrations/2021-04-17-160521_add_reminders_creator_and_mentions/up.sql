@@ -0,0 +1,4 @@
+alter table reminders add column creator_id bigint; -- Telegram user who added the reminder.
+alter table reminders add column creator_name text;
+alter table reminders add column creator_username text;
+alter table reminders add column mentions text; -- Users to tag, one per line: @username or "<id> <name>".
@@ -0,0 +1 @@
+alter table reminders add column recipient bigint; -- User to deliver to privately, instead of in chat.
@@ -0,0 +1,16 @@
+-- This file should undo anything in `up.sql`
+
+drop table chat_roles;
+
+-- SQLite can't drop columns, so rebuild the table.
+create table chat_settings_old (
+	chat     bigint not null primary key,
+	timezone text -- IANA timezone name. Null means the configured default.
+);
+
+insert into chat_settings_old (chat, timezone)
+	select chat, timezone from chat_settings;
+
+drop table chat_settings;
+
+alter table chat_settings_old rename to chat_settings;
@@ -0,0 +1,10 @@
+create table chat_roles (
+	chat    bigint not null,
+	user_id bigint not null,
+	role    text   not null, -- owner, admin or member.
+	primary key (chat, user_id)
+);
+
+-- Whether members may manage every reminder, instead of only their own, like they could
+-- before roles.
+alter table chat_settings add column everyone_manages boolean not null default 1;
@@ -0,0 +1,7 @@
+-- One-time codes that authenticate a chat, created by admins of trusted chats.
+create table invites (
+	code       text   not null primary key,
+	chat       bigint not null, -- Where it was created.
+	created_by bigint not null,
+	expires    bigint not null
+);
@@ -0,0 +1,34 @@
+-- This file should undo anything in `up.sql`
+
+drop table deliveries;
+
+-- Reuse reminder ids again.
+create table reminders_old (
+	id              integer not null primary key,
+	due             bigint  not null, -- Unix time
+	recurrying      text, -- Recurrence rule, like +2d, +mon,wed,fri or +lasttue.
+	chat            bigint  not null,
+	message         text    not null,
+	recur_until     bigint, -- Unix time, last possible occurrence.
+	recur_remaining integer, -- Occurrences left, including the next.
+	recur_anchor    integer, -- Day of the month of the first occurrence.
+	source_message  bigint, -- Message the reminder replies to.
+	media_kind      text, -- photo or document.
+	media_file_id   text, -- Telegram's id of the uploaded file.
+	creator_id      bigint, -- Telegram user who added the reminder.
+	creator_name    text,
+	creator_username text,
+	mentions        text, -- Users to tag, one per line: @username or "<id> <name>".
+	recipient       bigint -- User to deliver to privately, instead of in chat.
+);
+
+insert into reminders_old (id, due, recurrying, chat, message, recur_until, recur_remaining, recur_anchor, source_message, media_kind, media_file_id, creator_id, creator_name, creator_username, mentions, recipient)
+	select id, due, recurrying, chat, message, recur_until, recur_remaining, recur_anchor, source_message, media_kind, media_file_id, creator_id, creator_name, creator_username, mentions, recipient from reminders;
+
+drop index reminders_due;
+
+drop table reminders;
+
+alter table reminders_old rename to reminders;
+
+create index reminders_due on reminders (due);
@@ -0,0 +1,45 @@
+-- Every attempt to deliver a reminder, kept after the reminder is gone.
+create table deliveries (
+	id        integer not null primary key,
+	reminder  integer not null,
+	chat      bigint  not null, -- Where it was delivered, or added if it failed.
+	scheduled bigint  not null,
+	sent      bigint  not null, -- When it was attempted.
+	message   bigint,           -- The Telegram message, unless it failed.
+	outcome   text    not null, -- One of: sent, skipped, failed or snoozed.
+	text      text    not null
+);
+
+create index deliveries_chat on deliveries (chat, id);
+
+-- Deliveries outlive their reminders, so reminder ids must not be reused once deleted.
+-- SQLite can't change a primary key, so rebuild the table.
+create table reminders_new (
+	id              integer not null primary key autoincrement,
+	due             bigint  not null, -- Unix time
+	recurrying      text, -- Recurrence rule, like +2d, +mon,wed,fri or +lasttue.
+	chat            bigint  not null,
+	message         text    not null,
+	recur_until     bigint, -- Unix time, last possible occurrence.
+	recur_remaining integer, -- Occurrences left, including the next.
+	recur_anchor    integer, -- Day of the month of the first occurrence.
+	source_message  bigint, -- Message the reminder replies to.
+	media_kind      text, -- photo or document.
+	media_file_id   text, -- Telegram's id of the uploaded file.
+	creator_id      bigint, -- Telegram user who added the reminder.
+	creator_name    text,
+	creator_username text,
+	mentions        text, -- Users to tag, one per line: @username or "<id> <name>".
+	recipient       bigint -- User to deliver to privately, instead of in chat.
+);
+
+insert into reminders_new (id, due, recurrying, chat, message, recur_until, recur_remaining, recur_anchor, source_message, media_kind, media_file_id, creator_id, creator_name, creator_username, mentions, recipient)
+	select id, due, recurrying, chat, message, recur_until, recur_remaining, recur_anchor, source_message, media_kind, media_file_id, creator_id, creator_name, creator_username, mentions, recipient from reminders;
+
+drop index reminders_due;
+
+drop table reminders;
+
+alter table reminders_new rename to reminders;
+
+create index reminders_due on reminders (due);
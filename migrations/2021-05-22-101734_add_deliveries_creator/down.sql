@@ -0,0 +1,24 @@
+-- This file should undo anything in `up.sql`
+
+-- SQLite can't drop columns, so rebuild the table.
+create table deliveries_old (
+	id        integer not null primary key,
+	reminder  integer not null,
+	chat      bigint  not null, -- Where it was delivered, or added if it failed.
+	scheduled bigint  not null,
+	sent      bigint  not null, -- When it was attempted.
+	message   bigint,           -- The Telegram message, unless it failed.
+	outcome   text    not null, -- One of: sent, skipped, failed or snoozed.
+	text      text    not null
+);
+
+insert into deliveries_old (id, reminder, chat, scheduled, sent, message, outcome, text)
+	select id, reminder, chat, scheduled, sent, message, outcome, text from deliveries;
+
+drop index deliveries_chat;
+
+drop table deliveries;
+
+alter table deliveries_old rename to deliveries;
+
+create index deliveries_chat on deliveries (chat, id);
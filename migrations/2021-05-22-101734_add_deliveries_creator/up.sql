@@ -0,0 +1,4 @@
+-- Who added the delivered reminder, whom snoozing it keeps as the creator.
+alter table deliveries add column creator_id bigint;
+alter table deliveries add column creator_name text;
+alter table deliveries add column creator_username text;
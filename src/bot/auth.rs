@@ -0,0 +1,345 @@
+use std::{
+	cell::RefCell,
+	collections::{HashMap, HashSet},
+	fmt::{self, Debug},
+	iter::FromIterator
+};
+
+use futures::{Stream, StreamExt};
+
+use chrono::{DateTime, Duration, Utc};
+
+use argon2::{Argon2, PasswordHash, PasswordVerifier};
+
+use super::{ChatId, ChatInfo, Message, MessageId, Messenger, Update};
+use crate::{
+	clock::Clock,
+	config::{self, Secret},
+	db::{
+		Db,
+		Error as DbError,
+		models::trusted_chats::NewTrustedChat,
+		types::{ChatId as DbChatId, DueTimestamp, MessageId as DbMessageId},
+	}
+};
+
+
+/// A chat's failed authentication attempts in the current throttling period.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct FailedAttempts {
+	count: u32,
+	/// When the period started.
+	since: DateTime<Utc>,
+}
+
+
+/// Only lets through messages from trusted chats, which are the ones that have sent a
+/// valid password or invite. Chats that fail too many times are left, and ignored for a
+/// while.
+pub struct Authenticator<'a, M, C> {
+	messenger: &'a M,
+	db: &'a Db,
+	clock: &'a C,
+	authentication: &'a config::Authentication,
+	// These need to be refcells so we can mutate them while streaming. Just be sure that
+	// they won't be borrowed across an await point, and all shall be fine.
+	trusted_chats: RefCell<HashSet<ChatId>>,
+	failed_attempts: RefCell<HashMap<ChatId, FailedAttempts>>,
+}
+
+
+impl<'a, M: Messenger, C: Clock> Authenticator<'a, M, C> {
+	pub fn new(
+		messenger: &'a M,
+		db: &'a Db,
+		clock: &'a C,
+		authentication: &'a config::Authentication
+	) -> Result<Self, DbError> {
+		let trusted_chats = db.trusted_chat_ids()?;
+
+		let trusted_chats = HashSet::from_iter(
+			trusted_chats
+				.iter()
+				.map(
+					|chat_id| chat_id.0
+				)
+		);
+
+		Ok(
+			Self {
+				messenger,
+				db,
+				clock,
+				authentication,
+				trusted_chats: RefCell::new(trusted_chats),
+				failed_attempts: RefCell::new(HashMap::new()),
+			}
+		)
+	}
+
+
+	/// Whether the chat is the operator's, which is always trusted.
+	pub fn is_operator(&self, chat_id: ChatId) -> bool {
+		self.authentication.operator == Some(chat_id.into())
+	}
+
+
+	/// Stop trusting the chat, which must have been removed from the database already.
+	pub fn distrust_chat(&self, chat_id: ChatId) {
+		self.trusted_chats
+			.borrow_mut()
+			.remove(&chat_id);
+	}
+
+
+	pub fn stream(&'a self) -> impl Stream<Item = Message> + 'a {
+		self.messenger
+			.stream()
+			.filter_map(
+				move |update| async move {
+					if !self.validate_chat(&update).await {
+						return None;
+					}
+
+					match update {
+						Update::Message { message: Some(message), .. } => {
+							message.log();
+							Some(message)
+						}
+
+						_ => None,
+					}
+				}
+			)
+	}
+
+
+	async fn validate_chat(&self, update: &Update) -> bool {
+		let chat_info = update.chat();
+		let chat_id = chat_info.chat_id;
+
+		if let Update::Joined(_) = update {
+			log::warn!("I've been added to a new chat: {:?}", chat_info);
+
+			if self.throttled(chat_id, self.clock.now()) {
+				log::warn!("Leaving throttled chat: {:?}", chat_info);
+				self.leave_chat(chat_id).await;
+				return false;
+			}
+
+			log::info!("Requesting password...");
+
+			let result = self.messenger
+				.send_message(chat_id, &self.authentication.prompt)
+				.await;
+
+			if let Err(error) = result {
+				log::warn!("Failed to send message to chat {:?}: {}", chat_info, error);
+			}
+
+			return false;
+		}
+
+		let trusted = self.is_operator(chat_id) || self.trusted_chats
+			.borrow()
+			.contains(&chat_id);
+
+		if !trusted {
+			match update {
+				// The only buttons the bot sends to untrusted private chats are on reminders
+				// delivered privately.
+				Update::Message { message: Some(Message::Callback { message_id, .. }), .. } if chat_info.private => {
+					return self.is_delivery(chat_id, *message_id);
+				},
+
+				Update::Message { text: Some(text), .. } => self.authenticate(chat_info, text).await,
+
+				_ => { },
+			}
+		}
+
+		trusted
+	}
+
+
+	/// Whether the message is a reminder the bot delivered to the chat.
+	fn is_delivery(&self, chat_id: ChatId, message_id: MessageId) -> bool {
+		let delivery = self.db.message_delivery(
+			DbChatId(chat_id),
+			DbMessageId(message_id)
+		);
+
+		match delivery {
+			Ok(delivery) => delivery.is_some(),
+			Err(error) => {
+				log::warn!("Failed to look up delivery of message {} in chat {}: {}", message_id, chat_id, error);
+				false
+			}
+		}
+	}
+
+
+	/// Trust the chat if the text is a valid password or invite, or count a failed attempt
+	/// otherwise, leaving the chat if it has failed too many times.
+	async fn authenticate(&self, chat_info: &ChatInfo, text: &str) {
+		let chat_id = chat_info.chat_id;
+		let now = self.clock.now();
+
+		if self.throttled(chat_id, now) {
+			log::warn!("Ignoring authentication attempt from throttled chat: {:?}", chat_info);
+			return;
+		}
+
+		if self.accepts_password(text, now) || self.redeem_invite(text, now) {
+			self.failed_attempts
+				.borrow_mut()
+				.remove(&chat_id);
+
+			self.trust_chat(chat_info).await;
+			return;
+		}
+
+		let attempts = self.record_failure(chat_id, now);
+
+		log::warn!("Failed authentication attempt #{} from chat: {:?}", attempts, chat_info);
+
+		if attempts >= self.authentication.throttle.attempts {
+			log::warn!("Throttling chat: {:?}", chat_info);
+			self.leave_chat(chat_id).await;
+		}
+	}
+
+
+	/// Whether the text is one of the passwords that haven't expired.
+	fn accepts_password(&self, text: &str, now: DateTime<Utc>) -> bool {
+		self.authentication.passwords
+			.iter()
+			.filter(
+				|password| password.expires.map_or(true, |expires| now < expires)
+			)
+			.any(
+				|password| match &password.secret {
+					Secret::Plain(plain) => plain.as_ref() == text,
+					Secret::Hash(hash) => verify_hash(hash, text),
+				}
+			)
+	}
+
+
+	/// Whether the text is the code of an invite that hasn't expired, consuming it.
+	fn redeem_invite(&self, text: &str, now: DateTime<Utc>) -> bool {
+		self.db
+			.redeem_invite(text.trim(), DueTimestamp(now))
+			.unwrap_or_else(
+				|error| {
+					log::warn!("Failed to redeem invite: {}", error);
+					false
+				}
+			)
+	}
+
+
+	fn throttled(&self, chat_id: ChatId, now: DateTime<Utc>) -> bool {
+		let period = Duration::minutes(self.authentication.throttle.period as i64);
+
+		self.failed_attempts
+			.borrow()
+			.get(&chat_id)
+			.map_or(
+				false,
+				|failed| failed.count >= self.authentication.throttle.attempts
+					&& now - failed.since < period
+			)
+	}
+
+
+	/// Count a failed attempt, returning how many there were in the current period.
+	fn record_failure(&self, chat_id: ChatId, now: DateTime<Utc>) -> u32 {
+		let period = Duration::minutes(self.authentication.throttle.period as i64);
+
+		let mut failed_attempts = self.failed_attempts.borrow_mut();
+
+		let failed = failed_attempts
+			.entry(chat_id)
+			.or_insert(
+				FailedAttempts { count: 0, since: now }
+			);
+
+		if now - failed.since >= period {
+			*failed = FailedAttempts { count: 0, since: now };
+		}
+
+		failed.count += 1;
+		failed.count
+	}
+
+
+	async fn leave_chat(&self, chat_id: ChatId) {
+		let result = self.messenger
+			.leave_chat(chat_id)
+			.await;
+
+		if let Err(error) = result {
+			log::warn!("Failed to leave chat {}: {}", chat_id, error);
+		}
+	}
+
+
+	async fn trust_chat(&self, chat_info: &ChatInfo) {
+		let chat_id = chat_info.chat_id;
+
+		let result = self.db.new_trusted_chat(
+			&NewTrustedChat {
+				id: DbChatId(chat_id),
+				username: chat_info.username.as_deref(),
+				title: chat_info.title.as_deref(),
+			}
+		);
+
+		if let Err(error) = result {
+			log::warn!("Failed to add trusted chat {:?}: {}", chat_info, error);
+			return; // Don't update the cache if the DB update failed.
+		}
+
+		self.trusted_chats
+			.borrow_mut()
+			.insert(chat_id);
+
+		log::info!("Added trusted chat: {:?}", chat_info);
+
+		let result = self.messenger
+			.send_message(chat_id, &self.authentication.authorized)
+			.await;
+
+		if let Err(error) = result {
+			log::warn!("Failed to send message to chat {:?}: {}", chat_info, error);
+		}
+	}
+}
+
+
+impl<'a, M, C> Debug for Authenticator<'a, M, C> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(
+			f,
+			"Authenticator {{{:?}}}",
+			self.trusted_chats
+		)
+	}
+}
+
+
+/// Whether the text matches the argon2 hash. Invalid hashes match nothing.
+fn verify_hash(hash: &str, text: &str) -> bool {
+	match PasswordHash::new(hash) {
+		Ok(hash) => Argon2
+			::default()
+			.verify_password(text.as_bytes(), &hash)
+			.is_ok(),
+
+		Err(error) => {
+			log::error!("Invalid password hash: {}", error);
+			false
+		}
+	}
+}
@@ -0,0 +1,549 @@
+//! An in-memory messenger, for tests.
+
+use std::{
+	cell::{Cell, RefCell},
+	collections::{HashMap, HashSet, VecDeque},
+};
+
+use async_trait::async_trait;
+
+use futures::{StreamExt, stream::LocalBoxStream};
+
+use crate::clock::{Clock, ManualClock};
+use super::{
+	Administrator,
+	BotError,
+	CallbackQueryId,
+	ChatId,
+	ChatInfo,
+	Media,
+	Mention,
+	Message,
+	MessageId,
+	Messenger,
+	Reply,
+	Update,
+	UserId,
+};
+
+
+/// The user injected messages are from, unless changed.
+pub const USER_ID: i64 = 100;
+pub const USER_NICKNAME: &str = "tester";
+
+
+/// Something the bot sent.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Sent {
+	Message {
+		chat: ChatId,
+		text: String,
+		buttons: Vec<String>,
+		reply_to: Option<MessageId>,
+		mentions: Vec<Mention>,
+	},
+	Media {
+		chat: ChatId,
+		media: Media,
+		caption: String,
+		buttons: Vec<String>,
+		reply_to: Option<MessageId>,
+		mentions: Vec<Mention>,
+	},
+	Edit {
+		chat: ChatId,
+		message: MessageId,
+		text: String,
+	},
+	EditCaption {
+		chat: ChatId,
+		message: MessageId,
+		caption: String,
+	},
+	File {
+		chat: ChatId,
+		name: String,
+		data: Vec<u8>,
+	},
+	Answer {
+		text: String,
+	},
+	Left(ChatId),
+}
+
+
+/// Streams the injected updates, and records everything sent.
+#[derive(Debug)]
+pub struct FakeMessenger {
+	/// Messages are sent at this clock's time.
+	clock: ManualClock,
+	/// Messages are sent by this user.
+	sender: RefCell<(UserId, Box<str>)>,
+	updates: RefCell<VecDeque<Update>>,
+	sent: RefCell<Vec<Sent>>,
+	/// Chats that can't be sent to.
+	unreachable: RefCell<HashSet<ChatId>>,
+	administrators: RefCell<HashMap<ChatId, Vec<Administrator>>>,
+	/// Files that can be downloaded, by id.
+	files: RefCell<HashMap<Box<str>, Vec<u8>>>,
+	/// The id of the last message sent with buttons.
+	last_message_id: Cell<i64>,
+}
+
+
+impl FakeMessenger {
+	pub fn new(clock: ManualClock) -> Self {
+		Self {
+			clock,
+			sender: RefCell::new(
+				(UserId::new(USER_ID), USER_NICKNAME.into())
+			),
+			updates: RefCell::default(),
+			sent: RefCell::default(),
+			unreachable: RefCell::default(),
+			administrators: RefCell::default(),
+			files: RefCell::default(),
+			last_message_id: Cell::default(),
+		}
+	}
+
+
+	pub fn push(&self, update: Update) {
+		self.updates
+			.borrow_mut()
+			.push_back(update);
+	}
+
+
+	/// The bot was added to the chat.
+	pub fn push_joined(&self, chat_id: ChatId) {
+		self.push(
+			Update::Joined(
+				Self::chat_info(chat_id)
+			)
+		);
+	}
+
+
+	/// A text message addressed to the bot.
+	pub fn push_text(&self, chat_id: ChatId, text: &str) {
+		self.push_message(chat_id, text, Box::default(), None);
+	}
+
+
+	/// A text message addressed to the bot, mentioning users.
+	pub fn push_mentions(&self, chat_id: ChatId, text: &str, mentions: &[Mention]) {
+		self.push_message(chat_id, text, mentions.into(), None);
+	}
+
+
+	/// A text message addressed to the bot, in reply to another message.
+	pub fn push_reply(&self, chat_id: ChatId, text: &str, reply_to: Reply) {
+		self.push_message(chat_id, text, Box::default(), Some(reply_to));
+	}
+
+
+	/// A photo or document captioned with text addressed to the bot.
+	pub fn push_media(&self, chat_id: ChatId, caption: &str, media: Media) {
+		self.push_media_mentions(chat_id, caption, media, &[]);
+	}
+
+
+	/// A photo or document captioned with text addressed to the bot, mentioning users.
+	pub fn push_media_mentions(&self, chat_id: ChatId, caption: &str, media: Media, mentions: &[Mention]) {
+		let (user_id, nickname) = self.sender
+			.borrow()
+			.clone();
+
+		let message = Message::Media {
+			user_id,
+			nickname,
+			username: None,
+			chat_id,
+			date: self.clock.now(),
+			caption: caption.into(),
+			media,
+			mentions: mentions.into(),
+			reply_to: None,
+		};
+
+		self.push_update(chat_id, caption, message);
+	}
+
+
+	/// An iCalendar document sent without a command.
+	pub fn push_calendar(&self, chat_id: ChatId, media: Media) {
+		let (user_id, nickname) = self.sender
+			.borrow()
+			.clone();
+
+		let message = Message::Calendar {
+			user_id,
+			nickname,
+			username: None,
+			chat_id,
+			date: self.clock.now(),
+			media,
+		};
+
+		self.push(
+			Update::Message {
+				chat: Self::chat_info(chat_id),
+				text: None,
+				message: Some(message),
+			}
+		);
+	}
+
+
+	fn push_message(&self, chat_id: ChatId, text: &str, mentions: Box<[Mention]>, reply_to: Option<Reply>) {
+		let (user_id, nickname) = self.sender
+			.borrow()
+			.clone();
+
+		let message = Message::Text {
+			user_id,
+			nickname,
+			username: None,
+			chat_id,
+			date: self.clock.now(),
+			text: text.into(),
+			mentions,
+			reply_to,
+		};
+
+		self.push_update(chat_id, text, message);
+	}
+
+
+	/// A button pressed on a message the bot sent.
+	pub fn push_callback(&self, chat_id: ChatId, message_id: MessageId, text: &str, data: &str) {
+		self.push_callback_in(Self::chat_info(chat_id), message_id, text, data);
+	}
+
+
+	/// A button pressed on a message the bot sent to a private chat.
+	pub fn push_private_callback(&self, chat_id: ChatId, message_id: MessageId, text: &str, data: &str) {
+		let chat = ChatInfo {
+			private: true,
+			..Self::chat_info(chat_id)
+		};
+
+		self.push_callback_in(chat, message_id, text, data);
+	}
+
+
+	fn push_callback_in(&self, chat: ChatInfo, message_id: MessageId, text: &str, data: &str) {
+		// Query ids can't be built but by deserializing.
+		let query_id = serde_json
+			::from_str(r#""query""#)
+			.expect("invalid query id");
+
+		let (user_id, nickname) = self.sender
+			.borrow()
+			.clone();
+
+		let message = Message::Callback {
+			query_id,
+			user_id,
+			nickname,
+			username: None,
+			chat_id: chat.chat_id,
+			message_id,
+			message_text: text.into(),
+			media: None,
+			mentions: Box::default(),
+			reply_to: None,
+			data: data.into(),
+		};
+
+		self.push(
+			Update::Message {
+				chat,
+				text: None,
+				message: Some(message),
+			}
+		);
+	}
+
+
+	/// Messages injected from now on are sent by the given user.
+	pub fn set_sender(&self, user_id: UserId, nickname: &str) {
+		*self.sender.borrow_mut() = (user_id, nickname.into());
+	}
+
+
+	/// Sending to the chat fails from now on, like to users who never started a chat with
+	/// the bot.
+	pub fn set_unreachable(&self, chat_id: ChatId) {
+		self.unreachable
+			.borrow_mut()
+			.insert(chat_id);
+	}
+
+
+	pub fn set_administrators(&self, chat_id: ChatId, administrators: Vec<Administrator>) {
+		self.administrators
+			.borrow_mut()
+			.insert(chat_id, administrators);
+	}
+
+
+	/// Make a file available for download.
+	pub fn add_file(&self, file_id: &str, data: &[u8]) {
+		self.files
+			.borrow_mut()
+			.insert(file_id.into(), data.into());
+	}
+
+
+	fn push_update(&self, chat_id: ChatId, text: &str, message: Message) {
+		self.push(
+			Update::Message {
+				chat: Self::chat_info(chat_id),
+				text: Some(text.into()),
+				message: Some(message),
+			}
+		);
+	}
+
+
+	/// Everything sent since the last call.
+	pub fn take_sent(&self) -> Vec<Sent> {
+		self.sent
+			.borrow_mut()
+			.drain(..)
+			.collect()
+	}
+
+
+	/// The text of the messages sent since the last call.
+	pub fn take_texts(&self) -> Vec<String> {
+		self
+			.take_sent()
+			.into_iter()
+			.filter_map(
+				|sent| match sent {
+					Sent::Message { text, .. } => Some(text),
+					_ => None,
+				}
+			)
+			.collect()
+	}
+
+
+	fn chat_info(chat_id: ChatId) -> ChatInfo {
+		ChatInfo {
+			chat_id,
+			username: None,
+			title: Some("test".into()),
+			private: false,
+		}
+	}
+
+
+	fn record(&self, sent: Sent) {
+		self.sent
+			.borrow_mut()
+			.push(sent);
+	}
+
+
+	/// Messages with buttons are numbered from 1, so they can be pressed in tests.
+	fn next_message_id(&self) -> MessageId {
+		let id = self.last_message_id.get() + 1;
+
+		self.last_message_id.set(id);
+
+		MessageId::new(id)
+	}
+
+
+	fn check_reachable(&self, chat: ChatId) -> Result<(), BotError> {
+		if self.unreachable.borrow().contains(&chat) {
+			return Err(BotError::Fake);
+		}
+
+		Ok(())
+	}
+}
+
+
+#[async_trait(?Send)]
+impl Messenger for FakeMessenger {
+	/// Streams the updates injected so far, and then ends.
+	fn stream(&self) -> LocalBoxStream<'_, Update> {
+		let updates: Vec<_> = self.updates
+			.borrow_mut()
+			.drain(..)
+			.collect();
+
+		futures::stream
+			::iter(updates)
+			.boxed_local()
+	}
+
+
+	async fn send_message(&self, chat: ChatId, text: &str) -> Result<(), BotError> {
+		self.check_reachable(chat)?;
+
+		self.record(
+			Sent::Message {
+				chat,
+				text: text.into(),
+				buttons: Vec::new(),
+				reply_to: None,
+				mentions: Vec::new(),
+			}
+		);
+
+		Ok(())
+	}
+
+
+	async fn send_message_with_buttons(
+		&self,
+		chat: ChatId,
+		text: &str,
+		buttons: &[(&str, &str)],
+		reply_to: Option<MessageId>,
+		mentions: &[Mention],
+	) -> Result<MessageId, BotError> {
+		self.check_reachable(chat)?;
+
+		let buttons = buttons
+			.iter()
+			.map(
+				|(_, data)| String::from(*data)
+			)
+			.collect();
+
+		self.record(
+			Sent::Message {
+				chat,
+				text: text.into(),
+				buttons,
+				reply_to,
+				mentions: mentions.to_vec(),
+			}
+		);
+
+		Ok(
+			self.next_message_id()
+		)
+	}
+
+
+	async fn send_media_with_buttons(
+		&self,
+		chat: ChatId,
+		media: &Media,
+		caption: &str,
+		buttons: &[(&str, &str)],
+		reply_to: Option<MessageId>,
+		mentions: &[Mention],
+	) -> Result<MessageId, BotError> {
+		self.check_reachable(chat)?;
+
+		let buttons = buttons
+			.iter()
+			.map(
+				|(_, data)| String::from(*data)
+			)
+			.collect();
+
+		self.record(
+			Sent::Media {
+				chat,
+				media: media.clone(),
+				caption: caption.into(),
+				buttons,
+				reply_to,
+				mentions: mentions.to_vec(),
+			}
+		);
+
+		Ok(
+			self.next_message_id()
+		)
+	}
+
+
+	async fn edit_message(
+		&self,
+		chat: ChatId,
+		message: MessageId,
+		text: &str,
+	) -> Result<(), BotError> {
+		self.record(
+			Sent::Edit { chat, message, text: text.into() }
+		);
+
+		Ok(())
+	}
+
+
+	async fn edit_caption(
+		&self,
+		chat: ChatId,
+		message: MessageId,
+		caption: &str,
+	) -> Result<(), BotError> {
+		self.record(
+			Sent::EditCaption { chat, message, caption: caption.into() }
+		);
+
+		Ok(())
+	}
+
+
+	async fn send_file(&self, chat: ChatId, name: &str, data: Vec<u8>) -> Result<(), BotError> {
+		self.check_reachable(chat)?;
+
+		self.record(
+			Sent::File { chat, name: name.into(), data }
+		);
+
+		Ok(())
+	}
+
+
+	async fn download_file(&self, file_id: &str) -> Result<Vec<u8>, BotError> {
+		self.files
+			.borrow()
+			.get(file_id)
+			.cloned()
+			.ok_or(BotError::FileUnavailable)
+	}
+
+
+	async fn answer_callback(&self, _: &CallbackQueryId, text: &str) -> Result<(), BotError> {
+		self.record(
+			Sent::Answer { text: text.into() }
+		);
+
+		Ok(())
+	}
+
+
+	async fn chat_administrators(&self, chat: ChatId) -> Result<Box<[Administrator]>, BotError> {
+		let administrators = self.administrators
+			.borrow()
+			.get(&chat)
+			.cloned()
+			.unwrap_or_default();
+
+		Ok(
+			administrators.into_boxed_slice()
+		)
+	}
+
+
+	async fn leave_chat(&self, chat: ChatId) -> Result<(), BotError> {
+		self.record(
+			Sent::Left(chat)
+		);
+
+		Ok(())
+	}
+}
@@ -1,12 +1,20 @@
 use thiserror::Error;
 
+use chrono::{DateTime, TimeZone, Utc};
+
 use telegram_bot::{
+	CallbackQuery,
+	CallbackQueryId,
 	ChatId,
 	Message as Msg,
 	MessageChat,
+	MessageEntityKind,
+	MessageId,
 	MessageKind,
+	MessageOrChannelPost,
 	UpdateKind,
 	User,
+	UserId,
 };
 
 
@@ -37,14 +45,246 @@ impl UpdateError {
 }
 
 
+/// A message that another one replies to.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Reply {
+	pub message_id: MessageId,
+	/// Its text or caption, if any.
+	pub text: Option<Box<str>>,
+	/// The user who sent it, unless it is a channel post.
+	pub author: Option<Mention>,
+}
+
+
+impl Reply {
+	fn from_message(message: &MessageOrChannelPost) -> Self {
+		let (message_id, kind, author) = match message {
+			MessageOrChannelPost::Message(message) => (
+				message.id,
+				&message.kind,
+				Some(
+					Mention::User {
+						id: message.from.id,
+						name: message.from.first_name
+							.as_str()
+							.into(),
+					}
+				)
+			),
+			MessageOrChannelPost::ChannelPost(post) => (post.id, &post.kind, None),
+		};
+
+		Self {
+			message_id,
+			text: message_text(kind).map(Into::into),
+			author,
+		}
+	}
+}
+
+
+/// A user to be notified by a message.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Mention {
+	/// Mentioned by `@username`.
+	Username(Box<str>),
+	/// A user without a username, mentioned by name.
+	User {
+		id: UserId,
+		name: Box<str>,
+	},
+}
+
+
+impl Mention {
+	/// The mentions in a message's text or caption.
+	fn from_kind(kind: &MessageKind) -> Box<[Self]> {
+		let (text, entities) = match kind {
+			MessageKind::Text { data, entities } => (data, entities),
+			MessageKind::Photo { caption: Some(caption), .. } => return Self::from_caption(caption),
+			MessageKind::Document { caption: Some(caption), .. } => return Self::from_caption(caption),
+			_ => return Box::default(),
+		};
+
+		// Entities are delimited in UTF-16 code units.
+		let text: Vec<u16> = text
+			.encode_utf16()
+			.collect();
+
+		let span = |offset: i64, length: i64| {
+			let start = offset as usize;
+			let end = start + length as usize;
+
+			text
+				.get(start .. end)
+				.and_then(
+					|span| String::from_utf16(span).ok()
+				)
+		};
+
+		entities
+			.iter()
+			.filter_map(
+				|entity| match &entity.kind {
+					MessageEntityKind::Mention => {
+						let mention = span(entity.offset, entity.length)?;
+
+						Some(
+							Self::Username(
+								mention
+									.trim_start_matches('@')
+									.into()
+							)
+						)
+					},
+
+					MessageEntityKind::TextMention(user) => Some(
+						Self::User {
+							id: user.id,
+							name: span(entity.offset, entity.length)
+								.unwrap_or_else(
+									|| user.first_name.clone()
+								)
+								.into(),
+						}
+					),
+
+					_ => None,
+				}
+			)
+			.collect()
+	}
+
+
+	/// The usernames mentioned in a caption. telegram-bot drops the entities of captions, so
+	/// users without a username can't be found in them.
+	fn from_caption(caption: &str) -> Box<[Self]> {
+		caption
+			.split_whitespace()
+			.filter_map(
+				|word| word.strip_prefix('@')
+			)
+			.map(
+				|username| username.trim_end_matches(
+					|c: char| !(c.is_ascii_alphanumeric() || c == '_')
+				)
+			)
+			.filter(
+				|username| !username.is_empty()
+			)
+			.map(
+				|username| Self::Username(username.into())
+			)
+			.collect()
+	}
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MediaKind {
+	Photo,
+	Document,
+}
+
+
+/// A file already uploaded to Telegram, which can be sent again by its id.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Media {
+	pub kind: MediaKind,
+	pub file_id: Box<str>,
+}
+
+
+impl Media {
+	fn from_kind(kind: &MessageKind) -> Option<Self> {
+		match kind {
+			MessageKind::Photo { data, .. } => {
+				// Telegram sends every size of the photo, so pick the largest.
+				let photo = data
+					.iter()
+					.max_by_key(
+						|photo| photo.width * photo.height
+					)?;
+
+				Some(
+					Self {
+						kind: MediaKind::Photo,
+						file_id: photo.file_id.as_str().into(),
+					}
+				)
+			},
+
+			MessageKind::Document { data, .. } => Some(
+				Self {
+					kind: MediaKind::Document,
+					file_id: data.file_id.as_str().into(),
+				}
+			),
+
+			_ => None,
+		}
+	}
+}
+
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Message {
 	Text {
+		user_id: UserId,
 		nickname: Box<str>,
 		username: Option<Box<str>>,
 		chat_id: ChatId,
+		/// When the message was sent.
+		date: DateTime<Utc>,
 		text: Box<str>,
-	}
+		/// The users mentioned in the text.
+		mentions: Box<[Mention]>,
+		/// The message this one replies to, if any.
+		reply_to: Option<Reply>,
+	},
+	/// A photo or document, captioned with text addressed to the bot.
+	Media {
+		user_id: UserId,
+		nickname: Box<str>,
+		username: Option<Box<str>>,
+		chat_id: ChatId,
+		/// When the message was sent.
+		date: DateTime<Utc>,
+		caption: Box<str>,
+		media: Media,
+		/// The users mentioned in the caption.
+		mentions: Box<[Mention]>,
+		/// The message this one replies to, if any.
+		reply_to: Option<Reply>,
+	},
+	/// An iCalendar document sent to the bot without a command, to import its events.
+	Calendar {
+		user_id: UserId,
+		nickname: Box<str>,
+		username: Option<Box<str>>,
+		chat_id: ChatId,
+		/// When the message was sent.
+		date: DateTime<Utc>,
+		media: Media,
+	},
+	/// A button of a message sent by the bot was pressed.
+	Callback {
+		query_id: CallbackQueryId,
+		user_id: UserId,
+		nickname: Box<str>,
+		username: Option<Box<str>>,
+		chat_id: ChatId,
+		message_id: MessageId,
+		/// The text or caption of the message containing the button.
+		message_text: Box<str>,
+		/// The media of the message containing the button, if any.
+		media: Option<Media>,
+		/// The users mentioned in the message containing the button.
+		mentions: Box<[Mention]>,
+		/// The message that the one containing the button replies to, if any.
+		reply_to: Option<MessageId>,
+		data: Box<str>,
+	},
 }
 
 
@@ -89,31 +329,85 @@ impl Message {
 	}
 
 
+	pub fn chat_id(&self) -> ChatId {
+		match self {
+			Self::Text { chat_id, .. } => *chat_id,
+			Self::Media { chat_id, .. } => *chat_id,
+			Self::Calendar { chat_id, .. } => *chat_id,
+			Self::Callback { chat_id, .. } => *chat_id,
+		}
+	}
+
+
+	/// The user who sent the message, or pressed the button.
+	pub fn user_id(&self) -> UserId {
+		match self {
+			Self::Text { user_id, .. } => *user_id,
+			Self::Media { user_id, .. } => *user_id,
+			Self::Calendar { user_id, .. } => *user_id,
+			Self::Callback { user_id, .. } => *user_id,
+		}
+	}
+
+
 	pub fn from_update(bot_username: &str, update: UpdateKind) -> Result<Self, UpdateError> {
 		match update {
-			UpdateKind::Message(
-				Msg {
-					kind: MessageKind::Text {
-						data: text,
-						..
-					},
+			UpdateKind::Message(message) => Self::from_message(bot_username, message),
+
+			UpdateKind::CallbackQuery(
+				CallbackQuery {
+					id: query_id,
 					from: User {
+						id: user_id,
 						first_name: nickname,
 						username,
 						..
 					},
-					chat,
+					message: Some(message),
+					data: Some(data),
 					..
 				}
 			) => {
-				let text = Self::parse_text(bot_username, text, &chat)?;
+				let (chat_id, message_id, kind, reply_to_message) = match &message {
+					MessageOrChannelPost::Message(message) => (
+						message.chat.id(),
+						message.id,
+						&message.kind,
+						&message.reply_to_message
+					),
+					MessageOrChannelPost::ChannelPost(post) => (
+						post.chat.id.into(),
+						post.id,
+						&post.kind,
+						&post.reply_to_message
+					),
+				};
+
+				let reply_to = reply_to_message
+					.as_ref()
+					.map(
+						|message| Reply::from_message(message).message_id
+					);
+
+				let message_text = message_text(kind).unwrap_or("");
+
+				let media = Media::from_kind(kind);
+
+				let mentions = Mention::from_kind(kind);
 
 				Ok(
-					Self::Text {
+					Self::Callback {
+						query_id,
+						user_id,
 						nickname: nickname.into(),
 						username: username.map(Into::into),
-						chat_id: chat.id(),
-						text
+						chat_id,
+						message_id,
+						message_text: message_text.into(),
+						media,
+						mentions,
+						reply_to,
+						data: data.into(),
 					}
 				)
 			},
@@ -127,7 +421,7 @@ impl Message {
 
 	pub fn log(&self) {
 		match self {
-			Self::Text { nickname, username, chat_id, text } => {
+			Self::Text { nickname, username, chat_id, text, .. } => {
 				log::info!(
 					"Message from {} ({}) in {:?}: {}",
 					nickname,
@@ -138,10 +432,133 @@ impl Message {
 					text
 				);
 			}
+
+			Self::Media { nickname, username, chat_id, caption, media, .. } => {
+				log::info!(
+					"{:?} from {} ({}) in {:?}: {}",
+					media.kind,
+					nickname,
+					username
+						.as_deref()
+						.unwrap_or("?"),
+					chat_id,
+					caption
+				);
+			}
+
+			Self::Calendar { nickname, username, chat_id, .. } => {
+				log::info!(
+					"Calendar from {} ({}) in {:?}",
+					nickname,
+					username
+						.as_deref()
+						.unwrap_or("?"),
+					chat_id
+				);
+			}
+
+			Self::Callback { nickname, username, chat_id, data, .. } => {
+				log::info!(
+					"Callback from {} ({}) in {:?}: {}",
+					nickname,
+					username
+						.as_deref()
+						.unwrap_or("?"),
+					chat_id,
+					data
+				);
+			}
 		}
 	}
 
 
+	/// A text message, a photo or document with a caption, or a calendar.
+	fn from_message(bot_username: &str, message: Msg) -> Result<Self, UpdateError> {
+		let calendar = is_calendar_upload(bot_username, &message);
+
+		let text = match message_text(&message.kind) {
+			_ if calendar => Box::default(),
+			Some(text) => Self::parse_text(bot_username, text, &message.chat)?,
+			None => return Err(
+				UpdateError::Unrelated(
+					UpdateKind::Message(message)
+				)
+			),
+		};
+
+		let Msg {
+			kind,
+			from: User {
+				id: user_id,
+				first_name: nickname,
+				username,
+				..
+			},
+			chat,
+			date,
+			reply_to_message,
+			..
+		} = message;
+
+		// Groups address the bot by mentioning it, which isn't a mention of a user.
+		let mentions = Mention::from_kind(&kind)
+			.into_vec()
+			.into_iter()
+			.filter(
+				|mention| !matches!(
+					mention,
+					Mention::Username(username) if username.eq_ignore_ascii_case(bot_username)
+				)
+			)
+			.collect();
+
+		let nickname = nickname.into();
+		let username = username.map(Into::into);
+		let chat_id = chat.id();
+		let date = Utc.timestamp(date, 0);
+
+		let reply_to = reply_to_message.map(
+			|message| Reply::from_message(&message)
+		);
+
+		let message = match Media::from_kind(&kind) {
+			Some(media) if calendar => Self::Calendar {
+				user_id,
+				nickname,
+				username,
+				chat_id,
+				date,
+				media,
+			},
+
+			Some(media) => Self::Media {
+				user_id,
+				nickname,
+				username,
+				chat_id,
+				date,
+				caption: text,
+				media,
+				mentions,
+				reply_to,
+			},
+
+			None => Self::Text {
+				user_id,
+				nickname,
+				username,
+				chat_id,
+				date,
+				text,
+				mentions,
+				reply_to,
+			},
+		};
+
+		Ok(message)
+	}
+
+
 	fn parse_text<S>(
 		username: &str,
 		text: S,
@@ -181,3 +598,39 @@ impl Message {
 		}
 	}
 }
+
+
+/// Whether the message is an iCalendar document sent to the bot without a command:
+/// uncaptioned in a private chat, or captioned with just the bot's mention in a group.
+fn is_calendar_upload(bot_username: &str, message: &Msg) -> bool {
+	let (document, caption) = match &message.kind {
+		MessageKind::Document { data, caption } => (data, caption.as_deref().map(str::trim)),
+		_ => return false,
+	};
+
+	let is_calendar = document.mime_type.as_deref() == Some("text/calendar")
+		|| document.file_name
+			.as_deref()
+			.map_or(false, |name| name.to_lowercase().ends_with(".ics"));
+
+	let addressed = match (&message.chat, caption) {
+		(MessageChat::Private(_), None) => true,
+		(MessageChat::Group(_), Some(caption)) | (MessageChat::Supergroup(_), Some(caption)) => {
+			caption.strip_prefix('@') == Some(bot_username)
+		}
+		_ => false,
+	};
+
+	is_calendar && addressed
+}
+
+
+/// The text of a message, or the caption of a photo or document.
+fn message_text(kind: &MessageKind) -> Option<&str> {
+	match kind {
+		MessageKind::Text { data, .. } => Some(data),
+		MessageKind::Photo { caption, .. } => caption.as_deref(),
+		MessageKind::Document { caption, .. } => caption.as_deref(),
+		_ => None,
+	}
+}
@@ -1,296 +1,174 @@
+mod auth;
 mod message;
+mod telegram;
+mod webhook;
+#[cfg(test)]
+pub mod fake;
+#[cfg(test)]
+mod tests;
 
-use std::{
-	cell::RefCell,
-	collections::HashSet,
-	fmt::{self, Debug},
-	iter::FromIterator
-};
+use async_trait::async_trait;
 
-use futures::{Stream, StreamExt};
+use futures::stream::LocalBoxStream;
+
+use thiserror::Error;
 
-use telegram_bot::{
-	Api,
-	CanLeaveChat,
-	CanSendMessage,
-	Channel,
-	MessageChat,
-	MessageOrChannelPost,
-	UpdateKind,
-};
 pub use telegram_bot::{
+	CallbackQueryId,
 	ChatId,
-	Error as BotError
-};
-
-pub use message::Message;
-use crate::{
-	config,
-	db::{
-		Db,
-		Error as DbError,
-		models::trusted_chats::NewTrustedChat,
-		types::ChatId as DbChatId,
-	}
+	MessageId,
+	UserId,
 };
 
-
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-struct ChatInfo<'a> {
-	chat_id: ChatId,
-	username: Option<&'a str>,
-	title: Option<&'a str>,
+pub use auth::Authenticator;
+pub use message::{Media, MediaKind, Mention, Message, Reply};
+pub use telegram::Bot;
+pub use webhook::Webhook;
+
+
+/// Separates the mentioned users from the text of a message.
+pub const MENTION_MARKER: &str = "\n\n🔔 ";
+
+
+#[derive(Debug, Error)]
+pub enum BotError {
+	#[error("telegram error: {0}")]
+	Telegram(#[from] telegram_bot::Error),
+	#[error("webhook error: {0}")]
+	Webhook(#[from] hyper::Error),
+	#[error("download error: {0}")]
+	Download(reqwest::Error),
+	/// Telegram gave no path to download the file from, e.g. because it is too big.
+	#[error("file unavailable")]
+	FileUnavailable,
+	/// A failure injected by the fake messenger.
+	#[cfg(test)]
+	#[error("fake error")]
+	Fake,
 }
 
 
-impl<'a> ChatInfo<'a> {
-	pub fn from_update(update_kind: &'a UpdateKind) -> Option<Self> {
-		match update_kind {
-			UpdateKind::Message(message)  => Some(Self::from(&message.chat)),
-			UpdateKind::ChannelPost(post) => Some(Self::from(&post.chat)),
-			_ => None,
-		}
+impl From<reqwest::Error> for BotError {
+	/// Download URLs contain the bot's key, so they're left out of the error.
+	fn from(error: reqwest::Error) -> Self {
+		BotError::Download(
+			error.without_url()
+		)
 	}
 }
 
 
-impl<'a> From<&'a MessageChat> for ChatInfo<'a> {
-	fn from(chat: &'a MessageChat) -> Self {
-		let (username, title) =  match chat {
-			MessageChat::Private(user) => (
-				user.username.as_deref(),
-				Some(
-					user.first_name.as_ref()
-				)
-			),
-			MessageChat::Group(group) => (
-				None,
-				Some(
-					group.title.as_ref()
-				)
-			),
-			MessageChat::Supergroup(supergroup) => (
-				supergroup.username.as_deref(),
-				Some(
-					supergroup.title.as_ref()
-				)
-			),
-			MessageChat::Unknown(raw) => (
-				raw.username.as_deref(),
-				raw.title.as_deref()
-			)
-		};
-
-		Self {
-			chat_id: chat.id(),
-			username,
-			title
-		}
-	}
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ChatInfo {
+	pub chat_id: ChatId,
+	pub username: Option<Box<str>>,
+	pub title: Option<Box<str>>,
+	/// Whether it is a private chat with a user.
+	pub private: bool,
 }
 
 
-impl<'a> From<&'a Channel> for ChatInfo<'a> {
-	fn from(channel: &'a Channel) -> Self {
-		Self {
-			chat_id: channel.id.into(),
-			username: channel.username.as_deref(),
-			title: Some(
-				channel.title.as_ref()
-			)
-		}
-	}
+/// A user who administers a chat.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Administrator {
+	pub user_id: UserId,
+	/// Whether they created the chat.
+	pub owner: bool,
 }
 
 
-#[derive(Clone)]
-pub struct Bot<'a> {
-	api: Api,
-	db: &'a Db,
-	username: &'a str,
-	authentication: &'a config::Authentication,
-	// This needs to be a refcell so we can mutate it while streaming. Just be sure that it
-	// won't be borrowed outside of the `validate_chat` method, and all shall be fine.
-	trusted_chats: RefCell<HashSet<ChatId>>,
+/// An update from a chat, before checking whether the chat is trusted.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Update {
+	/// The bot was added to a chat.
+	Joined(ChatInfo),
+	Message {
+		chat: ChatInfo,
+		/// The raw text of the message, if any, which may be the password.
+		text: Option<Box<str>>,
+		/// The parsed message, if it is addressed to the bot.
+		message: Option<Message>,
+	},
 }
 
 
-impl<'a> Bot<'a> {
-	pub fn new<K>(
-		key: K,
-		db: &'a Db,
-		username: &'a str,
-		authentication: &'a config::Authentication
-	) -> Result<Self, DbError>
-	where
-		K: AsRef<str>,
-	{
-		let key = key.as_ref();
-
-		let trusted_chats = db.trusted_chat_ids()?;
-
-		let trusted_chats = HashSet::from_iter(
-			trusted_chats
-				.iter()
-				.map(
-					|chat_id| chat_id.0
-				)
-		);
-
-		Ok(
-			Self {
-				api: Api::new(key),
-				db,
-				username,
-				authentication,
-				trusted_chats: RefCell::new(trusted_chats)
-			}
-		)
+impl Update {
+	pub fn chat(&self) -> &ChatInfo {
+		match self {
+			Update::Joined(chat) => chat,
+			Update::Message { chat, .. } => chat,
+		}
 	}
+}
 
 
-	pub fn stream(&'a self) -> impl Stream<Item = Message> + 'a {
-		self.api
-			.stream()
-			.filter_map(
-				|update| async move {
-					update
-						.map_err(
-							|error| log::error!("Bot update error: {}", error)
-						)
-						.ok()
-				}
-			)
-			.filter_map(
-				move |update| async move {
-					self
-						.validate_chat(&update.kind)
-						.await
-						.then(|| update)
-				}
-			)
-			.filter_map(
-				move |update| async move {
-					match Message::from_update(self.username, update.kind) {
-						Ok(message) => {
-							message.log();
-							Some(message)
-						},
-						Err(error) => {
-							if !error.is_unrelated() {
-								error.log();
-							}
-							None
-						}
-					}
-				}
-			)
-	}
-
+/// A messaging service the bot talks through.
+#[async_trait(?Send)]
+pub trait Messenger {
+	/// Updates from every chat the bot is in.
+	fn stream(&self) -> LocalBoxStream<'_, Update>;
 
-	pub async fn leave_chat(&self, chat_id: ChatId) {
-		let result = self.api
-			.send(
-				chat_id.leave()
-			)
-			.await;
 
-		if let Err(error) = result {
-			log::warn!("Failed to leave chat {}: {}", chat_id, error);
-		}
-	}
+	async fn send_message(&self, chat: ChatId, text: &str) -> Result<(), BotError>;
 
 
-	pub async fn send_message<T>(
+	/// Send a message with a row of inline buttons, given as pairs of label and callback
+	/// data, possibly in reply to another message, and mentioning the given users. Returns
+	/// the sent message.
+	async fn send_message_with_buttons(
 		&self,
 		chat: ChatId,
-		text: T,
-	) -> Result<MessageOrChannelPost, BotError>
-	where
-		T: AsRef<str>
-	{
-		let text = text.as_ref();
-
-		self.api
-			.send(
-				chat.text(text)
-			)
-			.await
-	}
+		text: &str,
+		buttons: &[(&str, &str)],
+		reply_to: Option<MessageId>,
+		mentions: &[Mention],
+	) -> Result<MessageId, BotError>;
 
 
-	async fn validate_chat(&self, update_kind: &UpdateKind) -> bool {
-		if let Some(chat_info) = ChatInfo::from_update(update_kind) {
-			let ChatInfo { chat_id, username, title } = chat_info;
-
-			if Message::is_new_chat(self.username, update_kind) {
-				log::warn!("I've been added to a new chat: {:?}", chat_info);
-				log::info!("Requesting password...");
-
-				let result = self
-					.send_message(chat_id, &self.authentication.prompt)
-					.await;
+	/// Send a photo or document with a caption, like `send_message_with_buttons`.
+	async fn send_media_with_buttons(
+		&self,
+		chat: ChatId,
+		media: &Media,
+		caption: &str,
+		buttons: &[(&str, &str)],
+		reply_to: Option<MessageId>,
+		mentions: &[Mention],
+	) -> Result<MessageId, BotError>;
 
-				if let Err(error) = result {
-					log::warn!("Failed to send message to chat {:?}: {}", chat_info, error);
-				}
 
-				return false;
-			}
+	/// Replace the text of a message sent by the bot, removing its buttons.
+	async fn edit_message(
+		&self,
+		chat: ChatId,
+		message: MessageId,
+		text: &str,
+	) -> Result<(), BotError>;
 
-			let trusted = self.trusted_chats
-				.borrow()
-				.contains(&chat_id);
 
-			if !trusted {
-				match Message::get_text(update_kind) {
-					Some(password) if password == self.authentication.password.as_ref() => {
-						let result = self.db.new_trusted_chat(
-							&NewTrustedChat {
-								id: DbChatId(chat_id),
-								username,
-								title,
-							}
-						);
+	/// Replace the caption of a photo or document sent by the bot, removing its buttons.
+	async fn edit_caption(
+		&self,
+		chat: ChatId,
+		message: MessageId,
+		caption: &str,
+	) -> Result<(), BotError>;
 
-						if let Err(error) = result {
-							log::warn!("Failed to add trusted chat {:?}: {}", chat_info, error);
-							return false; // Don't update the cache if the DB update failed.
-						}
 
-						self.trusted_chats
-							.borrow_mut()
-							.insert(chat_id);
+	/// Send a file uploaded from memory.
+	async fn send_file(&self, chat: ChatId, name: &str, data: Vec<u8>) -> Result<(), BotError>;
 
-						log::info!("Added trusted chat: {:?}", chat_info);
 
-						let result = self
-							.send_message(chat_id, &self.authentication.authorized)
-							.await;
+	/// The contents of a file sent to the bot.
+	async fn download_file(&self, file_id: &str) -> Result<Vec<u8>, BotError>;
 
-						if let Err(error) = result {
-							log::warn!("Failed to send message to chat {:?}: {}", chat_info, error);
-						}
-					},
 
-					_ => self.leave_chat(chat_id).await,
-				}
-			}
+	async fn answer_callback(&self, query: &CallbackQueryId, text: &str) -> Result<(), BotError>;
 
-			return trusted;
-		}
 
-		false
-	}
-}
+	/// The chat's administrators. Private chats have none.
+	async fn chat_administrators(&self, chat: ChatId) -> Result<Box<[Administrator]>, BotError>;
 
 
-impl<'a> Debug for Bot<'a> {
-	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-		write!(
-			f,
-			"Bot {{{:?}}}",
-			self.trusted_chats
-		)
-	}
+	async fn leave_chat(&self, chat: ChatId) -> Result<(), BotError>;
 }
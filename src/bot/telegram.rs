@@ -0,0 +1,524 @@
+use std::{
+	borrow::Cow,
+	fmt::{self, Debug, Write},
+};
+
+use async_trait::async_trait;
+
+use futures::{StreamExt, stream::LocalBoxStream};
+
+use telegram_bot::{
+	Api,
+	CanAnswerCallbackQuery,
+	CanLeaveChat,
+	CanSendMessage,
+	Channel,
+	ChatMemberStatus,
+	EditMessageCaption,
+	EditMessageText,
+	GetChatAdministrators,
+	GetFile,
+	InlineKeyboardButton,
+	InlineKeyboardMarkup,
+	InputFileRef,
+	InputFileUpload,
+	MessageChat,
+	MessageOrChannelPost,
+	ParseMode,
+	SendDocument,
+	SendPhoto,
+	UpdateKind,
+};
+
+use super::{
+	Administrator,
+	BotError,
+	CallbackQueryId,
+	ChatId,
+	ChatInfo,
+	Media,
+	MediaKind,
+	Mention,
+	Message,
+	MessageId,
+	Messenger,
+	Update,
+	Webhook,
+	MENTION_MARKER,
+};
+
+
+impl ChatInfo {
+	fn from_update(update_kind: &UpdateKind) -> Option<Self> {
+		match update_kind {
+			UpdateKind::Message(message)  => Some(Self::from(&message.chat)),
+			UpdateKind::ChannelPost(post) => Some(Self::from(&post.chat)),
+			UpdateKind::CallbackQuery(query) => match query.message.as_ref()? {
+				MessageOrChannelPost::Message(message) => Some(Self::from(&message.chat)),
+				MessageOrChannelPost::ChannelPost(post) => Some(Self::from(&post.chat)),
+			},
+			_ => None,
+		}
+	}
+}
+
+
+impl<'a> From<&'a MessageChat> for ChatInfo {
+	fn from(chat: &'a MessageChat) -> Self {
+		let (username, title) =  match chat {
+			MessageChat::Private(user) => (
+				user.username.as_deref(),
+				Some(
+					user.first_name.as_ref()
+				)
+			),
+			MessageChat::Group(group) => (
+				None,
+				Some(
+					group.title.as_ref()
+				)
+			),
+			MessageChat::Supergroup(supergroup) => (
+				supergroup.username.as_deref(),
+				Some(
+					supergroup.title.as_ref()
+				)
+			),
+			MessageChat::Unknown(raw) => (
+				raw.username.as_deref(),
+				raw.title.as_deref()
+			)
+		};
+
+		Self {
+			chat_id: chat.id(),
+			username: username.map(Into::into),
+			title: title.map(Into::into),
+			private: matches!(chat, MessageChat::Private(_)),
+		}
+	}
+}
+
+
+impl<'a> From<&'a Channel> for ChatInfo {
+	fn from(channel: &'a Channel) -> Self {
+		Self {
+			chat_id: channel.id.into(),
+			username: channel.username
+				.as_deref()
+				.map(Into::into),
+			title: Some(
+				channel.title.as_str().into()
+			),
+			private: false,
+		}
+	}
+}
+
+
+/// The Telegram messenger.
+pub struct Bot<'a> {
+	api: Api,
+	/// Files are downloaded from an url containing the key.
+	key: Box<str>,
+	username: &'a str,
+	/// Where updates are received from, when not polling.
+	webhook: Option<Webhook>,
+}
+
+
+impl<'a> Bot<'a> {
+	pub fn new<K>(key: K, username: &'a str, webhook: Option<Webhook>) -> Self
+	where
+		K: AsRef<str>,
+	{
+		let key = key.as_ref();
+
+		Self {
+			api: Api::new(key),
+			key: key.into(),
+			username,
+			webhook,
+		}
+	}
+
+
+	/// A single row of callback buttons.
+	fn keyboard(buttons: &[(&str, &str)]) -> InlineKeyboardMarkup {
+		let mut keyboard = InlineKeyboardMarkup::new();
+
+		keyboard.add_row(
+			buttons
+				.iter()
+				.map(
+					|(label, data)| InlineKeyboardButton::callback(label, data)
+				)
+				.collect()
+		);
+
+		keyboard
+	}
+
+
+	/// The text followed by the mentioned users, if any. Users without a username can only
+	/// be mentioned by a link, so the text is then sent as HTML.
+	fn with_mentions<'t>(text: &'t str, mentions: &[Mention]) -> (Cow<'t, str>, Option<ParseMode>) {
+		if mentions.is_empty() {
+			return (Cow::Borrowed(text), None);
+		}
+
+		let mut html = escape_html(text);
+
+		html.push_str(MENTION_MARKER);
+
+		for (i, mention) in mentions.iter().enumerate() {
+			if i > 0 {
+				html.push(' ');
+			}
+
+			match mention {
+				Mention::Username(username) => write!(html, "@{}", username),
+
+				Mention::User { id, name } => write!(
+					html,
+					"<a href=\"tg://user?id={}\">{}</a>",
+					id,
+					escape_html(name)
+				),
+			}
+				.expect("write should not fail on string");
+		}
+
+		(Cow::Owned(html), Some(ParseMode::Html))
+	}
+
+
+	fn message_id(sent: MessageOrChannelPost) -> MessageId {
+		match sent {
+			MessageOrChannelPost::Message(message) => message.id,
+			MessageOrChannelPost::ChannelPost(post) => post.id,
+		}
+	}
+
+
+	fn update(&self, update_kind: UpdateKind) -> Option<Update> {
+		let chat = ChatInfo::from_update(&update_kind)?;
+
+		if Message::is_new_chat(self.username, &update_kind) {
+			return Some(
+				Update::Joined(chat)
+			);
+		}
+
+		let text = Message
+			::get_text(&update_kind)
+			.map(Into::into);
+
+		let message = match Message::from_update(self.username, update_kind) {
+			Ok(message) => Some(message),
+			Err(error) => {
+				if !error.is_unrelated() {
+					error.log();
+				}
+				None
+			}
+		};
+
+		Some(
+			Update::Message { chat, text, message }
+		)
+	}
+}
+
+
+#[async_trait(?Send)]
+impl<'a> Messenger for Bot<'a> {
+	fn stream(&self) -> LocalBoxStream<'_, Update> {
+		let updates = match &self.webhook {
+			Some(webhook) => webhook.stream(),
+
+			None => self.api
+				.stream()
+				.filter_map(
+					|update| async move {
+						update
+							.map_err(
+								|error| log::error!("Bot update error: {}", error)
+							)
+							.ok()
+					}
+				)
+				.boxed_local(),
+		};
+
+		updates
+			.filter_map(
+				move |update| async move {
+					self.update(update.kind)
+				}
+			)
+			.boxed_local()
+	}
+
+
+	async fn send_message(&self, chat: ChatId, text: &str) -> Result<(), BotError> {
+		self.api
+			.send(
+				chat.text(text)
+			)
+			.await
+			.map(
+				|_| ()
+			)
+			.map_err(BotError::Telegram)
+	}
+
+
+	async fn send_message_with_buttons(
+		&self,
+		chat: ChatId,
+		text: &str,
+		buttons: &[(&str, &str)],
+		reply_to: Option<MessageId>,
+		mentions: &[Mention],
+	) -> Result<MessageId, BotError> {
+		let keyboard = Self::keyboard(buttons);
+
+		let (text, parse_mode) = Self::with_mentions(text, mentions);
+
+		let mut request = chat.text(text.as_ref());
+
+		request.reply_markup(keyboard);
+
+		if let Some(parse_mode) = parse_mode {
+			request.parse_mode(parse_mode);
+		}
+
+		if let Some(message) = reply_to {
+			request.reply_to(message);
+		}
+
+		self.api
+			.send(request)
+			.await
+			.map(Self::message_id)
+			.map_err(BotError::Telegram)
+	}
+
+
+	async fn send_media_with_buttons(
+		&self,
+		chat: ChatId,
+		media: &Media,
+		caption: &str,
+		buttons: &[(&str, &str)],
+		reply_to: Option<MessageId>,
+		mentions: &[Mention],
+	) -> Result<MessageId, BotError> {
+		let keyboard = Self::keyboard(buttons);
+
+		let (caption, parse_mode) = Self::with_mentions(caption, mentions);
+
+		let file = InputFileRef::new(
+			media.file_id.as_ref()
+		);
+
+		// The requests share no trait for their options.
+		let result = match media.kind {
+			MediaKind::Photo => {
+				let mut request = SendPhoto::new(chat, file);
+
+				request
+					.caption(caption.as_ref())
+					.reply_markup(keyboard);
+
+				if let Some(parse_mode) = parse_mode {
+					request.parse_mode(parse_mode);
+				}
+
+				if let Some(message) = reply_to {
+					request.reply_to(message);
+				}
+
+				self.api
+					.send(request)
+					.await
+			}
+
+			MediaKind::Document => {
+				let mut request = SendDocument::new(chat, file);
+
+				request
+					.caption(caption.as_ref())
+					.reply_markup(keyboard);
+
+				if let Some(parse_mode) = parse_mode {
+					request.parse_mode(parse_mode);
+				}
+
+				if let Some(message) = reply_to {
+					request.reply_to(message);
+				}
+
+				self.api
+					.send(request)
+					.await
+			}
+		};
+
+		result
+			.map(Self::message_id)
+			.map_err(BotError::Telegram)
+	}
+
+
+	async fn edit_message(
+		&self,
+		chat: ChatId,
+		message: MessageId,
+		text: &str,
+	) -> Result<(), BotError> {
+		self.api
+			.send(
+				EditMessageText::new(chat, message, text)
+			)
+			.await
+			.map(
+				|_| ()
+			)
+			.map_err(BotError::Telegram)
+	}
+
+
+	async fn edit_caption(
+		&self,
+		chat: ChatId,
+		message: MessageId,
+		caption: &str,
+	) -> Result<(), BotError> {
+		self.api
+			.send(
+				EditMessageCaption::new(chat, message, caption)
+			)
+			.await
+			.map(
+				|_| ()
+			)
+			.map_err(BotError::Telegram)
+	}
+
+
+	async fn send_file(&self, chat: ChatId, name: &str, data: Vec<u8>) -> Result<(), BotError> {
+		self.api
+			.send(
+				SendDocument::new(
+					chat,
+					InputFileUpload::with_data(data, name)
+				)
+			)
+			.await
+			.map(
+				|_| ()
+			)
+			.map_err(BotError::Telegram)
+	}
+
+
+	async fn download_file(&self, file_id: &str) -> Result<Vec<u8>, BotError> {
+		let file = self.api
+			.send(
+				GetFile::new(file_id)
+			)
+			.await
+			.map_err(BotError::Telegram)?;
+
+		let url = file
+			.get_url(&self.key)
+			.ok_or(BotError::FileUnavailable)?;
+
+		let data = reqwest
+			::get(&url)
+			.await?
+			.error_for_status()?
+			.bytes()
+			.await?;
+
+		Ok(
+			data.to_vec()
+		)
+	}
+
+
+	async fn answer_callback(&self, query: &CallbackQueryId, text: &str) -> Result<(), BotError> {
+		self.api
+			.send(
+				query.answer(text)
+			)
+			.await
+			.map(
+				|_| ()
+			)
+			.map_err(BotError::Telegram)
+	}
+
+
+	async fn chat_administrators(&self, chat: ChatId) -> Result<Box<[Administrator]>, BotError> {
+		let members = self.api
+			.send(
+				GetChatAdministrators::new(chat)
+			)
+			.await
+			.map_err(BotError::Telegram)?;
+
+		let administrators = members
+			.into_iter()
+			.filter_map(
+				|member| match member.status {
+					ChatMemberStatus::Creator => Some(
+						Administrator { user_id: member.user.id, owner: true }
+					),
+					ChatMemberStatus::Administrator => Some(
+						Administrator { user_id: member.user.id, owner: false }
+					),
+					_ => None,
+				}
+			)
+			.collect();
+
+		Ok(administrators)
+	}
+
+
+	async fn leave_chat(&self, chat: ChatId) -> Result<(), BotError> {
+		self.api
+			.send(
+				chat.leave()
+			)
+			.await
+			.map(
+				|_| ()
+			)
+			.map_err(BotError::Telegram)
+	}
+}
+
+
+impl<'a> Debug for Bot<'a> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(
+			f,
+			"Bot {{{:?}, {:?}}}",
+			self.username,
+			self.webhook
+		)
+	}
+}
+
+
+/// Escape text to be sent as HTML.
+fn escape_html(text: &str) -> String {
+	text
+		.replace('&', "&amp;")
+		.replace('<', "&lt;")
+		.replace('>', "&gt;")
+}
@@ -0,0 +1,17 @@
+use super::*;
+
+
+#[tokio::test]
+async fn test_download_error() {
+	let key = "123456:ABC-DEF1234ghIkl";
+
+	let error = reqwest
+		::get(format!("http://127.0.0.1:1/file/bot{}/documents/file_0.ics", key))
+		.await
+		.err()
+		.expect("download should fail");
+
+	let error = BotError::from(error).to_string();
+
+	assert!(!error.contains(key), "{}", error);
+}
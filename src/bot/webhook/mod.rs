@@ -0,0 +1,213 @@
+#[cfg(test)]
+mod tests;
+
+use std::{
+	cell::RefCell,
+	convert::Infallible,
+	fmt::{self, Debug},
+	net::SocketAddr,
+	sync::Arc,
+};
+
+use futures::{
+	StreamExt,
+	channel::mpsc::{self, UnboundedReceiver, UnboundedSender},
+	stream::{self, LocalBoxStream},
+};
+
+use hyper::{
+	Body,
+	Method,
+	Request,
+	Response,
+	Server,
+	StatusCode,
+	service::{make_service_fn, service_fn},
+};
+
+use super::BotError;
+use crate::config;
+
+
+/// The header Telegram sends the configured secret token in.
+const SECRET_TOKEN_HEADER: &str = "X-Telegram-Bot-Api-Secret-Token";
+
+
+/// Receives the updates Telegram posts to the configured address, instead of polling for
+/// them. Plain HTTP only, meant to sit behind a reverse proxy.
+pub struct Webhook {
+	address: SocketAddr,
+	// This needs to be a refcell so the stream can be taken from a shared reference. It is
+	// only borrowed while polling.
+	updates: RefCell<UnboundedReceiver<telegram_bot::Update>>,
+}
+
+
+impl Webhook {
+	/// Start listening in the background. Must be called within the runtime.
+	pub fn bind(config: &config::Webhook) -> Result<Self, BotError> {
+		let (sender, updates) = mpsc::unbounded();
+
+		let endpoint = Arc::new(
+			Endpoint::new(config, sender)
+		);
+
+		let make_service = make_service_fn(
+			move |_| {
+				let endpoint = endpoint.clone();
+
+				async move {
+					Ok::<_, Infallible>(
+						service_fn(
+							move |request| {
+								let endpoint = endpoint.clone();
+
+								async move {
+									Ok::<_, Infallible>(
+										endpoint.handle(request).await
+									)
+								}
+							}
+						)
+					)
+				}
+			}
+		);
+
+		let server = Server
+			::try_bind(&config.address)?
+			.serve(make_service);
+
+		let address = server.local_addr();
+
+		tokio::spawn(
+			async move {
+				if let Err(error) = server.await {
+					log::error!("Webhook server halted: {}", error);
+				}
+			}
+		);
+
+		Ok(
+			Self {
+				address,
+				updates: RefCell::new(updates),
+			}
+		)
+	}
+
+
+	/// The address actually listened on, which is only known after binding when the
+	/// configured port is 0.
+	pub fn address(&self) -> SocketAddr {
+		self.address
+	}
+
+
+	pub fn stream(&self) -> LocalBoxStream<'_, telegram_bot::Update> {
+		stream
+			::poll_fn(
+				move |context| self.updates
+					.borrow_mut()
+					.poll_next_unpin(context)
+			)
+			.boxed_local()
+	}
+}
+
+
+impl Debug for Webhook {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(
+			f,
+			"Webhook {{{}}}",
+			self.address
+		)
+	}
+}
+
+
+/// Validates posted updates, and forwards them to the webhook.
+struct Endpoint {
+	path: Box<str>,
+	secret_token: Box<str>,
+	updates: UnboundedSender<telegram_bot::Update>,
+}
+
+
+impl Endpoint {
+	fn new(config: &config::Webhook, updates: UnboundedSender<telegram_bot::Update>) -> Self {
+		Self {
+			path: config.path.clone(),
+			secret_token: config.secret_token.clone(),
+			updates,
+		}
+	}
+
+
+	async fn handle(&self, request: Request<Body>) -> Response<Body> {
+		if request.uri().path() != self.path.as_ref() {
+			return Self::respond(StatusCode::NOT_FOUND);
+		}
+
+		if request.method() != Method::POST {
+			return Self::respond(StatusCode::METHOD_NOT_ALLOWED);
+		}
+
+		let authorized = request
+			.headers()
+			.get(SECRET_TOKEN_HEADER)
+			.map_or(
+				false,
+				|token| constant_time_eq(token.as_bytes(), self.secret_token.as_bytes())
+			);
+
+		if !authorized {
+			log::warn!("Rejected webhook request with an invalid secret token");
+			return Self::respond(StatusCode::UNAUTHORIZED);
+		}
+
+		let body = match hyper::body::to_bytes(request.into_body()).await {
+			Ok(body) => body,
+			Err(error) => {
+				log::warn!("Failed to read webhook request: {}", error);
+				return Self::respond(StatusCode::BAD_REQUEST);
+			}
+		};
+
+		let update = match serde_json::from_slice(&body) {
+			Ok(update) => update,
+			Err(error) => {
+				log::warn!("Failed to parse webhook update: {}", error);
+				return Self::respond(StatusCode::BAD_REQUEST);
+			}
+		};
+
+		// Telegram retries updates that fail, so make it wait for the bot to come back.
+		if self.updates.unbounded_send(update).is_err() {
+			return Self::respond(StatusCode::SERVICE_UNAVAILABLE);
+		}
+
+		Self::respond(StatusCode::OK)
+	}
+
+
+	fn respond(status: StatusCode) -> Response<Body> {
+		let mut response = Response::new(Body::empty());
+		*response.status_mut() = status;
+		response
+	}
+}
+
+
+/// Compare secrets without leaking how much of them matched through timing.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+	a.len() == b.len()
+		&& a
+			.iter()
+			.zip(b)
+			.fold(
+				0,
+				|difference, (a, b)| difference | (a ^ b)
+			) == 0
+}
@@ -0,0 +1,129 @@
+use super::*;
+
+
+/// A text message update, as recorded from Telegram.
+const UPDATE: &str = r#"{
+	"update_id": 718203945,
+	"message": {
+		"message_id": 42,
+		"from": {
+			"id": 1234567,
+			"is_bot": false,
+			"first_name": "Tester",
+			"username": "tester",
+			"language_code": "pt-br"
+		},
+		"chat": {
+			"id": 1234567,
+			"first_name": "Tester",
+			"username": "tester",
+			"type": "private"
+		},
+		"date": 1614603600,
+		"text": "chora"
+	}
+}"#;
+
+
+fn endpoint() -> (Endpoint, UnboundedReceiver<telegram_bot::Update>) {
+	let config = config::Webhook {
+		address: ([127, 0, 0, 1], 0).into(),
+		path: "/telegram".into(),
+		secret_token: "secret".into(),
+	};
+
+	let (sender, updates) = mpsc::unbounded();
+
+	(Endpoint::new(&config, sender), updates)
+}
+
+
+fn request(method: Method, path: &str, token: Option<&str>, body: &str) -> Request<Body> {
+	let mut request = Request::builder()
+		.method(method)
+		.uri(path);
+
+	if let Some(token) = token {
+		request = request.header(SECRET_TOKEN_HEADER, token);
+	}
+
+	request
+		.body(Body::from(body.to_owned()))
+		.expect("invalid request")
+}
+
+
+#[tokio::test]
+async fn test_update() {
+	let (endpoint, mut updates) = endpoint();
+
+	let response = endpoint
+		.handle(
+			request(Method::POST, "/telegram", Some("secret"), UPDATE)
+		)
+		.await;
+
+	assert_eq!(response.status(), StatusCode::OK);
+
+	let update = updates
+		.try_next()
+		.expect("no update")
+		.expect("closed");
+
+	assert_eq!(update.id, 718203945);
+}
+
+
+#[tokio::test]
+async fn test_rejected() {
+	let (endpoint, mut updates) = endpoint();
+
+	let cases = [
+		(Method::POST, "/telegram", None, UPDATE, StatusCode::UNAUTHORIZED),
+		(Method::POST, "/telegram", Some("secreT"), UPDATE, StatusCode::UNAUTHORIZED),
+		(Method::POST, "/telegram", Some("secret!"), UPDATE, StatusCode::UNAUTHORIZED),
+		(Method::POST, "/", Some("secret"), UPDATE, StatusCode::NOT_FOUND),
+		(Method::GET, "/telegram", Some("secret"), "", StatusCode::METHOD_NOT_ALLOWED),
+		(Method::POST, "/telegram", Some("secret"), "{", StatusCode::BAD_REQUEST),
+	];
+
+	for (method, path, token, body, status) in cases.iter().cloned() {
+		let response = endpoint
+			.handle(
+				request(method, path, token, body)
+			)
+			.await;
+
+		assert_eq!(response.status(), status, "{} {} {:?}", path, body, token);
+	}
+
+	assert!(updates.try_next().is_err());
+}
+
+
+#[tokio::test]
+async fn test_bot_gone() {
+	let (endpoint, updates) = endpoint();
+
+	drop(updates);
+
+	let response = endpoint
+		.handle(
+			request(Method::POST, "/telegram", Some("secret"), UPDATE)
+		)
+		.await;
+
+	assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+}
+
+
+#[test]
+fn test_secret_token() {
+	let config = |token: &str| toml::from_str::<config::Webhook>(
+		&format!("address = '127.0.0.1:8443'\npath = '/telegram'\nsecret_token = '{}'", token)
+	);
+
+	assert!(config("").is_err());
+	assert!(config("secret").is_err());
+	assert!(config("0123456789abcdef").is_ok());
+}
@@ -0,0 +1,469 @@
+#[cfg(test)]
+mod tests;
+
+use std::{fs, str::FromStr};
+
+use anyhow::Context;
+
+use thiserror::Error;
+
+use chrono::{Datelike, NaiveDateTime, Utc};
+
+use chrono_tz::Tz;
+
+use crate::{
+	bot,
+	config::Config,
+	db::{
+		Db,
+		models::reminders::{NewReminder, Reminder},
+		types::{ChatId, DueTimestamp, Recurrence},
+	},
+	export::{Format, ics, json},
+};
+
+
+pub const DEFAULT_CONFIG_FILE: &str = "./config.toml";
+
+pub const USAGE: &str = "\
+usage: reminder-bot [--config <path>] [<command>]
+
+commands:
+  run                    start the bot, the default
+  help                   print this message
+  check-schema           list the migrations that would run, without running them
+  list [--chat <id>]     list the reminders, of every chat or of the given one
+  add --chat <id> --due <yyyy-mm-dd hh:mm> [--recur <rule>] <message>
+                         add a reminder, due in the chat's timezone
+  remove <id>            remove a reminder
+  chats                  list the trusted chats
+  export [--chat <id>] [--format json|ics]
+                         print the reminders, of every chat or of the given one, as JSON
+                         or as an iCalendar file
+  import [--chat <id>] [--replace] <path>
+                         store the reminders from a JSON export, keeping their ids, which
+                         must be free unless replacing the reminders that have them, or add
+                         them to the given chat; iCalendar files are added to the given
+                         chat";
+
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Error)]
+#[error("{0}")]
+pub struct ArgsError(Box<str>);
+
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Command {
+	Run,
+	Help,
+	CheckSchema,
+	List { chat: Option<bot::ChatId> },
+	Add {
+		chat: bot::ChatId,
+		/// Wall-clock time in the chat's timezone.
+		due: NaiveDateTime,
+		recurrence: Option<Recurrence>,
+		message: Box<str>,
+	},
+	Remove { id: i32 },
+	Chats,
+	Export { chat: Option<bot::ChatId>, format: Format },
+	/// Restore a JSON export, replacing the reminders with the same ids if asked to, or add
+	/// the reminders to the given chat.
+	Import { path: Box<str>, chat: Option<bot::ChatId>, replace: bool },
+}
+
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Args {
+	pub config: Box<str>,
+	pub command: Command,
+}
+
+
+impl Args {
+	/// Parse the arguments, excluding the program name.
+	pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Self, ArgsError> {
+		let mut config = None;
+		let mut chat = None;
+		let mut due = None;
+		let mut recurrence = None;
+		let mut format = None;
+		let mut replace = false;
+		let mut positional = Vec::new();
+
+		let mut args = args.into_iter();
+
+		while let Some(arg) = args.next() {
+			let mut value = |name| args
+				.next()
+				.ok_or_else(
+					|| ArgsError(
+						format!("missing value for {}", name).into()
+					)
+				);
+
+			match arg.as_str() {
+				"--config" => config = Some(value("--config")?),
+				"--chat" => chat = Some(parse("chat id", &value("--chat")?)?),
+				"--due" => due = Some(value("--due")?),
+				"--recur" => recurrence = Some(parse("recurrence", &value("--recur")?)?),
+				"--format" => format = Some(parse("format", &value("--format")?)?),
+				"--replace" => replace = true,
+				// Kept for compatibility, from before subcommands.
+				"--check-schema" => positional.insert(0, "check-schema".into()),
+				"-h" | "--help" => positional.insert(0, "help".into()),
+				_ if arg.starts_with("--") => return Err(
+					ArgsError(
+						format!("unknown option: {}", arg).into()
+					)
+				),
+				_ => positional.push(arg),
+			}
+		}
+
+		let mut positional = positional.into_iter();
+
+		let command = match positional.next().as_deref() {
+			None | Some("run") => Command::Run,
+
+			Some("help") => Command::Help,
+
+			Some("check-schema") => Command::CheckSchema,
+
+			Some("list") => Command::List { chat: chat.map(bot::ChatId::new) },
+
+			Some("add") => {
+				let chat = chat.ok_or_else(
+					|| ArgsError("missing --chat".into())
+				)?;
+
+				let due = due.ok_or_else(
+					|| ArgsError("missing --due".into())
+				)?;
+
+				let due = NaiveDateTime
+					::parse_from_str(&due, "%Y-%m-%d %H:%M")
+					.map_err(
+						|_| ArgsError(
+							format!("invalid due time, expected yyyy-mm-dd hh:mm: {}", due).into()
+						)
+					)?;
+
+				let message = positional
+					.by_ref()
+					.collect::<Vec<_>>()
+					.join(" ");
+
+				if message.is_empty() {
+					return Err(
+						ArgsError("missing message".into())
+					);
+				}
+
+				Command::Add {
+					chat: bot::ChatId::new(chat),
+					due,
+					recurrence,
+					message: message.into(),
+				}
+			}
+
+			Some("remove") => {
+				let id = positional
+					.next()
+					.ok_or_else(
+						|| ArgsError("missing reminder id".into())
+					)?;
+
+				Command::Remove { id: parse("reminder id", &id)? }
+			}
+
+			Some("chats") => Command::Chats,
+
+			Some("export") => Command::Export {
+				chat: chat.map(bot::ChatId::new),
+				format: format.unwrap_or(Format::Json),
+			},
+
+			Some("import") => {
+				let path = positional
+					.next()
+					.ok_or_else(
+						|| ArgsError("missing path".into())
+					)?;
+
+				Command::Import {
+					path: path.into(),
+					chat: chat.map(bot::ChatId::new),
+					replace,
+				}
+			}
+
+			Some(command) => return Err(
+				ArgsError(
+					format!("unknown command: {}", command).into()
+				)
+			),
+		};
+
+		if let Some(arg) = positional.next() {
+			return Err(
+				ArgsError(
+					format!("unexpected argument: {}", arg).into()
+				)
+			);
+		}
+
+		Ok(
+			Self {
+				config: config.map_or_else(
+					|| DEFAULT_CONFIG_FILE.into(),
+					String::into_boxed_str
+				),
+				command,
+			}
+		)
+	}
+}
+
+
+fn parse<T: FromStr>(name: &str, value: &str) -> Result<T, ArgsError> {
+	value
+		.parse()
+		.map_err(
+			|_| ArgsError(
+				format!("invalid {}: {}", name, value).into()
+			)
+		)
+}
+
+
+/// Execute an operator command, other than running the bot, printing its results.
+pub fn execute(command: Command, db: &Db, config: &Config) -> anyhow::Result<()> {
+	match command {
+		Command::Run | Command::Help | Command::CheckSchema => unreachable!("handled before opening the database"),
+
+		Command::List { chat } => {
+			for reminder in reminders(db, chat)?.iter() {
+				let timezone = chat_timezone(db, reminder.chat, config.bot.timezone)?;
+
+				println!("{} {}", reminder.chat.0, reminder.localized(timezone));
+			}
+		}
+
+		Command::Add { chat, due, recurrence, message } => {
+			let timezone = chat_timezone(db, ChatId(chat), config.bot.timezone)?;
+
+			let reminder = NewReminder {
+				due: DueTimestamp::from_local(due, timezone),
+				recurrying: recurrence,
+				chat: ChatId(chat),
+				message: &message,
+				recur_until: None,
+				recur_remaining: None,
+				recur_anchor: recurrence.map(
+					|_| due.day() as i32
+				),
+				source_message: None,
+				media_kind: None,
+				media_file_id: None,
+				creator_id: None,
+				creator_name: None,
+				creator_username: None,
+				mentions: None,
+				recipient: None,
+			};
+
+			db
+				.new_reminder(&reminder)
+				.context("failed to add reminder")?;
+
+			println!("Added: {}", reminder.localized(timezone));
+		}
+
+		Command::Remove { id } => {
+			let removed = db
+				.delete_reminder(id)
+				.context("failed to remove reminder")?;
+
+			if !removed {
+				anyhow::bail!("reminder not found: {}", id);
+			}
+
+			println!("Removed reminder {}.", id);
+		}
+
+		Command::Chats => {
+			let chats = db
+				.trusted_chats()
+				.context("failed to load trusted chats")?;
+
+			for chat in chats.iter() {
+				println!("{}", chat);
+			}
+		}
+
+		Command::Export { chat, format: Format::Json } => {
+			println!("{}", json::export(&reminders(db, chat)?));
+		}
+
+		Command::Export { chat, format: Format::Ics } => {
+			let reminders = reminders(db, chat)?;
+
+			let mut timezones = Vec::new();
+
+			for reminder in reminders.iter() {
+				if !timezones.iter().any(|&(chat, _)| chat == reminder.chat) {
+					timezones.push(
+						(reminder.chat, chat_timezone(db, reminder.chat, config.bot.timezone)?)
+					);
+				}
+			}
+
+			let timezone = |chat| timezones
+				.iter()
+				.find(
+					|&&(other, _)| other == chat
+				)
+				.map_or(config.bot.timezone, |&(_, timezone)| timezone);
+
+			print!("{}", ics::export(&reminders, timezone, Utc::now()));
+		}
+
+		Command::Import { path, chat, replace } => {
+			let data = fs
+				::read_to_string(path.as_ref())
+				.with_context(
+					|| format!("failed to read file: {}", path)
+				)?;
+
+			let imported = match (Format::detect(&data), chat) {
+				(Some(Format::Json), None) => {
+					let reminders = json
+						::import(&data)
+						.with_context(
+							|| format!("failed to parse file: {}", path)
+						)?;
+
+					if !replace {
+						let ids: Vec<_> = reminders
+							.iter()
+							.map(
+								|reminder| reminder.id
+							)
+							.collect();
+
+						let taken = db
+							.taken_reminder_ids(&ids)
+							.context("failed to look up reminders")?;
+
+						if !taken.is_empty() {
+							anyhow::bail!(
+								"reminders already exist with ids {:?}, import with --replace to overwrite them, or with --chat to add them under new ids",
+								taken
+							);
+						}
+					}
+
+					db
+						.import_reminders(&reminders, replace)
+						.context("failed to store reminders")?;
+
+					reminders.len()
+				}
+
+				(Some(Format::Json), Some(chat)) => {
+					let reminders = json
+						::import(&data)
+						.with_context(
+							|| format!("failed to parse file: {}", path)
+						)?;
+
+					// Replies are to messages in the exported chat. Private reminders are kept
+					// private, still delivered to their recipient.
+					let new_reminders: Vec<_> = reminders
+						.iter()
+						.map(
+							|reminder| NewReminder {
+								chat: ChatId(chat),
+								source_message: None,
+								..reminder.as_new()
+							}
+						)
+						.collect();
+
+					db
+						.new_reminders(&new_reminders)
+						.context("failed to store reminders")?;
+
+					new_reminders.len()
+				}
+
+				(Some(Format::Ics), Some(chat)) => {
+					let timezone = chat_timezone(db, ChatId(chat), config.bot.timezone)?;
+
+					let calendar = ics
+						::import(&data, timezone, Utc::now())
+						.with_context(
+							|| format!("failed to parse file: {}", path)
+						)?;
+
+					for event in calendar.unsupported.iter() {
+						eprintln!("Skipped event {}", event);
+					}
+
+					let new_reminders: Vec<_> = calendar.events
+						.iter()
+						.map(
+							|event| event.new_reminder(ChatId(chat))
+						)
+						.collect();
+
+					db
+						.new_reminders(&new_reminders)
+						.context("failed to store reminders")?;
+
+					new_reminders.len()
+				}
+
+				(Some(Format::Ics), None) => anyhow::bail!("missing --chat to import an iCalendar file into"),
+
+				(None, _) => anyhow::bail!("unknown file format: {}", path),
+			};
+
+			println!("Imported {} reminders.", imported);
+		}
+	}
+
+	Ok(())
+}
+
+
+/// The reminders of every chat, or of the given one.
+fn reminders(db: &Db, chat: Option<bot::ChatId>) -> anyhow::Result<Box<[Reminder]>> {
+	match chat {
+		Some(chat) => db.chat_reminders(ChatId(chat)),
+		None => db.reminders(),
+	}
+		.context("failed to load reminders")
+}
+
+
+fn chat_timezone(db: &Db, chat: ChatId, default_timezone: Tz) -> anyhow::Result<Tz> {
+	let settings = db
+		.chat_settings(chat)
+		.context("failed to load chat settings")?;
+
+	Ok(
+		settings
+			.and_then(
+				|settings| settings.timezone
+			)
+			.map_or(
+				default_timezone,
+				|timezone| timezone.0
+			)
+	)
+}
@@ -0,0 +1,102 @@
+use super::*;
+
+
+fn parse(args: &[&str]) -> Result<Args, ArgsError> {
+	Args::parse(
+		args
+			.iter()
+			.map(
+				|arg| arg.to_string()
+			)
+	)
+}
+
+
+fn command(args: &[&str]) -> Command {
+	parse(args)
+		.expect("invalid arguments")
+		.command
+}
+
+
+#[test]
+fn test_run() {
+	assert_eq!(
+		parse(&[]),
+		Ok(
+			Args {
+				config: DEFAULT_CONFIG_FILE.into(),
+				command: Command::Run,
+			}
+		)
+	);
+
+	assert_eq!(
+		parse(&["--config", "/etc/reminder-bot.toml", "run"]),
+		Ok(
+			Args {
+				config: "/etc/reminder-bot.toml".into(),
+				command: Command::Run,
+			}
+		)
+	);
+
+	assert_eq!(command(&["--check-schema"]), Command::CheckSchema);
+	assert_eq!(command(&["check-schema"]), Command::CheckSchema);
+	assert_eq!(command(&["--help"]), Command::Help);
+}
+
+
+#[test]
+fn test_commands() {
+	let chat = bot::ChatId::new(-100);
+
+	assert_eq!(command(&["list"]), Command::List { chat: None });
+	assert_eq!(command(&["list", "--chat", "-100"]), Command::List { chat: Some(chat) });
+	assert_eq!(command(&["remove", "3"]), Command::Remove { id: 3 });
+	assert_eq!(command(&["chats"]), Command::Chats);
+	assert_eq!(command(&["--chat", "-100", "export"]), Command::Export { chat: Some(chat), format: Format::Json });
+	assert_eq!(command(&["export", "--format", "ics"]), Command::Export { chat: None, format: Format::Ics });
+	assert_eq!(command(&["import", "backup.json"]), Command::Import { path: "backup.json".into(), chat: None, replace: false });
+	assert_eq!(
+		command(&["import", "--replace", "backup.json"]),
+		Command::Import { path: "backup.json".into(), chat: None, replace: true }
+	);
+	assert_eq!(
+		command(&["import", "--chat", "-100", "calendar.ics"]),
+		Command::Import { path: "calendar.ics".into(), chat: Some(chat), replace: false }
+	);
+
+	assert_eq!(
+		command(&["add", "--chat", "-100", "--due", "2021-03-02 10:00", "--recur", "+1d", "water", "plants"]),
+		Command::Add {
+			chat,
+			due: NaiveDateTime
+				::parse_from_str("2021-03-02 10:00", "%Y-%m-%d %H:%M")
+				.unwrap(),
+			recurrence: Some(
+				"+1d"
+					.parse()
+					.unwrap()
+			),
+			message: "water plants".into(),
+		}
+	);
+}
+
+
+#[test]
+fn test_invalid() {
+	assert!(parse(&["--config"]).is_err());
+	assert!(parse(&["--verbose"]).is_err());
+	assert!(parse(&["frobnicate"]).is_err());
+	assert!(parse(&["remove"]).is_err());
+	assert!(parse(&["remove", "three"]).is_err());
+	assert!(parse(&["remove", "3", "4"]).is_err());
+	assert!(parse(&["list", "--chat", "general"]).is_err());
+	assert!(parse(&["export", "--format", "csv"]).is_err());
+	assert!(parse(&["add", "--chat", "1", "water plants"]).is_err());
+	assert!(parse(&["add", "--chat", "1", "--due", "tomorrow", "water plants"]).is_err());
+	assert!(parse(&["add", "--chat", "1", "--due", "2021-03-02 10:00"]).is_err());
+	assert!(parse(&["add", "--chat", "1", "--due", "2021-03-02 10:00", "--recur", "+1x", "water"]).is_err());
+}
@@ -0,0 +1,60 @@
+#[cfg(test)]
+use std::{cell::Cell, rc::Rc};
+
+use chrono::{DateTime, Utc};
+
+
+/// A source of the current time.
+pub trait Clock {
+	fn now(&self) -> DateTime<Utc>;
+}
+
+
+/// The system's clock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct SystemClock;
+
+
+impl Clock for SystemClock {
+	fn now(&self) -> DateTime<Utc> {
+		Utc::now()
+	}
+}
+
+
+/// A clock that only moves when told to. Clones share the same time.
+#[cfg(test)]
+#[derive(Debug, Clone)]
+pub struct ManualClock(Rc<Cell<DateTime<Utc>>>);
+
+
+#[cfg(test)]
+impl ManualClock {
+	pub fn new(now: DateTime<Utc>) -> Self {
+		Self(
+			Rc::new(
+				Cell::new(now)
+			)
+		)
+	}
+
+
+	pub fn set(&self, now: DateTime<Utc>) {
+		self.0.set(now);
+	}
+
+
+	pub fn advance(&self, duration: chrono::Duration) {
+		self.set(
+			self.0.get() + duration
+		);
+	}
+}
+
+
+#[cfg(test)]
+impl Clock for ManualClock {
+	fn now(&self) -> DateTime<Utc> {
+		self.0.get()
+	}
+}
@@ -1,15 +1,171 @@
-use serde::Deserialize;
+use std::{convert::TryFrom, net::SocketAddr};
+
+use serde::{de, Deserialize, Deserializer};
+
+use chrono::{DateTime, Utc};
+
+use chrono_tz::Tz;
 
 
 type Str = Box<str>;
 
 
+/// The shortest webhook secret token accepted, as anyone who guesses it may post updates.
+const MIN_SECRET_TOKEN_LENGTH: usize = 16;
+
+
+/// A password's secret, either in plain text: `{ plain = '...' }`, or as an argon2 hash
+/// in the PHC string format: `{ hash = '$argon2id$...' }`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Secret {
+	Plain(Str),
+	Hash(Str),
+}
+
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 #[derive(Deserialize)]
+pub struct Password {
+	#[serde(flatten)]
+	pub secret: Secret,
+	/// When the password stops being accepted, e.g.: 2021-06-01T00:00:00Z. Never if absent.
+	pub expires: Option<DateTime<Utc>>,
+}
+
+
+/// How many failed attempts a chat may make in a period, before the bot leaves it and
+/// ignores it until the period is over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Deserialize)]
+pub struct Throttle {
+	pub attempts: u32,
+	/// In minutes.
+	pub period: u32,
+}
+
+
+impl Default for Throttle {
+	fn default() -> Self {
+		Self {
+			attempts: 3,
+			period: 60,
+		}
+	}
+}
+
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Deserialize)]
+#[serde(try_from = "AuthenticationFields")]
 pub struct Authentication {
 	pub prompt: Str,
-	pub password: Str,
+	/// Every password currently accepted, so that they may be rotated.
+	pub passwords: Box<[Password]>,
 	pub authorized: Str,
+	pub throttle: Throttle,
+	/// The id of the chat operator commands are accepted from, which needs no password.
+	pub operator: Option<i64>,
+}
+
+
+/// The authentication as written in the config, which may still have the single plain
+/// text `password` of older configs.
+#[derive(Deserialize)]
+struct AuthenticationFields {
+	prompt: Str,
+	passwords: Option<Box<[Password]>>,
+	password: Option<Str>,
+	authorized: Str,
+	#[serde(default)]
+	throttle: Throttle,
+	operator: Option<i64>,
+}
+
+
+impl TryFrom<AuthenticationFields> for Authentication {
+	type Error = &'static str;
+
+	fn try_from(fields: AuthenticationFields) -> Result<Self, Self::Error> {
+		let legacy = fields.password.map(
+			|password| Password {
+				secret: Secret::Plain(password),
+				expires: None,
+			}
+		);
+
+		let passwords = match (fields.passwords, legacy) {
+			(Some(passwords), None) => passwords,
+			(None, Some(legacy)) => Box::new([legacy]),
+			(Some(passwords), Some(legacy)) => passwords
+				.into_vec()
+				.into_iter()
+				.chain(Some(legacy))
+				.collect(),
+			(None, None) => return Err("missing field `passwords`"),
+		};
+
+		Ok(
+			Self {
+				prompt: fields.prompt,
+				passwords,
+				authorized: fields.authorized,
+				throttle: fields.throttle,
+				operator: fields.operator,
+			}
+		)
+	}
+}
+
+
+/// What to do with reminders that were missed, e.g.: while the bot was down.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CatchUp {
+	/// Deliver once, and skip to the next future occurrence.
+	Once,
+	/// Deliver every missed occurrence.
+	All,
+	/// Send a note instead of delivering, and skip to the next future occurrence.
+	Skip,
+}
+
+
+impl Default for CatchUp {
+	fn default() -> Self {
+		CatchUp::Once
+	}
+}
+
+
+/// Receiving updates through a webhook, instead of polling for them.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Deserialize)]
+pub struct Webhook {
+	/// The address to listen on, e.g.: 127.0.0.1:8443.
+	pub address: SocketAddr,
+	/// The path updates are posted to.
+	pub path: Str,
+	/// The secret token set along with the webhook, which Telegram sends in every request.
+	#[serde(deserialize_with = "secret_token")]
+	pub secret_token: Str,
+}
+
+
+fn secret_token<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Str, D::Error> {
+	let token = Str::deserialize(deserializer)?;
+
+	if token.len() < MIN_SECRET_TOKEN_LENGTH {
+		return Err(
+			de::Error::custom(
+				format!("secret_token must be at least {} characters long", MIN_SECRET_TOKEN_LENGTH)
+			)
+		);
+	}
+
+	Ok(token)
 }
 
 
@@ -19,15 +175,53 @@ pub struct Bot {
 	pub db: Str,
 	pub key: Str,
 	pub username: Str,
+	/// The timezone for chats that haven't set one.
+	pub timezone: Tz,
+	#[serde(default)]
+	pub catch_up: CatchUp,
 	pub authentication: Authentication,
+	/// Poll for updates when absent.
+	pub webhook: Option<Webhook>,
 }
 
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 #[derive(Deserialize)]
 pub struct Commands {
+	pub edit_command: Box<Str>,
 	pub remove_command: Box<Str>,
 	pub list_command: Box<Str>,
+	pub timezone_command: Box<Str>,
+	pub grant_command: Box<Str>,
+	pub revoke_command: Box<Str>,
+	pub management_command: Box<Str>,
+	pub invite_command: Box<Str>,
+	pub export_command: Box<Str>,
+	pub import_command: Box<Str>,
+	pub history_command: Box<Str>,
+}
+
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+#[derive(Deserialize)]
+pub struct Weekdays {
+	pub monday: Box<[Str]>,
+	pub tuesday: Box<[Str]>,
+	pub wednesday: Box<[Str]>,
+	pub thursday: Box<[Str]>,
+	pub friday: Box<[Str]>,
+	pub saturday: Box<[Str]>,
+	pub sunday: Box<[Str]>,
+}
+
+
+/// Localized words accepted in place of a date, in addition to the english ones.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+#[derive(Deserialize)]
+pub struct Words {
+	pub today: Box<[Str]>,
+	pub tomorrow: Box<[Str]>,
+	pub weekdays: Weekdays,
 }
 
 
@@ -35,10 +229,27 @@ pub struct Commands {
 #[derive(Deserialize)]
 pub struct Messages {
 	pub added_messages: Box<[Str]>,
+	pub edited_messages: Box<[Str]>,
 	pub removed_messages: Box<[Str]>,
 	pub not_found_messages: Box<[Str]>,
 	pub empty_messages: Box<[Str]>,
 	pub list_header_messages: Box<[Str]>,
+	pub history_header_messages: Box<[Str]>,
+	pub timezone_messages: Box<[Str]>,
+	pub snoozed_messages: Box<[Str]>,
+	pub done_messages: Box<[Str]>,
+	pub late_messages: Box<[Str]>,
+	pub skipped_messages: Box<[Str]>,
+	pub unreachable_messages: Box<[Str]>,
+	pub forbidden_messages: Box<[Str]>,
+	pub granted_messages: Box<[Str]>,
+	pub revoked_messages: Box<[Str]>,
+	pub everyone_manages_messages: Box<[Str]>,
+	pub admins_manage_messages: Box<[Str]>,
+	pub invite_messages: Box<[Str]>,
+	pub imported_messages: Box<[Str]>,
+	pub invalid_file_messages: Box<[Str]>,
+	pub unsupported_events_messages: Box<[Str]>,
 	pub misunderstanding_messages: Box<[Str]>,
 }
 
@@ -55,10 +266,27 @@ macro_rules! pick_message_fn {
 
 impl Messages {
 	pick_message_fn!(added_messages, added_message);
+	pick_message_fn!(edited_messages, edited_message);
 	pick_message_fn!(removed_messages, removed_message);
 	pick_message_fn!(not_found_messages, not_found_message);
 	pick_message_fn!(empty_messages, empty_message);
 	pick_message_fn!(list_header_messages, list_header_message);
+	pick_message_fn!(history_header_messages, history_header_message);
+	pick_message_fn!(timezone_messages, timezone_message);
+	pick_message_fn!(snoozed_messages, snoozed_message);
+	pick_message_fn!(done_messages, done_message);
+	pick_message_fn!(late_messages, late_message);
+	pick_message_fn!(skipped_messages, skipped_message);
+	pick_message_fn!(unreachable_messages, unreachable_message);
+	pick_message_fn!(forbidden_messages, forbidden_message);
+	pick_message_fn!(granted_messages, granted_message);
+	pick_message_fn!(revoked_messages, revoked_message);
+	pick_message_fn!(everyone_manages_messages, everyone_manages_message);
+	pick_message_fn!(admins_manage_messages, admins_manage_message);
+	pick_message_fn!(invite_messages, invite_message);
+	pick_message_fn!(imported_messages, imported_message);
+	pick_message_fn!(invalid_file_messages, invalid_file_message);
+	pick_message_fn!(unsupported_events_messages, unsupported_events_message);
 	pick_message_fn!(misunderstanding_messages, misunderstanding_message);
 }
 
@@ -68,6 +296,9 @@ impl Messages {
 pub struct Config {
 	pub bot: Bot,
 	pub commands: Commands,
+	/// Only the english words are accepted when absent.
+	#[serde(default)]
+	pub words: Words,
 	pub messages: Messages,
 }
 
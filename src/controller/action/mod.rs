@@ -1,59 +1,210 @@
 mod parser;
+#[cfg(test)]
+mod tests;
 
 use std::fmt::Write;
 
 use thiserror::Error;
 
-use chrono::NaiveDateTime as DateTime;
+use chrono::{
+	Datelike,
+	Duration,
+	NaiveDate as Date,
+	NaiveDateTime as DateTime,
+	NaiveTime as Time,
+	Utc,
+};
+
+use chrono_tz::Tz;
 
 pub use parser::ParseError;
 use crate::{
-	bot::{self, Bot, BotError},
+	bot::{self, BotError, Messenger},
 	config,
+	export::{Format, ics, json},
 	db::{
 		Db,
 		Error as DbError,
-		models::reminders::NewReminder,
+		models::{
+			chat_roles::ChatRole,
+			invites::Invite,
+			reminders::{NewReminder, Reminder, ReminderChanges},
+		},
 		types::{
 			ChatId,
 			DueTimestamp,
+			MediaKind,
+			Mentions,
+			MessageId,
+			Nth,
+			Outcome,
 			Recurrence,
 			RecurrenceUnit,
+			Role,
+			Timezone,
+			UserId,
+			WeekdaySet,
 		},
 	},
 };
 
 
+/// Buttons attached to delivered reminders, as pairs of label and callback data.
+pub const SNOOZE_BUTTONS: [(&str, &str); 4] = [
+	("+10m", "snooze 10"),
+	("+1h", "snooze 60"),
+	("tomorrow", "snooze tomorrow"),
+	("✓", "done"),
+];
+
+/// Separates the lateness note from the message text in late deliveries.
+const LATE_MARKER: &str = "\n\n⏰ ";
+
+/// The text of reminders about a replied message that has none, e.g.: a photo.
+const REPLY_PLACEHOLDER: &str = "↩️";
+
+/// The characters invite codes are made of, leaving out the easily confused `l` and `o`,
+/// and `0` and `1`. There are 32 of them, so that every random byte maps evenly.
+const INVITE_ALPHABET: &[u8; 32] = b"abcdefghijkmnpqrstuvwxyz23456789";
+const INVITE_LENGTH: usize = 12;
+/// How long invites may be redeemed for, in hours.
+const INVITE_VALIDITY: i64 = 24;
+
+
 #[derive(Debug, Error)]
 pub enum ExecutionError {
 	#[error("database error: {0}")]
 	Db(DbError),
 	#[error("bot error: {0}")]
 	Bot(BotError),
+	#[error("random generation error: {0}")]
+	Random(getrandom::Error),
+}
+
+
+/// The user executing an action, and what they may do in its chat.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Actor {
+	pub id: bot::UserId,
+	pub role: Role,
+	/// Whether the chat lets every member manage every reminder.
+	pub everyone_manages: bool,
+}
+
+
+impl Actor {
+	pub fn is_admin(&self) -> bool {
+		self.role >= Role::Admin
+	}
+
+
+	/// Whether the user may edit or remove the reminder.
+	pub fn may_manage(&self, reminder: &Reminder) -> bool {
+		self.may_manage_created_by(reminder.creator_id)
+	}
+
+
+	/// Whether the user may manage a reminder added by the given user.
+	fn may_manage_created_by(&self, creator_id: Option<UserId>) -> bool {
+		self.everyone_manages
+			|| self.is_admin()
+			// Reminders added before creators were stored are everyone's.
+			|| creator_id.map_or(true, |creator| creator.0 == self.id)
+	}
+}
+
+
+/// The user who added a reminder.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Creator {
+	pub id: bot::UserId,
+	pub name: Box<str>,
+	pub username: Option<Box<str>>,
 }
 
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct AddReminder {
+	/// Wall-clock time in the chat's timezone.
 	due: DateTime,
 	recurrence: Option<Recurrence>,
+	/// How many times a recurring reminder will be delivered.
+	count: Option<u32>,
+	/// The last day a recurring reminder will be delivered.
+	until: Option<Date>,
 	message: Box<str>,
+	/// The message the reminder will be sent in reply to.
+	source_message: Option<bot::MessageId>,
+	/// A file to be sent along with the reminder.
+	media: Option<bot::Media>,
+	/// The users to tag when the reminder is delivered.
+	targets: Box<[bot::Mention]>,
+	creator: Option<Creator>,
+	/// Whether to deliver the reminder in the creator's private chat.
+	private: bool,
 	chat_id: bot::ChatId,
+	timezone: Tz,
 }
 
 
 impl AddReminder {
-	pub async fn execute<'a>(
+	pub async fn execute<'a, M: Messenger>(
 		self,
 		db: &'a Db,
-		bot: &'a Bot<'a>,
+		bot: &'a M,
 		messages: &'a config::Messages,
 	) -> Result<(), ExecutionError> {
+		let mentions = mentions(&self.targets);
+
 		let reminder = NewReminder {
-			due: DueTimestamp(self.due),
+			due: DueTimestamp::from_local(self.due, self.timezone),
 			recurrying: self.recurrence,
 			chat: ChatId(self.chat_id),
 			message: &self.message,
+			recur_until: self.until.map(
+				|until| end_of_day(until, self.timezone)
+			),
+			recur_remaining: self.count.map(
+				|count| count as i32
+			),
+			recur_anchor: self.recurrence.map(
+				|_| self.due.day() as i32
+			),
+			source_message: self.source_message.map(MessageId),
+			media_kind: self.media
+				.as_ref()
+				.map(
+					|media| MediaKind(media.kind)
+				),
+			media_file_id: self.media
+				.as_ref()
+				.map(
+					|media| media.file_id.as_ref()
+				),
+			creator_id: self.creator
+				.as_ref()
+				.map(
+					|creator| UserId(creator.id)
+				),
+			creator_name: self.creator
+				.as_ref()
+				.map(
+					|creator| creator.name.as_ref()
+				),
+			creator_username: self.creator
+				.as_ref()
+				.and_then(
+					|creator| creator.username.as_deref()
+				),
+			mentions: mentions.as_ref(),
+			recipient: self.creator
+				.as_ref()
+				.filter(
+					|_| self.private
+				)
+				.map(
+					|creator| UserId(creator.id)
+				),
 		};
 
 		db
@@ -66,12 +217,12 @@ impl AddReminder {
 			message,
 			"{}\n{}",
 			messages.added_message(),
-			reminder
+			reminder.localized(self.timezone)
 		)
 			.expect("write should not fail on string");
 
 		bot
-			.send_message(self.chat_id, message)
+			.send_message(self.chat_id, &message)
 			.await
 			.map_err(ExecutionError::Bot)?;
 
@@ -88,25 +239,212 @@ pub struct RemoveReminder {
 
 
 impl RemoveReminder {
-	pub async fn execute<'a>(
+	pub async fn execute<'a, M: Messenger>(
 		self,
 		db: &'a Db,
-		bot: &'a Bot<'a>,
+		bot: &'a M,
 		messages: &'a config::Messages,
+		actor: Actor,
 	) -> Result<(), ExecutionError> {
-		let success = db
-			.delete_chat_reminder(self.reminder_id, ChatId(self.chat_id))
+		let chat_id = ChatId(self.chat_id);
+
+		let reminder = db
+			.chat_reminder(self.reminder_id, chat_id)
 			.map_err(ExecutionError::Db)?;
 
-		let message =
-			if success {
-				messages.removed_message()
+		let message = match reminder {
+			Some(reminder) if !actor.may_manage(&reminder) => messages.forbidden_message(),
+
+			Some(_) => {
+				let success = db
+					.delete_chat_reminder(self.reminder_id, chat_id)
+					.map_err(ExecutionError::Db)?;
+
+				if success {
+					messages.removed_message()
+				} else {
+					messages.not_found_message()
+				}
+			}
+
+			None => messages.not_found_message(),
+		};
+
+		bot
+			.send_message(self.chat_id, message)
+			.await
+			.map_err(ExecutionError::Bot)?;
+
+		Ok(())
+	}
+}
+
+
+/// A change to a reminder's due time, as wall-clock time in the chat's timezone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DueChange {
+	/// A new date, keeping the time of day if none is given.
+	Date(Date, Option<Time>),
+	/// A new time of day, keeping the date.
+	Time(Time),
+	/// A new date and time.
+	DateTime(DateTime),
+}
+
+
+impl DueChange {
+	pub fn apply(self, due: DateTime) -> DateTime {
+		match self {
+			DueChange::Date(date, time) => DateTime::new(
+				date,
+				time.unwrap_or_else(
+					|| due.time()
+				)
+			),
+			DueChange::Time(time) => DateTime::new(due.date(), time),
+			DueChange::DateTime(datetime) => datetime,
+		}
+	}
+}
+
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct EditReminder {
+	reminder_id: i32,
+	due: Option<DueChange>,
+	/// When given, replaces the recurrence and its end conditions. `Some(None)` removes
+	/// them.
+	recurrence: Option<Option<Recurrence>>,
+	count: Option<u32>,
+	until: Option<Date>,
+	message: Option<Box<str>>,
+	chat_id: bot::ChatId,
+	timezone: Tz,
+}
+
+
+impl EditReminder {
+	pub async fn execute<'a, M: Messenger>(
+		self,
+		db: &'a Db,
+		bot: &'a M,
+		messages: &'a config::Messages,
+		actor: Actor,
+	) -> Result<(), ExecutionError> {
+		let chat_id = ChatId(self.chat_id);
+
+		let before = db
+			.chat_reminder(self.reminder_id, chat_id)
+			.map_err(ExecutionError::Db)?;
+
+		let before = match before {
+			Some(reminder) => reminder,
+			None => {
+				bot
+					.send_message(self.chat_id, messages.not_found_message())
+					.await
+					.map_err(ExecutionError::Bot)?;
+
+				return Ok(());
+			}
+		};
+
+		if !actor.may_manage(&before) {
+			bot
+				.send_message(self.chat_id, messages.forbidden_message())
+				.await
+				.map_err(ExecutionError::Bot)?;
+
+			return Ok(());
+		}
+
+		let timezone = self.timezone;
+
+		let due = self.due.map(
+			|change| DueTimestamp::from_local(
+				change.apply(
+					before.due.local(timezone)
+				),
+				timezone
+			)
+		);
+
+		let recur_until = self.recurrence.map(
+			|_| self.until.map(
+				|until| end_of_day(until, timezone)
+			)
+		);
+
+		let recur_remaining = self.recurrence.map(
+			|_| self.count.map(
+				|count| count as i32
+			)
+		);
+
+		// Monthly and yearly recurrences return to the day of the month of the new date.
+		let date_changed = matches!(
+			self.due,
+			Some(DueChange::Date(..)) | Some(DueChange::DateTime(_))
+		);
+
+		let recur_anchor =
+			if date_changed || (matches!(self.recurrence, Some(Some(_))) && before.recur_anchor.is_none()) {
+				let day = due
+					.unwrap_or(before.due)
+					.local(timezone)
+					.day();
+
+				Some(Some(day as i32))
 			} else {
-				messages.not_found_message()
+				None
 			};
 
+		let changes = ReminderChanges {
+			due,
+			recurrying: self.recurrence,
+			message: self.message.as_deref(),
+			recur_until,
+			recur_remaining,
+			recur_anchor,
+		};
+
+		let success = db
+			.update_chat_reminder(self.reminder_id, chat_id, &changes)
+			.map_err(ExecutionError::Db)?;
+
+		if !success {
+			bot
+				.send_message(self.chat_id, messages.not_found_message())
+				.await
+				.map_err(ExecutionError::Bot)?;
+
+			return Ok(());
+		}
+
+		let after = Reminder {
+			due: due.unwrap_or(before.due),
+			recurrying: self.recurrence.unwrap_or(before.recurrying),
+			recur_until: recur_until.unwrap_or(before.recur_until),
+			recur_remaining: recur_remaining.unwrap_or(before.recur_remaining),
+			recur_anchor: recur_anchor.unwrap_or(before.recur_anchor),
+			message: self.message
+				.as_deref()
+				.map_or_else(
+					|| before.message.clone(),
+					String::from
+				),
+			..before.clone()
+		};
+
+		let message = format!(
+			"{}\n{}\n→ {}",
+			messages.edited_message(),
+			before.localized(timezone),
+			after.localized(timezone)
+		);
+
 		bot
-			.send_message(self.chat_id, message)
+			.send_message(self.chat_id, &message)
 			.await
 			.map_err(ExecutionError::Bot)?;
 
@@ -118,14 +456,15 @@ impl RemoveReminder {
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct ListReminders{
 	chat_id: bot::ChatId,
+	timezone: Tz,
 }
 
 
 impl ListReminders {
-	pub async fn execute<'a>(
+	pub async fn execute<'a, M: Messenger>(
 		self,
 		db: &'a Db,
-		bot: &'a Bot<'a>,
+		bot: &'a M,
 		messages: &'a config::Messages,
 	) -> Result<(), ExecutionError> {
 		let reminders = db
@@ -144,49 +483,924 @@ impl ListReminders {
 			text = format!("{}\n", messages.list_header_message());
 
 			for reminder in reminders {
-				writeln!(text, "{}", reminder)
+				writeln!(text, "{}", reminder.localized(self.timezone))
+					.expect("write should not fail on string");
+			}
+		}
+
+		bot
+			.send_message(self.chat_id, &text)
+			.await
+			.map_err(ExecutionError::Bot)?;
+
+		Ok(())
+	}
+}
+
+
+/// List the chat's latest deliveries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ListDeliveries {
+	chat_id: bot::ChatId,
+	count: u32,
+	timezone: Tz,
+}
+
+
+impl ListDeliveries {
+	pub async fn execute<'a, M: Messenger>(
+		self,
+		db: &'a Db,
+		bot: &'a M,
+		messages: &'a config::Messages,
+	) -> Result<(), ExecutionError> {
+		let deliveries = db
+			.chat_deliveries(ChatId(self.chat_id), self.count.into())
+			.map_err(ExecutionError::Db)?
+			.into_vec();
+
+		let mut text;
+
+		if deliveries.is_empty() {
+			text = String::from(
+				messages.empty_message()
+			);
+		}
+		else {
+			text = format!("{}\n", messages.history_header_message());
+
+			for delivery in deliveries {
+				writeln!(text, "{}", delivery.localized(self.timezone))
+					.expect("write should not fail on string");
+			}
+		}
+
+		bot
+			.send_message(self.chat_id, &text)
+			.await
+			.map_err(ExecutionError::Bot)?;
+
+		Ok(())
+	}
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SetTimezone {
+	chat_id: bot::ChatId,
+	timezone: Tz,
+}
+
+
+impl SetTimezone {
+	pub async fn execute<'a, M: Messenger>(
+		self,
+		db: &'a Db,
+		bot: &'a M,
+		messages: &'a config::Messages,
+	) -> Result<(), ExecutionError> {
+		db
+			.set_chat_timezone(ChatId(self.chat_id), Timezone(self.timezone))
+			.map_err(ExecutionError::Db)?;
+
+		let message = format!(
+			"{}\n{}",
+			messages.timezone_message(),
+			self.timezone.name()
+		);
+
+		bot
+			.send_message(self.chat_id, &message)
+			.await
+			.map_err(ExecutionError::Bot)?;
+
+		Ok(())
+	}
+}
+
+
+/// Give a user a role in the chat, or take it away.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SetRole {
+	chat_id: bot::ChatId,
+	user_id: bot::UserId,
+	name: Box<str>,
+	/// Admin to grant, member to revoke.
+	role: Role,
+}
+
+
+impl SetRole {
+	pub async fn execute<'a, M: Messenger>(
+		self,
+		db: &'a Db,
+		bot: &'a M,
+		messages: &'a config::Messages,
+		actor: Actor,
+	) -> Result<(), ExecutionError> {
+		let chat_id = ChatId(self.chat_id);
+		let user_id = UserId(self.user_id);
+
+		let current = db
+			.chat_role(chat_id, user_id)
+			.map_err(ExecutionError::Db)?;
+
+		// Owners keep their role.
+		if !actor.is_admin() || current == Some(Role::Owner) {
+			bot
+				.send_message(self.chat_id, messages.forbidden_message())
+				.await
+				.map_err(ExecutionError::Bot)?;
+
+			return Ok(());
+		}
+
+		db
+			.set_chat_role(
+				&ChatRole {
+					chat: chat_id,
+					user_id,
+					role: self.role,
+				}
+			)
+			.map_err(ExecutionError::Db)?;
+
+		let header =
+			if self.role >= Role::Admin {
+				messages.granted_message()
+			} else {
+				messages.revoked_message()
+			};
+
+		let message = format!("{}\n{}", header, self.name);
+
+		bot
+			.send_message(self.chat_id, &message)
+			.await
+			.map_err(ExecutionError::Bot)?;
+
+		Ok(())
+	}
+}
+
+
+/// Let every member manage every reminder, or only admins.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SetManagement {
+	chat_id: bot::ChatId,
+	everyone_manages: bool,
+}
+
+
+impl SetManagement {
+	pub async fn execute<'a, M: Messenger>(
+		self,
+		db: &'a Db,
+		bot: &'a M,
+		messages: &'a config::Messages,
+		actor: Actor,
+	) -> Result<(), ExecutionError> {
+		if !actor.is_admin() {
+			bot
+				.send_message(self.chat_id, messages.forbidden_message())
+				.await
+				.map_err(ExecutionError::Bot)?;
+
+			return Ok(());
+		}
+
+		db
+			.set_chat_everyone_manages(ChatId(self.chat_id), self.everyone_manages)
+			.map_err(ExecutionError::Db)?;
+
+		let message =
+			if self.everyone_manages {
+				messages.everyone_manages_message()
+			} else {
+				messages.admins_manage_message()
+			};
+
+		bot
+			.send_message(self.chat_id, message)
+			.await
+			.map_err(ExecutionError::Bot)?;
+
+		Ok(())
+	}
+}
+
+
+/// Create a one-time code that authenticates another chat.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CreateInvite {
+	chat_id: bot::ChatId,
+}
+
+
+impl CreateInvite {
+	pub async fn execute<'a, M: Messenger>(
+		self,
+		db: &'a Db,
+		bot: &'a M,
+		messages: &'a config::Messages,
+		actor: Actor,
+		now: chrono::DateTime<Utc>,
+	) -> Result<(), ExecutionError> {
+		if !actor.is_admin() {
+			bot
+				.send_message(self.chat_id, messages.forbidden_message())
+				.await
+				.map_err(ExecutionError::Bot)?;
+
+			return Ok(());
+		}
+
+		let mut bytes = [0; INVITE_LENGTH];
+
+		getrandom
+			::getrandom(&mut bytes)
+			.map_err(ExecutionError::Random)?;
+
+		let code: String = bytes
+			.iter()
+			.map(
+				|byte| INVITE_ALPHABET[(byte % 32) as usize] as char
+			)
+			.collect();
+
+		let expires = now + Duration::hours(INVITE_VALIDITY);
+
+		db
+			.new_invite(
+				&Invite {
+					code: code.clone(),
+					chat: ChatId(self.chat_id),
+					created_by: UserId(actor.id),
+					expires: DueTimestamp(expires),
+				}
+			)
+			.map_err(ExecutionError::Db)?;
+
+		log::info!("Created invite in chat {}, by user {}", self.chat_id, actor.id);
+
+		let message = format!("{}\n{}", messages.invite_message(), code);
+
+		bot
+			.send_message(self.chat_id, &message)
+			.await
+			.map_err(ExecutionError::Bot)?;
+
+		Ok(())
+	}
+}
+
+
+/// Send the chat's reminders as a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ExportReminders {
+	chat_id: bot::ChatId,
+	format: Format,
+	timezone: Tz,
+}
+
+
+impl ExportReminders {
+	pub async fn execute<'a, M: Messenger>(
+		self,
+		db: &'a Db,
+		bot: &'a M,
+		messages: &'a config::Messages,
+		now: chrono::DateTime<Utc>,
+	) -> Result<(), ExecutionError> {
+		let reminders = db
+			.chat_reminders(ChatId(self.chat_id))
+			.map_err(ExecutionError::Db)?;
+
+		if reminders.is_empty() {
+			bot
+				.send_message(self.chat_id, messages.empty_message())
+				.await
+				.map_err(ExecutionError::Bot)?;
+
+			return Ok(());
+		}
+
+		let data = match self.format {
+			Format::Json => json::export(&reminders),
+
+			Format::Ics => ics::export(
+				&reminders,
+				|_| self.timezone,
+				now
+			),
+		};
+
+		bot
+			.send_file(self.chat_id, self.format.file_name(), data.into_bytes())
+			.await
+			.map_err(ExecutionError::Bot)?;
+
+		Ok(())
+	}
+}
+
+
+/// Add the reminders from a file sent along, exported from this or another chat.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ImportReminders {
+	/// The exported file.
+	media: Option<bot::Media>,
+	creator: Option<Creator>,
+	chat_id: bot::ChatId,
+	timezone: Tz,
+}
+
+
+impl ImportReminders {
+	/// Import the events of a calendar sent without a command.
+	pub fn calendar(media: bot::Media, creator: Creator, chat_id: bot::ChatId, timezone: Tz) -> Self {
+		Self {
+			media: Some(media),
+			creator: Some(creator),
+			chat_id,
+			timezone,
+		}
+	}
+
+
+	pub async fn execute<'a, M: Messenger>(
+		self,
+		db: &'a Db,
+		bot: &'a M,
+		messages: &'a config::Messages,
+		now: chrono::DateTime<Utc>,
+	) -> Result<(), ExecutionError> {
+		let media = self.media
+			.as_ref()
+			.filter(
+				|media| media.kind == bot::MediaKind::Document
+			);
+
+		let data = match media {
+			Some(media) => bot
+				.download_file(&media.file_id)
+				.await
+				.map_err(ExecutionError::Bot)?,
+
+			None => {
+				bot
+					.send_message(self.chat_id, messages.invalid_file_message())
+					.await
+					.map_err(ExecutionError::Bot)?;
+
+				return Ok(());
+			}
+		};
+
+		let data = String::from_utf8_lossy(&data);
+
+		let chat = ChatId(self.chat_id);
+
+		let creator_id = self.creator
+			.as_ref()
+			.map(
+				|creator| UserId(creator.id)
+			);
+
+		let parsed = match Format::detect(&data) {
+			Some(Format::Json) => json
+				::import(&data)
+				.map(Imported::Reminders)
+				.map_err(
+					|error| error.to_string()
+				),
+
+			Some(Format::Ics) => ics
+				::import(&data, self.timezone, now)
+				.map(Imported::Calendar)
+				.map_err(
+					|error| error.to_string()
+				),
+
+			None => Err(
+				String::from("unknown format")
+			),
+		};
+
+		let imported = match parsed {
+			Ok(imported) => imported,
+
+			Err(error) => {
+				let message = format!("{}\n{}", messages.invalid_file_message(), error);
+
+				bot
+					.send_message(self.chat_id, &message)
+					.await
+					.map_err(ExecutionError::Bot)?;
+
+				return Ok(());
+			}
+		};
+
+		let reminders: Vec<_> = match &imported {
+			// Replies are to messages in the exported chat, and only the creator of a private
+			// reminder may import it.
+			Imported::Reminders(reminders) => reminders
+				.iter()
+				.filter(
+					|reminder| reminder.recipient.map_or(true, |recipient| Some(recipient) == creator_id)
+				)
+				.map(
+					|reminder| self.imported(
+						NewReminder { source_message: None, ..reminder.as_new() }
+					)
+				)
+				.collect(),
+
+			Imported::Calendar(calendar) => calendar.events
+				.iter()
+				.map(
+					|event| self.imported(
+						event.new_reminder(chat)
+					)
+				)
+				.collect(),
+		};
+
+		let unsupported = match &imported {
+			Imported::Reminders(_) => &[][..],
+			Imported::Calendar(calendar) => &calendar.unsupported[..],
+		};
+
+		let mut sections = Vec::new();
+
+		if !reminders.is_empty() {
+			db
+				.new_reminders(&reminders)
+				.map_err(ExecutionError::Db)?;
+
+			let mut section = String::from(
+				messages.imported_message()
+			);
+
+			for reminder in reminders.iter() {
+				write!(section, "\n{}", reminder.localized(self.timezone))
+					.expect("write should not fail on string");
+			}
+
+			sections.push(section);
+		}
+
+		if !unsupported.is_empty() {
+			let mut section = String::from(
+				messages.unsupported_events_message()
+			);
+
+			for event in unsupported {
+				write!(section, "\n{}", event)
 					.expect("write should not fail on string");
 			}
+
+			sections.push(section);
+		}
+
+		if sections.is_empty() {
+			sections.push(
+				messages.empty_message().into()
+			);
+		}
+
+		let message = sections.join("\n\n");
+
+		bot
+			.send_message(self.chat_id, &message)
+			.await
+			.map_err(ExecutionError::Bot)?;
+
+		Ok(())
+	}
+
+
+	/// The reminder as added to this chat, by the importing user.
+	fn imported<'r>(&'r self, reminder: NewReminder<'r>) -> NewReminder<'r> {
+		NewReminder {
+			chat: ChatId(self.chat_id),
+			creator_id: self.creator
+				.as_ref()
+				.map(
+					|creator| UserId(creator.id)
+				),
+			creator_name: self.creator
+				.as_ref()
+				.map(
+					|creator| creator.name.as_ref()
+				),
+			creator_username: self.creator
+				.as_ref()
+				.and_then(
+					|creator| creator.username.as_deref()
+				),
+			..reminder
+		}
+	}
+}
+
+
+/// Reminders read from an exported file.
+enum Imported {
+	Reminders(Box<[Reminder]>),
+	Calendar(ics::Calendar),
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SnoozeChoice {
+	/// Remind again after the given amount of minutes.
+	Delay(u32),
+	/// Remind again the next day, at the same time of day.
+	Tomorrow,
+	/// Dismiss the reminder.
+	Done,
+}
+
+
+/// A button was pressed on a delivered reminder.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SnoozeReminder {
+	choice: SnoozeChoice,
+	query_id: bot::CallbackQueryId,
+	chat_id: bot::ChatId,
+	message_id: bot::MessageId,
+	message: Box<str>,
+	/// The message the delivered reminder replies to, which the snoozed one will too.
+	source_message: Option<bot::MessageId>,
+	/// The file attached to the delivered reminder.
+	media: Option<bot::Media>,
+	/// The users tagged in the delivered reminder.
+	targets: Box<[bot::Mention]>,
+	/// When the button was pressed.
+	now: chrono::DateTime<Utc>,
+	timezone: Tz,
+}
+
+
+impl SnoozeReminder {
+	#[allow(clippy::too_many_arguments)]
+	pub fn parse(
+		data: &str,
+		query_id: bot::CallbackQueryId,
+		chat_id: bot::ChatId,
+		message_id: bot::MessageId,
+		message: Box<str>,
+		source_message: Option<bot::MessageId>,
+		media: Option<bot::Media>,
+		mentions: Box<[bot::Mention]>,
+		now: chrono::DateTime<Utc>,
+		timezone: Tz,
+	) -> Option<Self> {
+		// The tags come last, and are sent again from the mentions.
+		let (message, targets) = match message.rfind(bot::MENTION_MARKER) {
+			Some(ix) => (message[.. ix].into(), mentions),
+			None => (message, Box::default()),
+		};
+
+		// Don't carry the lateness note over to the snoozed reminder.
+		let message = match message.rfind(LATE_MARKER) {
+			Some(ix) => message[.. ix].into(),
+			None => message,
+		};
+
+		let choice = match data.strip_prefix("snooze ") {
+			Some("tomorrow") => SnoozeChoice::Tomorrow,
+			Some(minutes) => SnoozeChoice::Delay(
+				minutes.parse().ok()?
+			),
+			None if data == "done" => SnoozeChoice::Done,
+			None => return None,
+		};
+
+		Some(
+			Self {
+				choice,
+				query_id,
+				chat_id,
+				message_id,
+				message,
+				source_message,
+				media,
+				targets,
+				now,
+				timezone,
+			}
+		)
+	}
+
+
+	pub async fn execute<'a, M: Messenger>(
+		self,
+		db: &'a Db,
+		bot: &'a M,
+		messages: &'a config::Messages,
+		actor: Actor,
+	) -> Result<(), ExecutionError> {
+		let delivery = db
+			.message_delivery(ChatId(self.chat_id), MessageId(self.message_id))
+			.map_err(ExecutionError::Db)?;
+
+		// Pressing the buttons manages the reminder, like editing or removing it.
+		let forbidden = delivery
+			.as_ref()
+			.map_or(
+				false,
+				|delivery| !actor.may_manage_created_by(delivery.creator_id)
+			);
+
+		if forbidden {
+			bot
+				.answer_callback(&self.query_id, messages.forbidden_message())
+				.await
+				.map_err(ExecutionError::Bot)?;
+
+			return Ok(());
 		}
 
+		let due = match self.choice {
+			SnoozeChoice::Delay(minutes) => Some(
+				DueTimestamp(
+					self.now + Duration::minutes(minutes as i64)
+				)
+			),
+
+			SnoozeChoice::Tomorrow => Some(
+				DueTimestamp::from_local(
+					DueTimestamp(self.now).local(self.timezone) + Duration::days(1),
+					self.timezone
+				)
+			),
+
+			SnoozeChoice::Done => None,
+		};
+
+		let outcome = match due {
+			Some(due) => {
+				// Only the first press snoozes, even when several arrive before the buttons
+				// are gone.
+				let delivery = match delivery {
+					Some(delivery) if delivery.outcome == Outcome::Sent => delivery,
+					_ => {
+						bot
+							.answer_callback(&self.query_id, messages.not_found_message())
+							.await
+							.map_err(ExecutionError::Bot)?;
+
+						return Ok(());
+					}
+				};
+
+				let mentions = mentions(&self.targets);
+
+				let reminder = NewReminder {
+					due,
+					recurrying: None,
+					chat: ChatId(self.chat_id),
+					// The delivered text may have notes, like when it fell back to the chat.
+					message: &delivery.text,
+					recur_until: None,
+					recur_remaining: None,
+					recur_anchor: None,
+					source_message: self.source_message.map(MessageId),
+					media_kind: self.media
+						.as_ref()
+						.map(
+							|media| MediaKind(media.kind)
+						),
+					media_file_id: self.media
+						.as_ref()
+						.map(
+							|media| media.file_id.as_ref()
+						),
+					// Whoever pressed the button, the reminder is still the creator's.
+					creator_id: delivery.creator_id,
+					creator_name: delivery.creator_name.as_deref(),
+					creator_username: delivery.creator_username.as_deref(),
+					mentions: mentions.as_ref(),
+					// Pressed in the chat the reminder was delivered in.
+					recipient: None,
+				};
+
+				db
+					.snooze_delivery(ChatId(self.chat_id), MessageId(self.message_id))
+					.map_err(ExecutionError::Db)?;
+
+				db
+					.new_reminder(&reminder)
+					.map_err(ExecutionError::Db)?;
+
+				format!(
+					"{} {}",
+					messages.snoozed_message(),
+					reminder.due.localized(self.timezone)
+				)
+			}
+
+			None => String::from(
+				messages.done_message()
+			),
+		};
+
 		bot
-			.send_message(self.chat_id, text)
+			.answer_callback(&self.query_id, &outcome)
 			.await
 			.map_err(ExecutionError::Bot)?;
 
+		let text = format!("{}\n\n{}", self.message, outcome);
+
+		let result =
+			if self.media.is_some() {
+				bot
+					.edit_caption(self.chat_id, self.message_id, &text)
+					.await
+			} else {
+				bot
+					.edit_message(self.chat_id, self.message_id, &text)
+					.await
+			};
+
+		result.map_err(ExecutionError::Bot)?;
+
 		Ok(())
 	}
 }
 
 
+/// The occurrence a delivered reminder moves to, skipping occurrences up to `skip_until`,
+/// if it has any left.
+pub fn next_occurrence(
+	reminder: &Reminder,
+	timezone: Tz,
+	skip_until: Option<DueTimestamp>,
+) -> Option<Reminder> {
+	let mut next = reminder.next_occurrence(timezone);
+
+	if let Some(skip_until) = skip_until {
+		while let Some(reminder) = next.as_ref().filter(|next| next.due <= skip_until) {
+			next = reminder.next_occurrence(timezone);
+		}
+	}
+
+	next
+}
+
+
+/// The text of a late delivery, noting how late it is, e.g.: `2h5m`.
+pub fn late_reminder_text(message: &str, late_message: &str, lateness: Duration) -> String {
+	let mut text = format!("{}{}{} ", message, LATE_MARKER, late_message);
+
+	let minutes = lateness.num_minutes();
+
+	let units = [
+		(minutes / (24 * 60), 'd'),
+		(minutes / 60 % 24, 'h'),
+		(minutes % 60, 'm'),
+	];
+
+	for (ammount, unit) in units.iter() {
+		if *ammount > 0 {
+			write!(text, "{}{}", ammount, unit)
+				.expect("write should not fail on string");
+		}
+	}
+
+	if minutes < 1 {
+		text.push_str("0m");
+	}
+
+	text
+}
+
+
+/// The users to be tagged, if any, as stored.
+fn mentions(targets: &[bot::Mention]) -> Option<Mentions> {
+	if targets.is_empty() {
+		return None;
+	}
+
+	Some(
+		Mentions(targets.into())
+	)
+}
+
+
+/// The last instant of the given day, in the given timezone.
+fn end_of_day(date: Date, timezone: Tz) -> DueTimestamp {
+	DueTimestamp::from_local(
+		date.and_hms(23, 59, 59),
+		timezone
+	)
+}
+
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Action {
 	AddReminder(AddReminder),
+	EditReminder(EditReminder),
 	RemoveReminder(RemoveReminder),
 	ListReminders(ListReminders),
+	ListDeliveries(ListDeliveries),
+	SetTimezone(SetTimezone),
+	SetRole(SetRole),
+	SetManagement(SetManagement),
+	CreateInvite(CreateInvite),
+	ExportReminders(ExportReminders),
+	ImportReminders(ImportReminders),
+	SnoozeReminder(SnoozeReminder),
 }
 
 
 impl Action {
+	#[allow(clippy::too_many_arguments)]
 	pub fn parse<'a>(
 		commands: &'a config::Commands,
+		words: &'a config::Words,
 		chat_id: bot::ChatId,
+		timezone: Tz,
+		now: DateTime,
+		reply_to: Option<&'a bot::Reply>,
+		mentions: &'a [bot::Mention],
 		input: &'a str
 	) -> Result<Self, ParseError<'a>> {
-		parser::parse(commands, chat_id, input)
+		parser::parse(commands, words, chat_id, timezone, now, reply_to, mentions, input)
+	}
+
+
+	/// Attach a file to the reminder being added, or the file to import from, if that's the
+	/// action.
+	pub fn with_media(self, media: bot::Media) -> Self {
+		match self {
+			Action::AddReminder(action) => Action::AddReminder(
+				AddReminder { media: Some(media), ..action }
+			),
+			Action::ImportReminders(action) => Action::ImportReminders(
+				ImportReminders { media: Some(media), ..action }
+			),
+			action => action,
+		}
+	}
+
+
+	/// Record who is adding the reminders, if that's the action.
+	pub fn with_creator(self, creator: Creator) -> Self {
+		match self {
+			Action::AddReminder(action) => Action::AddReminder(
+				AddReminder { creator: Some(creator), ..action }
+			),
+			Action::ImportReminders(action) => Action::ImportReminders(
+				ImportReminders { creator: Some(creator), ..action }
+			),
+			action => action,
+		}
+	}
+
+
+	/// Whether executing this action may change when the next reminder is due.
+	pub fn changes_schedule(&self) -> bool {
+		matches!(
+			self,
+			Action::AddReminder(_)
+				| Action::EditReminder(_)
+				| Action::RemoveReminder(_)
+				| Action::ImportReminders(_)
+				| Action::SnoozeReminder(
+					SnoozeReminder { choice: SnoozeChoice::Delay(_), .. }
+						| SnoozeReminder { choice: SnoozeChoice::Tomorrow, .. }
+				)
+		)
 	}
 
 
-	pub async fn execute<'a>(
+	/// Execute the action on behalf of the given user, who may not be allowed to, at the
+	/// given time.
+	pub async fn execute<'a, M: Messenger>(
 		self,
 		db: &'a Db,
-		bot: &'a Bot<'a>,
+		bot: &'a M,
 		messages: &'a config::Messages,
+		actor: Actor,
+		now: chrono::DateTime<Utc>,
 	) -> Result<(), ExecutionError> {
 		match self {
 			Action::AddReminder(action) => action.execute(db, bot, messages).await,
-			Action::RemoveReminder(action) => action.execute(db, bot, messages).await,
+			Action::EditReminder(action) => action.execute(db, bot, messages, actor).await,
+			Action::RemoveReminder(action) => action.execute(db, bot, messages, actor).await,
 			Action::ListReminders(action) => action.execute(db, bot, messages).await,
+			Action::ListDeliveries(action) => action.execute(db, bot, messages).await,
+			Action::SetTimezone(action) => action.execute(db, bot, messages).await,
+			Action::SetRole(action) => action.execute(db, bot, messages, actor).await,
+			Action::SetManagement(action) => action.execute(db, bot, messages, actor).await,
+			Action::CreateInvite(action) => action.execute(db, bot, messages, actor, now).await,
+			Action::ExportReminders(action) => action.execute(db, bot, messages, now).await,
+			Action::ImportReminders(action) => action.execute(db, bot, messages, now).await,
+			Action::SnoozeReminder(action) => action.execute(db, bot, messages, actor).await,
 		}
 	}
 }
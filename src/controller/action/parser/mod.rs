@@ -4,18 +4,36 @@ mod tests;
 use super::{
 	Action,
 	AddReminder,
+	CreateInvite,
+	DueChange,
+	EditReminder,
+	ExportReminders,
+	ImportReminders,
 	RemoveReminder,
+	ListDeliveries,
 	ListReminders,
+	SetManagement,
+	SetRole,
+	SetTimezone,
+	Nth,
 	Recurrence,
-	RecurrenceUnit
+	RecurrenceUnit,
+	Role,
+	WeekdaySet,
+	REPLY_PLACEHOLDER,
 };
 
 use chrono::{
+	Datelike,
+	Duration,
+	Weekday,
 	NaiveDate as Date,
 	NaiveTime as Time,
 	NaiveDateTime as DateTime,
 };
 
+use chrono_tz::Tz;
+
 use combine::{
 	EasyParser,
 	Parser,
@@ -27,14 +45,16 @@ use combine::{
 	eof,
 	error::StreamError,
 	from_str,
+	look_ahead,
 	many1,
-	one_of,
 	optional,
+	satisfy,
+	sep_by1,
 	tokens_cmp,
-	parser::char::{char, digit, space, spaces}
+	parser::char::{char, digit, letter, space, spaces}
 };
 
-use crate::{bot, config};
+use crate::{bot, config, export::Format};
 
 
 pub type ParseError<'a> = combine::easy::ParseError<&'a str>;
@@ -42,21 +62,73 @@ pub type ParseError<'a> = combine::easy::ParseError<&'a str>;
 pub type Result<'a, T> = std::result::Result<T, ParseError<'a>>;
 
 
+/// How many deliveries the history command lists, unless given.
+const HISTORY_DEFAULT_COUNT: u32 = 10;
+
+/// The most deliveries the history command lists, so they fit in a message.
+const HISTORY_MAX_COUNT: u32 = 50;
+
+
+/// Parse an action. Dates are wall-clock times in the given timezone, and so is `now`.
+/// Reminders added in reply to a message may leave out their text. The input's mentions
+/// allow tagging users without a username.
+#[allow(clippy::too_many_arguments)]
 pub fn parse<'a>(
 	commands: &'a config::Commands,
+	words: &'a config::Words,
 	chat_id: bot::ChatId,
+	timezone: Tz,
+	now: DateTime,
+	reply_to: Option<&'a bot::Reply>,
+	mentions: &'a [bot::Mention],
 	input: &'a str,
 ) -> Result<'a, Action> {
 	let action = choice!(
 		attempt(
-			list_command(&commands.list_command, chat_id)
+			list_command(&commands.list_command, chat_id, timezone)
 				.map(Action::ListReminders)
 		),
+		attempt(
+			history_command(&commands.history_command, chat_id, timezone)
+				.map(Action::ListDeliveries)
+		),
+		attempt(
+			timezone_command(&commands.timezone_command, chat_id)
+				.map(Action::SetTimezone)
+		),
+		attempt(
+			edit_command(&commands.edit_command, words, chat_id, timezone, now)
+				.map(Action::EditReminder)
+		),
 		attempt(
 			remove_command(&commands.remove_command, chat_id)
 				.map(Action::RemoveReminder)
 		),
-		add_command(chat_id)
+		attempt(
+			role_command(&commands.grant_command, Role::Admin, chat_id, reply_to, mentions)
+				.map(Action::SetRole)
+		),
+		attempt(
+			role_command(&commands.revoke_command, Role::Member, chat_id, reply_to, mentions)
+				.map(Action::SetRole)
+		),
+		attempt(
+			management_command(&commands.management_command, chat_id)
+				.map(Action::SetManagement)
+		),
+		attempt(
+			invite_command(&commands.invite_command, chat_id)
+				.map(Action::CreateInvite)
+		),
+		attempt(
+			export_command(&commands.export_command, chat_id, timezone)
+				.map(Action::ExportReminders)
+		),
+		attempt(
+			import_command(&commands.import_command, chat_id, timezone)
+				.map(Action::ImportReminders)
+		),
+		add_command(words, chat_id, timezone, now, reply_to, mentions)
 			.map(Action::AddReminder)
 	);
 
@@ -78,7 +150,9 @@ pub fn parse<'a>(
 
 
 fn case_insensitive(c1: char, c2: char) -> bool {
-	c1.eq_ignore_ascii_case(&c2)
+	c1 == c2 || c1
+		.to_lowercase()
+		.eq(c2.to_lowercase())
 }
 
 
@@ -142,6 +216,147 @@ where
 }
 
 
+/// A word, which may be hyphenated, like `segunda-feira`.
+fn word<Input>() -> impl Parser<Input, Output = String>
+where
+	Input: Stream<Token = char>
+{
+	many1(
+		satisfy(
+			|c: char| c.is_alphabetic() || c == '-'
+		)
+	)
+}
+
+
+/// A word for a day, like `today`, `tomorrow` or `friday`, resolved relative to `today`.
+/// Weekdays resolve to their next occurrence, excluding today.
+fn day<'a, Input: 'a>(
+	words: &'a config::Words,
+	today: Date,
+) -> impl Parser<Input, Output = Date> + 'a
+where
+	Input: Stream<Token = char>
+{
+	(
+		spaces(),
+		word(),
+	)
+		.and_then(
+			move |(_, word)| {
+				let word = word.to_lowercase();
+
+				resolve_day(words, &word, today)
+					.ok_or(
+						<Input::Error as combine::ParseError<_, _, _>>::StreamError::unexpected_format("invalid day")
+					)
+			}
+		)
+}
+
+
+fn resolve_day(words: &config::Words, word: &str, today: Date) -> Option<Date> {
+	let matches = |list: &[Box<str>]| list
+		.iter()
+		.any(
+			|w| w.to_lowercase() == word
+		);
+
+	if word == "today" || matches(&words.today) {
+		return Some(today);
+	}
+
+	if word == "tomorrow" || matches(&words.tomorrow) {
+		return today.succ_opt();
+	}
+
+	let weekday = resolve_weekday(words, word)?;
+
+	let days_ahead = (
+		weekday.num_days_from_monday() + 7 - today.weekday().num_days_from_monday()
+	) % 7;
+
+	let days_ahead =
+		if days_ahead == 0 {
+			7
+		} else {
+			days_ahead
+		};
+
+	today.checked_add_signed(
+		Duration::days(days_ahead as i64)
+	)
+}
+
+
+/// An english weekday name, like `fri` or `friday`, or one of the configured weekday words.
+fn resolve_weekday(words: &config::Words, word: &str) -> Option<Weekday> {
+	let weekdays = &words.weekdays;
+
+	word
+		.parse::<Weekday>()
+		.ok()
+		.or_else(
+			|| [
+				(Weekday::Mon, &weekdays.monday),
+				(Weekday::Tue, &weekdays.tuesday),
+				(Weekday::Wed, &weekdays.wednesday),
+				(Weekday::Thu, &weekdays.thursday),
+				(Weekday::Fri, &weekdays.friday),
+				(Weekday::Sat, &weekdays.saturday),
+				(Weekday::Sun, &weekdays.sunday),
+			]
+				.iter()
+				.find(
+					|(_, list)| list
+						.iter()
+						.any(
+							|w| w.to_lowercase() == word
+						)
+				)
+				.map(
+					|(weekday, _)| *weekday
+				)
+		)
+}
+
+
+/// Either an ISO date or a day word.
+fn any_date<'a, Input: 'a>(
+	words: &'a config::Words,
+	today: Date,
+) -> impl Parser<Input, Output = Date> + 'a
+where
+	Input: Stream<Token = char>
+{
+	choice!(
+		attempt(date()),
+		day(words, today)
+	)
+}
+
+
+/// A datetime relative to `now`, e.g.: `in 2h30m`.
+fn relative<Input>(now: DateTime) -> impl Parser<Input, Output = DateTime>
+where
+	Input: Stream<Token = char>
+{
+	(
+		spaces(),
+		tokens_cmp("in".chars(), case_insensitive),
+		space(),
+		duration(),
+	)
+		.and_then(
+			move |(_, _, _, offset)| now
+				.checked_add_signed(offset)
+				.ok_or(
+					<Input::Error as combine::ParseError<_, _, _>>::StreamError::unexpected_format("invalid offset")
+				)
+		)
+}
+
+
 fn time<Input>() -> impl Parser<Input, Output = Time>
 where
 	Input: Stream<Token = char>
@@ -162,7 +377,51 @@ where
 }
 
 
-fn recurrence<Input>() -> impl Parser<Input, Output = Recurrence>
+/// A compound offset, like `2h30m`, `1w 2d` or `3 days`.
+fn duration<Input>() -> impl Parser<Input, Output = Duration>
+where
+	Input: Stream<Token = char>
+{
+	let component = (
+		spaces(),
+		fixed_width_u32(1, 4),
+		spaces(),
+		many1::<String, _, _>(letter()),
+	)
+		.and_then(
+			|(_, ammount, _, unit)| {
+				let ammount = ammount as i64;
+
+				match unit.as_str() {
+					"m" | "min" | "mins" | "minute" | "minutes" => Ok(Duration::minutes(ammount)),
+					"h" | "hour" | "hours" => Ok(Duration::hours(ammount)),
+					"d" | "day" | "days" => Ok(Duration::days(ammount)),
+					"w" | "week" | "weeks" => Ok(Duration::weeks(ammount)),
+
+					_ => Err(
+						<Input::Error as combine::ParseError<_, _, _>>::StreamError::unexpected_format("invalid duration unit")
+					)
+				}
+			}
+		);
+
+	many1::<Vec<_>, _, _>(
+		attempt(component)
+	)
+		.map(
+			|components| components
+				.into_iter()
+				.fold(
+					Duration::zero(),
+					|total, component| total + component
+				)
+		)
+}
+
+
+/// A recurrence rule: a period, like `+2d`, a set of weekdays, like `+mon,wed,fri`, or a
+/// weekday of the month, like `+2tue` or `+lastfri`.
+fn recurrence<'a, Input: 'a>(words: &'a config::Words) -> impl Parser<Input, Output = Recurrence> + 'a
 where
 	Input: Stream<Token = char>
 {
@@ -170,9 +429,11 @@ where
 		::StreamError
 		::unexpected_format(message);
 
-	let validate_recurrence = move |max, recurrence: Recurrence| {
-		if recurrence.ammount < max {
-			Ok(recurrence)
+	let validate_period = move |max, ammount, unit| {
+		if ammount > 0 && ammount < max {
+			Ok(
+				Recurrence::Period { ammount, unit }
+			)
 		}
 		else {
 			Err(
@@ -181,40 +442,132 @@ where
 		}
 	};
 
-	let units = "hdwmy".chars();
-
 	(
 		spaces(),
 		char('+'),
 		optional(
 			fixed_width_u32(1, 2)
 		),
-		one_of(units)
+		sep_by1::<Vec<String>, _, _, _>(
+			word(),
+			satisfy(
+				|c: char| c == ',' || c == '/'
+			)
+		),
 	)
 		.and_then(
-			move |(_, _, num, unit)| {
-				let ammount = num.unwrap_or(1) as u8;
+			move |(_, _, num, rule)| {
+				let rule = rule.join(",");
+				let mut chars = rule.chars();
 
-				match unit {
-					'm' => validate_recurrence(90, Recurrence { ammount, unit: RecurrenceUnit::Minutes }),
-					'h' => validate_recurrence(24, Recurrence { ammount, unit: RecurrenceUnit::Hours }),
-					'd' => validate_recurrence(99, Recurrence { ammount, unit: RecurrenceUnit::Days }),
-					'w' => validate_recurrence(10, Recurrence { ammount, unit: RecurrenceUnit::Weeks }),
-					'M' => validate_recurrence(64, Recurrence { ammount, unit: RecurrenceUnit::Months }),
-					'y' => validate_recurrence(10, Recurrence { ammount, unit: RecurrenceUnit::Years }),
+				if let (Some(unit), None) = (chars.next(), chars.next()) {
+					let ammount = num.unwrap_or(1) as u8;
 
-					_ => Err(
-						error("failed to parse recurrence")
-					)
+					return match RecurrenceUnit::from_char(unit) {
+						Some(unit @ RecurrenceUnit::Minutes) => validate_period(90, ammount, unit),
+						Some(unit @ RecurrenceUnit::Hours)   => validate_period(24, ammount, unit),
+						Some(unit @ RecurrenceUnit::Days)    => validate_period(99, ammount, unit),
+						Some(unit @ RecurrenceUnit::Weeks)   => validate_period(10, ammount, unit),
+						Some(unit @ RecurrenceUnit::Months)  => validate_period(64, ammount, unit),
+						Some(unit @ RecurrenceUnit::Years)   => validate_period(10, ammount, unit),
+
+						None => Err(
+							error("failed to parse recurrence")
+						),
+					};
+				}
+
+				let rule = rule.to_lowercase();
+
+				let weekday = |word: &str| resolve_weekday(words, word)
+					.ok_or_else(
+						|| error("invalid weekday")
+					);
+
+				match num {
+					Some(nth) if (1 ..= 5).contains(&nth) => Ok(
+						Recurrence::MonthlyWeekday { nth: Nth::Nth(nth as u8), weekday: weekday(&rule)? }
+					),
+
+					Some(_) => Err(
+						error("invalid weekday of the month")
+					),
+
+					None => match rule.strip_prefix("last") {
+						Some(last) => Ok(
+							Recurrence::MonthlyWeekday { nth: Nth::Last, weekday: weekday(last)? }
+						),
+
+						None => Ok(
+							Recurrence::Weekdays(
+								rule
+									.split(',')
+									.map(weekday)
+									.collect::<std::result::Result<WeekdaySet, _>>()?
+							)
+						),
+					},
 				}
 			}
 		)
 }
 
 
+/// A recurrence followed by its optional end conditions: an occurrence count, like `x6`,
+/// and a last date, like `until 2021-06-01`.
+fn limited_recurrence<'a, Input: 'a>(
+	words: &'a config::Words,
+	today: Date,
+) -> impl Parser<Input, Output = (Recurrence, Option<u32>, Option<Date>)> + 'a
+where
+	Input: Stream<Token = char>
+{
+	let count = (
+		char('x'),
+		fixed_width_u32(1, 4),
+	)
+		.and_then(
+			|(_, count)| {
+				if count > 0 {
+					Ok(count)
+				}
+				else {
+					Err(
+						<Input::Error as combine::ParseError<_, _, _>>::StreamError::unexpected_format("invalid count")
+					)
+				}
+			}
+		);
+
+	let until = (
+		tokens_cmp("until".chars(), case_insensitive),
+		space(),
+		any_date(words, today),
+	)
+		.map(
+			|(_, _, date)| date
+		);
+
+	(
+		recurrence(words),
+		optional(
+			attempt(
+				space().with(count)
+			)
+		),
+		optional(
+			attempt(
+				space().with(until)
+			)
+		),
+	)
+}
+
+
 fn list_command<'a, Input: 'a>(
 	command: &'a str,
-	chat_id: bot::ChatId
+	chat_id: bot::ChatId,
+	timezone: Tz,
 ) -> impl Parser<Input, Output = ListReminders> + 'a
 where
 	Input: Stream<Token = char>
@@ -224,12 +577,94 @@ where
 		tokens_cmp(command.chars(), case_insensitive),
 	)
 		.map(
-			move |_| ListReminders { chat_id }
+			move |_| ListReminders { chat_id, timezone }
+		)
+}
+
+
+/// List the latest deliveries in the chat, e.g.: `historico`, or a given number of them,
+/// at least one, and up to `HISTORY_MAX_COUNT`: `historico 20`.
+fn history_command<'a, Input: 'a>(
+	command: &'a str,
+	chat_id: bot::ChatId,
+	timezone: Tz,
+) -> impl Parser<Input, Output = ListDeliveries> + 'a
+where
+	Input: Stream<Token = char>
+{
+	(
+		spaces(),
+		tokens_cmp(command.chars(), case_insensitive),
+		optional(
+			attempt(
+				(
+					space(),
+					spaces(),
+					from_str(
+						many1::<String, _, _>(digit())
+					)
+						.and_then(
+							|count: u32| if count > 0 {
+								Ok(count)
+							} else {
+								Err(
+									<Input::Error as combine::ParseError<_, _, _>>::StreamError::unexpected_format("no deliveries")
+								)
+							}
+						),
+				)
+			)
+		),
+	)
+		.map(
+			move |(_, _, count): (_, _, Option<(_, _, u32)>)| ListDeliveries {
+				chat_id,
+				count: count.map_or(HISTORY_DEFAULT_COUNT, |(_, _, count)| count.min(HISTORY_MAX_COUNT)),
+				timezone,
+			}
+		)
+}
+
+
+fn timezone_command<'a, Input: 'a>(
+	command: &'a str,
+	chat_id: bot::ChatId,
+) -> impl Parser<Input, Output = SetTimezone> + 'a
+where
+	Input: Stream<Token = char>
+{
+	(
+		spaces(),
+		tokens_cmp(command.chars(), case_insensitive),
+		space(),
+		spaces(),
+		many1::<String, _, _>(
+			satisfy(
+				|c: char| !c.is_whitespace()
+			)
+		),
+	)
+		.and_then(
+			move |(_, _, _, _, name)| name
+				.parse::<Tz>()
+				.map(
+					|timezone| SetTimezone { chat_id, timezone }
+				)
+				.map_err(
+					|_| <Input::Error as combine::ParseError<_, _, _>>::StreamError::unexpected_format("invalid timezone")
+				)
 		)
 }
 
 
-fn add_command<Input>(chat_id: bot::ChatId) -> impl Parser<Input, Output = AddReminder>
+fn add_command<'a, Input: 'a>(
+	words: &'a config::Words,
+	chat_id: bot::ChatId,
+	timezone: Tz,
+	now: DateTime,
+	reply_to: Option<&'a bot::Reply>,
+	mentions: &'a [bot::Mention],
+) -> impl Parser<Input, Output = AddReminder> + 'a
 where
 	Input: Stream<Token = char>
 {
@@ -246,35 +681,288 @@ where
 			)
 		);
 
+	let absolute = (
+		any_date(words, now.date()),
+		opt_time,
+	)
+		.map(
+			|(date, time)| DateTime::new(date, time)
+		);
+
 	let opt_rec = optional(
 		attempt(
 			space() // Require a space first to separate from the date/time.
-				.with(recurrence())
+				.with(limited_recurrence(words, now.date()))
+		)
+	);
+
+	// Deliver in the creator's private chat, e.g.: `in 1h dm take the pills`.
+	let opt_private = optional(
+		attempt(
+			space()
+				.with(tokens_cmp("dm".chars(), case_insensitive))
+				.skip(
+					look_ahead(
+						space()
+							.map(drop)
+							.or(eof())
+					)
+				)
+		)
+	)
+		.map(
+			|private| private.is_some()
+		);
+
+	let opt_msg = optional(
+		attempt(
+			space() // Require a space first to separate from the date/time/recurrence.
+				.with(many1::<String, _, _>(any()))
 		)
 	);
 
 	(
 		spaces(),
-		date(),
-		opt_time,
+		choice!(
+			attempt(relative(now)),
+			absolute
+		),
 		opt_rec,
-		space(),
-		many1::<String, _, _>(any())
+		opt_private,
+		opt_msg,
 	)
-		.map(
-			move |(_, date, time, rec, _, mut msg)| {
-				msg.truncate(
+		.and_then(
+			move |(_, due, rec, private, msg)| {
+				let (targets, msg) = split_targets(
 					msg
-						.trim_end()
-						.len()
+						.as_deref()
+						.unwrap_or_default()
+						.trim_end(),
+					mentions
 				);
 
-				AddReminder {
-					due: DateTime::new(date, time),
-					recurrence: rec,
-					message: msg.into(),
-					chat_id,
+				let msg = Some(msg).filter(
+					|msg| !msg.is_empty()
+				);
+
+				// Without text, the reminder is about the message being replied to.
+				let (message, source_message) = match (msg, reply_to) {
+					(Some(msg), _) => (msg.into(), None),
+
+					(None, Some(reply)) => (
+						reply.text
+							.clone()
+							.unwrap_or_else(
+								|| REPLY_PLACEHOLDER.into()
+							),
+						Some(reply.message_id)
+					),
+
+					(None, None) => return Err(
+						<Input::Error as combine::ParseError<_, _, _>>::StreamError::unexpected_format("missing message")
+					),
+				};
+
+				let (recurrence, count, until) = match rec {
+					Some((recurrence, count, until)) => (Some(recurrence), count, until),
+					None => (None, None, None),
+				};
+
+				Ok(
+					AddReminder {
+						due,
+						recurrence,
+						count,
+						until,
+						message,
+						source_message,
+						media: None,
+						targets: targets.into_boxed_slice(),
+						creator: None,
+						private,
+						chat_id,
+						timezone,
+					}
+				)
+			}
+		)
+}
+
+
+/// Split the users to be tagged off the start of a reminder's message: `@username`s, and
+/// the names of the given mentions of users without one.
+fn split_targets<'m>(message: &'m str, mentions: &[bot::Mention]) -> (Vec<bot::Mention>, &'m str) {
+	let mut targets = Vec::new();
+	let mut rest = message;
+
+	while let Some((target, len)) = target(rest, mentions) {
+		let after = &rest[len ..];
+
+		// Must be a whole word.
+		if !after.is_empty() && !after.starts_with(char::is_whitespace) {
+			break;
+		}
+
+		targets.push(target);
+		rest = after.trim_start();
+	}
+
+	(targets, rest)
+}
+
+
+/// The user tagged at the start of the text, and the length of the tag.
+fn target(text: &str, mentions: &[bot::Mention]) -> Option<(bot::Mention, usize)> {
+	if let Some(username) = text.strip_prefix('@') {
+		let len = username
+			.find(
+				|c: char| !(c.is_ascii_alphanumeric() || c == '_')
+			)
+			.unwrap_or(
+				username.len()
+			);
+
+		if len == 0 {
+			return None;
+		}
+
+		return Some(
+			(bot::Mention::Username(username[.. len].into()), 1 + len)
+		);
+	}
+
+	mentions
+		.iter()
+		.find_map(
+			|mention| match mention {
+				bot::Mention::User { name, .. } if !name.is_empty() && text.starts_with(name.as_ref()) => Some(
+					(mention.clone(), name.len())
+				),
+				_ => None,
+			}
+		)
+}
+
+
+fn edit_command<'a, Input: 'a>(
+	command: &'a str,
+	words: &'a config::Words,
+	chat_id: bot::ChatId,
+	timezone: Tz,
+	now: DateTime,
+) -> impl Parser<Input, Output = EditReminder> + 'a
+where
+	Input: Stream<Token = char>
+{
+	let due = choice!(
+		attempt(relative(now))
+			.map(DueChange::DateTime),
+		attempt(
+			(
+				any_date(words, now.date()),
+				optional(
+					attempt(
+						space() // Require a space first to separate from the date.
+							.with(time())
+					)
+				),
+			)
+		)
+			.map(
+				|(date, time)| DueChange::Date(date, time)
+			),
+		time()
+			.map(DueChange::Time)
+	);
+
+	// `+once` removes the recurrence.
+	let recurrence_change = choice!(
+		attempt(
+			tokens_cmp("+once".chars(), case_insensitive)
+				.skip(
+					look_ahead(
+						space()
+							.map(drop)
+							.or(eof())
+					)
+				)
+		)
+			.map(
+				|_| None
+			),
+		limited_recurrence(words, now.date())
+			.map(Some)
+	);
+
+	(
+		spaces(),
+		tokens_cmp(command.chars(), case_insensitive),
+		spaces(),
+		from_str(
+			many1::<String, _, _>(
+				digit()
+			)
+		),
+		optional(
+			attempt(
+				space().with(due)
+			)
+		),
+		optional(
+			attempt(
+				space().with(recurrence_change)
+			)
+		),
+		optional(
+			attempt(
+				space().with(
+					many1::<String, _, _>(any())
+				)
+			)
+		),
+	)
+		.and_then(
+			move |(_, _, _, reminder_id, due, rec, message)| {
+				let message = message
+					.map(
+						|mut msg| {
+							msg.truncate(
+								msg
+									.trim_end()
+									.len()
+							);
+
+							msg
+						}
+					)
+					.filter(
+						|msg| !msg.is_empty()
+					);
+
+				if due.is_none() && rec.is_none() && message.is_none() {
+					return Err(
+						<Input::Error as combine::ParseError<_, _, _>>::StreamError::unexpected_format("nothing to edit")
+					);
 				}
+
+				let (recurrence, count, until) = match rec {
+					Some(Some((recurrence, count, until))) => (Some(Some(recurrence)), count, until),
+					Some(None) => (Some(None), None, None),
+					None => (None, None, None),
+				};
+
+				Ok(
+					EditReminder {
+						reminder_id,
+						due,
+						recurrence,
+						count,
+						until,
+						message: message.map(Into::into),
+						chat_id,
+						timezone,
+					}
+				)
 			}
 		)
 }
@@ -301,3 +989,162 @@ where
 			move |(_, _, _, reminder_id)| RemoveReminder { reminder_id, chat_id }
 		)
 }
+
+
+/// Give the mentioned user a role, or the user who sent the replied message, if no one is
+/// mentioned.
+fn role_command<'a, Input: 'a>(
+	command: &'a str,
+	role: Role,
+	chat_id: bot::ChatId,
+	reply_to: Option<&'a bot::Reply>,
+	mentions: &'a [bot::Mention],
+) -> impl Parser<Input, Output = SetRole> + 'a
+where
+	Input: Stream<Token = char>
+{
+	(
+		spaces(),
+		tokens_cmp(command.chars(), case_insensitive),
+		optional(
+			attempt(
+				space() // The mentioned user's name.
+					.with(many1::<String, _, _>(any()))
+			)
+		),
+	)
+		.and_then(
+			move |(_, _, mentioned)| {
+				// In groups, the bot's own mention comes first, and isn't a user's.
+				let candidates = match mentioned {
+					Some(_) => mentions
+						.iter()
+						.find(
+							|mention| matches!(mention, bot::Mention::User { .. })
+						),
+					None => reply_to.and_then(
+						|reply| reply.author.as_ref()
+					),
+				};
+
+				// Users can only be told apart by id.
+				match candidates {
+					Some(bot::Mention::User { id, name }) => Ok(
+						SetRole {
+							chat_id,
+							user_id: *id,
+							name: name.clone(),
+							role,
+						}
+					),
+
+					_ => Err(
+						<Input::Error as combine::ParseError<_, _, _>>::StreamError::unexpected_format("missing user")
+					),
+				}
+			}
+		)
+}
+
+
+/// Create a one-time code that authenticates another chat, e.g.: `convite`.
+fn invite_command<'a, Input: 'a>(
+	command: &'a str,
+	chat_id: bot::ChatId,
+) -> impl Parser<Input, Output = CreateInvite> + 'a
+where
+	Input: Stream<Token = char>
+{
+	(
+		spaces(),
+		tokens_cmp(command.chars(), case_insensitive),
+	)
+		.map(
+			move |_| CreateInvite { chat_id }
+		)
+}
+
+
+/// Send the chat's reminders as JSON, e.g.: `exporta`, or in another format: `exporta ics`.
+fn export_command<'a, Input: 'a>(
+	command: &'a str,
+	chat_id: bot::ChatId,
+	timezone: Tz,
+) -> impl Parser<Input, Output = ExportReminders> + 'a
+where
+	Input: Stream<Token = char>
+{
+	(
+		spaces(),
+		tokens_cmp(command.chars(), case_insensitive),
+		optional(
+			attempt(
+				(
+					space(),
+					spaces(),
+					from_str(
+						many1::<String, _, _>(letter())
+					),
+				)
+			)
+		),
+	)
+		.map(
+			move |(_, _, format): (_, _, Option<(_, _, Format)>)| ExportReminders {
+				chat_id,
+				format: format.map_or(Format::Json, |(_, _, format)| format),
+				timezone,
+			}
+		)
+}
+
+
+/// Add the reminders from the file this is the caption of, e.g.: `importa`.
+fn import_command<'a, Input: 'a>(
+	command: &'a str,
+	chat_id: bot::ChatId,
+	timezone: Tz,
+) -> impl Parser<Input, Output = ImportReminders> + 'a
+where
+	Input: Stream<Token = char>
+{
+	(
+		spaces(),
+		tokens_cmp(command.chars(), case_insensitive),
+	)
+		.map(
+			move |_| ImportReminders { media: None, creator: None, chat_id, timezone }
+		)
+}
+
+
+/// Let every member manage every reminder, e.g.: `geral on`, or only admins: `geral off`.
+fn management_command<'a, Input: 'a>(
+	command: &'a str,
+	chat_id: bot::ChatId,
+) -> impl Parser<Input, Output = SetManagement> + 'a
+where
+	Input: Stream<Token = char>
+{
+	(
+		spaces(),
+		tokens_cmp(command.chars(), case_insensitive),
+		space(),
+		spaces(),
+		choice!(
+			attempt(
+				tokens_cmp("on".chars(), case_insensitive)
+					.map(
+						|_| true
+					)
+			),
+			tokens_cmp("off".chars(), case_insensitive)
+				.map(
+					|_| false
+				)
+		),
+	)
+		.map(
+			move |(_, _, _, _, everyone_manages)| SetManagement { chat_id, everyone_manages }
+		)
+}
@@ -1,108 +1,979 @@
 use super::*;
 
 
+fn commands() -> config::Commands {
+	config::Commands {
+		edit_command: Box::new("muda".into()),
+		remove_command: Box::new("cancela".into()),
+		list_command: Box::new("chora".into()),
+		timezone_command: Box::new("tz".into()),
+		grant_command: Box::new("promove".into()),
+		revoke_command: Box::new("rebaixa".into()),
+		management_command: Box::new("geral".into()),
+		invite_command: Box::new("convite".into()),
+		export_command: Box::new("exporta".into()),
+		import_command: Box::new("importa".into()),
+		history_command: Box::new("historico".into()),
+	}
+}
+
+
+fn words() -> config::Words {
+	let list = |words: &[&str]| words
+		.iter()
+		.map(
+			|&word| word.into()
+		)
+		.collect();
+
+	config::Words {
+		today: list(&["hoje"]),
+		tomorrow: list(&["amanhã"]),
+		weekdays: config::Weekdays {
+			monday: list(&["segunda"]),
+			tuesday: list(&["terça"]),
+			wednesday: list(&["quarta"]),
+			thursday: list(&["quinta"]),
+			friday: list(&["sexta", "sexta-feira"]),
+			saturday: list(&["sábado"]),
+			sunday: list(&["domingo"]),
+		},
+	}
+}
+
+
+// A monday.
+fn now() -> DateTime {
+	datetime("2021-03-01 10:00")
+}
+
+
+fn date(str: &str) -> DateTime {
+	Date
+		::parse_from_str(str, "%Y-%m-%d")
+		.expect("invalid date")
+		.and_hms(0, 0, 0)
+}
+
+
+fn datetime(str: &str) -> DateTime {
+	DateTime
+		::parse_from_str(str, "%Y-%m-%d %H:%M")
+		.expect("invalid datetime")
+}
+
+
 // TODO: add more tests
 
 #[test]
 fn test_add() {
+	let commands = commands();
+	let words = words();
 	let chat_id = 0.into();
 
 	let parse = |input| super
-		::parse(chat_id, input)
+		::parse(&commands, &words, chat_id, Tz::UTC, now(), None, &[], input)
 		.expect("parse failed");
 
-	let date = |str| Date
-		::parse_from_str(str, "%Y-%m-%d")
-		.expect("invalid date")
-		.and_hms(0, 0, 0);
-
-	let datetime = |str| DateTime
-		::parse_from_str(str, "%Y-%m-%d %H:%M")
-		.expect("invalid datetime");
-
 	assert_eq!(
 		parse("2020-02-03 hey"),
-		Action::AddReminder {
-			due: date("2020-02-03"),
-			recurrence: None,
-			message: "hey".into(),
-			chat_id
-		}
+		Action::AddReminder(
+			AddReminder {
+				due: date("2020-02-03"),
+				recurrence: None,
+				count: None,
+				until: None,
+				message: "hey".into(),
+				source_message: None,
+				media: None,
+				targets: Box::default(),
+				creator: None,
+				private: false,
+				chat_id,
+				timezone: Tz::UTC,
+			}
+		)
 	);
 
 	assert_eq!(
 		parse("2020-03-02 hey ho"),
-		Action::AddReminder {
-			due: date("2020-03-02"),
-			recurrence: None,
-			message: "hey ho".into(),
-			chat_id
-		}
+		Action::AddReminder(
+			AddReminder {
+				due: date("2020-03-02"),
+				recurrence: None,
+				count: None,
+				until: None,
+				message: "hey ho".into(),
+				source_message: None,
+				media: None,
+				targets: Box::default(),
+				creator: None,
+				private: false,
+				chat_id,
+				timezone: Tz::UTC,
+			}
+		)
 	);
 
 	assert_eq!(
 		parse("2020-02-03 00:00 hey"),
-		Action::AddReminder {
-			due: datetime("2020-02-03 00:00"),
-			recurrence: None,
-			message: "hey".into(),
-			chat_id
-		}
+		Action::AddReminder(
+			AddReminder {
+				due: datetime("2020-02-03 00:00"),
+				recurrence: None,
+				count: None,
+				until: None,
+				message: "hey".into(),
+				source_message: None,
+				media: None,
+				targets: Box::default(),
+				creator: None,
+				private: false,
+				chat_id,
+				timezone: Tz::UTC,
+			}
+		)
 	);
 
 	assert_eq!(
 		parse("2020-02-03 23:59 hey"),
-		Action::AddReminder {
-			due: datetime("2020-02-03 23:59"),
+		Action::AddReminder(
+			AddReminder {
+				due: datetime("2020-02-03 23:59"),
+				recurrence: None,
+				count: None,
+				until: None,
+				message: "hey".into(),
+				source_message: None,
+				media: None,
+				targets: Box::default(),
+				creator: None,
+				private: false,
+				chat_id,
+				timezone: Tz::UTC,
+			}
+		)
+	);
+}
+
+
+#[test]
+fn test_add_relative() {
+	let commands = commands();
+	let words = words();
+	let chat_id = 0.into();
+
+	let parse = |input| super
+		::parse(&commands, &words, chat_id, Tz::UTC, now(), None, &[], input)
+		.expect("parse failed");
+
+	assert_eq!(
+		parse("in 20m hey"),
+		Action::AddReminder(
+			AddReminder {
+				due: datetime("2021-03-01 10:20"),
+				recurrence: None,
+				count: None,
+				until: None,
+				message: "hey".into(),
+				source_message: None,
+				media: None,
+				targets: Box::default(),
+				creator: None,
+				private: false,
+				chat_id,
+				timezone: Tz::UTC,
+			}
+		)
+	);
+
+	assert_eq!(
+		parse("in 2h30m hey"),
+		Action::AddReminder(
+			AddReminder {
+				due: datetime("2021-03-01 12:30"),
+				recurrence: None,
+				count: None,
+				until: None,
+				message: "hey".into(),
+				source_message: None,
+				media: None,
+				targets: Box::default(),
+				creator: None,
+				private: false,
+				chat_id,
+				timezone: Tz::UTC,
+			}
+		)
+	);
+
+	assert_eq!(
+		parse("in 3 days hey ho"),
+		Action::AddReminder(
+			AddReminder {
+				due: datetime("2021-03-04 10:00"),
+				recurrence: None,
+				count: None,
+				until: None,
+				message: "hey ho".into(),
+				source_message: None,
+				media: None,
+				targets: Box::default(),
+				creator: None,
+				private: false,
+				chat_id,
+				timezone: Tz::UTC,
+			}
+		)
+	);
+
+	assert_eq!(
+		parse("IN 1w2d hey"),
+		Action::AddReminder(
+			AddReminder {
+				due: datetime("2021-03-10 10:00"),
+				recurrence: None,
+				count: None,
+				until: None,
+				message: "hey".into(),
+				source_message: None,
+				media: None,
+				targets: Box::default(),
+				creator: None,
+				private: false,
+				chat_id,
+				timezone: Tz::UTC,
+			}
+		)
+	);
+
+	assert_eq!(
+		parse("in 1d +1w water plants"),
+		Action::AddReminder(
+			AddReminder {
+				due: datetime("2021-03-02 10:00"),
+				recurrence: Some(
+					Recurrence::Period { ammount: 1, unit: RecurrenceUnit::Weeks }
+				),
+				count: None,
+				until: None,
+				message: "water plants".into(),
+				source_message: None,
+				media: None,
+				targets: Box::default(),
+				creator: None,
+				private: false,
+				chat_id,
+				timezone: Tz::UTC,
+			}
+		)
+	);
+}
+
+
+#[test]
+fn test_add_relative_invalid() {
+	let commands = commands();
+	let words = words();
+	let chat_id = 0.into();
+
+	let parse = |input| super::parse(&commands, &words, chat_id, Tz::UTC, now(), None, &[], input);
+
+	assert!(parse("in 2 hey").is_err());
+	assert!(parse("in 2x hey").is_err());
+	assert!(parse("in hey").is_err());
+	assert!(parse("in 2h").is_err());
+}
+
+
+#[test]
+fn test_add_recurrence_limits() {
+	let commands = commands();
+	let words = words();
+	let chat_id = 0.into();
+
+	let parse = |input| super
+		::parse(&commands, &words, chat_id, Tz::UTC, now(), None, &[], input)
+		.expect("parse failed");
+
+	let weekly = Some(
+		Recurrence::Period { ammount: 1, unit: RecurrenceUnit::Weeks }
+	);
+
+	assert_eq!(
+		parse("2021-03-02 +1w x6 water plants"),
+		Action::AddReminder(
+			AddReminder {
+				due: date("2021-03-02"),
+				recurrence: weekly,
+				count: Some(6),
+				until: None,
+				message: "water plants".into(),
+				source_message: None,
+				media: None,
+				targets: Box::default(),
+				creator: None,
+				private: false,
+				chat_id,
+				timezone: Tz::UTC,
+			}
+		)
+	);
+
+	assert_eq!(
+		parse("2021-03-02 +1w until 2021-06-01 water plants"),
+		Action::AddReminder(
+			AddReminder {
+				due: date("2021-03-02"),
+				recurrence: weekly,
+				count: None,
+				until: Some(date("2021-06-01").date()),
+				message: "water plants".into(),
+				source_message: None,
+				media: None,
+				targets: Box::default(),
+				creator: None,
+				private: false,
+				chat_id,
+				timezone: Tz::UTC,
+			}
+		)
+	);
+
+	assert_eq!(
+		parse("in 1d +1w x2 until sexta xylophone"),
+		Action::AddReminder(
+			AddReminder {
+				due: datetime("2021-03-02 10:00"),
+				recurrence: weekly,
+				count: Some(2),
+				until: Some(date("2021-03-05").date()),
+				message: "xylophone".into(),
+				source_message: None,
+				media: None,
+				targets: Box::default(),
+				creator: None,
+				private: false,
+				chat_id,
+				timezone: Tz::UTC,
+			}
+		)
+	);
+
+	// Without a recurrence, these are part of the message.
+	assert_eq!(
+		parse("2021-03-02 x6 hey"),
+		Action::AddReminder(
+			AddReminder {
+				due: date("2021-03-02"),
+				recurrence: None,
+				count: None,
+				until: None,
+				message: "x6 hey".into(),
+				source_message: None,
+				media: None,
+				targets: Box::default(),
+				creator: None,
+				private: false,
+				chat_id,
+				timezone: Tz::UTC,
+			}
+		)
+	);
+
+	assert_eq!(
+		parse("2021-03-02 +1w x0 hey"),
+		Action::AddReminder(
+			AddReminder {
+				due: date("2021-03-02"),
+				recurrence: weekly,
+				count: None,
+				until: None,
+				message: "x0 hey".into(),
+				source_message: None,
+				media: None,
+				targets: Box::default(),
+				creator: None,
+				private: false,
+				chat_id,
+				timezone: Tz::UTC,
+			}
+		)
+	);
+}
+
+
+#[test]
+fn test_add_weekday_recurrence() {
+	let commands = commands();
+	let words = words();
+	let chat_id = 0.into();
+
+	let parse = |input| super
+		::parse(&commands, &words, chat_id, Tz::UTC, now(), None, &[], input)
+		.map(
+			|action| match action {
+				Action::AddReminder(reminder) => reminder.recurrence,
+				action => panic!("unexpected action: {:?}", action),
+			}
+		);
+
+	let weekdays = |weekdays: &[Weekday]| Some(
+		Recurrence::Weekdays(
+			weekdays
+				.iter()
+				.copied()
+				.collect()
+		)
+	);
+
+	assert_eq!(
+		parse("2021-03-01 09:00 +mon,wed,fri standup"),
+		Ok(weekdays(&[Weekday::Mon, Weekday::Wed, Weekday::Fri]))
+	);
+
+	assert_eq!(
+		parse("2021-03-01 09:00 +Segunda/sexta standup"),
+		Ok(weekdays(&[Weekday::Mon, Weekday::Fri]))
+	);
+
+	assert_eq!(
+		parse("2021-03-01 09:00 +segunda,sexta-feira standup"),
+		Ok(weekdays(&[Weekday::Mon, Weekday::Fri]))
+	);
+
+	assert_eq!(
+		parse("2021-03-01 +lastsexta-feira meeting"),
+		Ok(Some(Recurrence::MonthlyWeekday { nth: Nth::Last, weekday: Weekday::Fri }))
+	);
+
+	assert_eq!(
+		parse("2021-03-01 +1mon meeting"),
+		Ok(Some(Recurrence::MonthlyWeekday { nth: Nth::Nth(1), weekday: Weekday::Mon }))
+	);
+
+	assert_eq!(
+		parse("2021-03-01 +2terça meeting"),
+		Ok(Some(Recurrence::MonthlyWeekday { nth: Nth::Nth(2), weekday: Weekday::Tue }))
+	);
+
+	assert_eq!(
+		parse("2021-03-01 +lastfriday x3 meeting"),
+		Ok(Some(Recurrence::MonthlyWeekday { nth: Nth::Last, weekday: Weekday::Fri }))
+	);
+
+	assert_eq!(
+		parse("2021-03-01 +2M meeting"),
+		Ok(Some(Recurrence::Period { ammount: 2, unit: RecurrenceUnit::Months }))
+	);
+
+	// Invalid rules are part of the message.
+	assert_eq!(parse("2021-03-01 +6mon meeting"), Ok(None));
+	assert_eq!(parse("2021-03-01 +mon,funday meeting"), Ok(None));
+	assert_eq!(parse("2021-03-01 +mon, meeting"), Ok(None));
+	assert_eq!(parse("2021-03-01 +last meeting"), Ok(None));
+}
+
+
+#[test]
+fn test_add_reply() {
+	let commands = commands();
+	let words = words();
+	let chat_id = 0.into();
+
+	let reply = |text: Option<&str>| bot::Reply {
+		message_id: 7.into(),
+		text: text.map(Into::into),
+		author: None,
+	};
+
+	let with_text = reply(Some("pay the rent"));
+	let without_text = reply(None);
+
+	let parse = |reply_to, input| super::parse(&commands, &words, chat_id, Tz::UTC, now(), reply_to, &[], input);
+
+	let add = |message: &str, source_message| Action::AddReminder(
+		AddReminder {
+			due: datetime("2021-03-02 09:00"),
+			recurrence: Some(
+				Recurrence::Period { ammount: 1, unit: RecurrenceUnit::Weeks }
+			),
+			count: None,
+			until: None,
+			message: message.into(),
+			source_message,
+			media: None,
+			targets: Box::default(),
+			creator: None,
+			private: false,
+			chat_id,
+			timezone: Tz::UTC,
+		}
+	);
+
+	assert_eq!(
+		parse(Some(&with_text), "2021-03-02 09:00 +1w"),
+		Ok(add("pay the rent", Some(7.into())))
+	);
+
+	assert_eq!(
+		parse(Some(&without_text), "2021-03-02 09:00 +1w  "),
+		Ok(add(REPLY_PLACEHOLDER, Some(7.into())))
+	);
+
+	// Text given in a reply is kept as a regular reminder.
+	assert_eq!(
+		parse(Some(&with_text), "2021-03-02 09:00 +1w hey"),
+		Ok(add("hey", None))
+	);
+
+	assert!(parse(None, "2021-03-02 09:00 +1w").is_err());
+}
+
+
+#[test]
+fn test_add_targets() {
+	let commands = commands();
+	let words = words();
+	let chat_id = 0.into();
+
+	let joao = bot::Mention::User {
+		id: 42.into(),
+		name: "João Silva".into(),
+	};
+
+	let mentions = [joao.clone()];
+
+	let parse = |input| super::parse(&commands, &words, chat_id, Tz::UTC, now(), None, &mentions, input);
+
+	let add = |message: &str, targets: Vec<bot::Mention>| Action::AddReminder(
+		AddReminder {
+			due: datetime("2021-03-02 09:00"),
 			recurrence: None,
-			message: "hey".into(),
-			chat_id
+			count: None,
+			until: None,
+			message: message.into(),
+			source_message: None,
+			media: None,
+			targets: targets.into_boxed_slice(),
+			creator: None,
+			private: false,
+			chat_id,
+			timezone: Tz::UTC,
+		}
+	);
+
+	let username = |username: &str| bot::Mention::Username(username.into());
+
+	assert_eq!(
+		parse("2021-03-02 09:00 @ana @bob_2  water the plants"),
+		Ok(add("water the plants", vec![username("ana"), username("bob_2")]))
+	);
+
+	assert_eq!(
+		parse("2021-03-02 09:00 João Silva @ana call mom"),
+		Ok(add("call mom", vec![joao, username("ana")]))
+	);
+
+	// Only leading, whole tags are targets.
+	assert_eq!(
+		parse("2021-03-02 09:00 call @ana"),
+		Ok(add("call @ana", Vec::new()))
+	);
+
+	assert_eq!(
+		parse("2021-03-02 09:00 @ana's birthday"),
+		Ok(add("@ana's birthday", Vec::new()))
+	);
+
+	// Tags alone aren't a message.
+	assert!(parse("2021-03-02 09:00 @ana").is_err());
+}
+
+
+#[test]
+fn test_add_private() {
+	let commands = commands();
+	let words = words();
+	let chat_id = 0.into();
+
+	let reply = bot::Reply {
+		message_id: 7.into(),
+		text: Some("pay the rent".into()),
+		author: None,
+	};
+
+	let parse = |reply_to, input| super::parse(&commands, &words, chat_id, Tz::UTC, now(), reply_to, &[], input);
+
+	let add = |message: &str, private, source_message| Action::AddReminder(
+		AddReminder {
+			due: datetime("2021-03-02 09:00"),
+			recurrence: Some(
+				Recurrence::Period { ammount: 1, unit: RecurrenceUnit::Days }
+			),
+			count: None,
+			until: None,
+			message: message.into(),
+			source_message,
+			media: None,
+			targets: Box::default(),
+			creator: None,
+			private,
+			chat_id,
+			timezone: Tz::UTC,
 		}
 	);
+
+	assert_eq!(
+		parse(None, "2021-03-02 09:00 +1d dm take the pills"),
+		Ok(add("take the pills", true, None))
+	);
+
+	assert_eq!(
+		parse(None, "2021-03-02 09:00 +1d DM take the pills"),
+		Ok(add("take the pills", true, None))
+	);
+
+	assert_eq!(
+		parse(Some(&reply), "2021-03-02 09:00 +1d dm"),
+		Ok(add("pay the rent", true, Some(7.into())))
+	);
+
+	// Only as a whole word.
+	assert_eq!(
+		parse(None, "2021-03-02 09:00 +1d dmitri's birthday"),
+		Ok(add("dmitri's birthday", false, None))
+	);
+
+	assert!(parse(None, "2021-03-02 09:00 +1d dm").is_err());
+}
+
+
+#[test]
+fn test_add_day() {
+	let commands = commands();
+	let words = words();
+	let chat_id = 0.into();
+
+	let parse = |input| match super::parse(&commands, &words, chat_id, Tz::UTC, now(), None, &[], input) {
+		Ok(Action::AddReminder(AddReminder { due, .. })) => due,
+		result => panic!("unexpected parse result: {:?}", result),
+	};
+
+	assert_eq!(parse("today 23:00 hey"), datetime("2021-03-01 23:00"));
+	assert_eq!(parse("tomorrow 09:00 hey"), datetime("2021-03-02 09:00"));
+	assert_eq!(parse("Tomorrow hey"), date("2021-03-02"));
+	assert_eq!(parse("friday 17:30 hey"), datetime("2021-03-05 17:30"));
+	assert_eq!(parse("fri hey"), date("2021-03-05"));
+	assert_eq!(parse("monday hey"), date("2021-03-08"));
+	assert_eq!(parse("sunday +1w hey"), date("2021-03-07"));
+
+	assert_eq!(parse("hoje 12:00 hey"), datetime("2021-03-01 12:00"));
+	assert_eq!(parse("Amanhã hey"), date("2021-03-02"));
+	assert_eq!(parse("sexta 17:30 hey"), datetime("2021-03-05 17:30"));
+	assert_eq!(parse("sexta-feira hey"), date("2021-03-05"));
+	assert_eq!(parse("segunda hey"), date("2021-03-08"));
+
+	assert!(super::parse(&commands, &words, chat_id, Tz::UTC, now(), None, &[], "someday hey").is_err());
+	assert!(super::parse(&commands, &words, chat_id, Tz::UTC, now(), None, &[], "sextas hey").is_err());
 }
 
 
 #[test]
 fn test_list() {
+	let commands = commands();
+	let words = words();
 	let chat_id = 0.into();
 
 	let parse = |input| super
-		::parse(chat_id, input)
+		::parse(&commands, &words, chat_id, Tz::UTC, now(), None, &[], input)
 		.expect("parse failed");
 
 	assert_eq!(
 		parse("chora"),
-		Action::ListReminders(chat_id),
+		Action::ListReminders(
+			ListReminders { chat_id, timezone: Tz::UTC }
+		),
 	);
 
 	assert_eq!(
 		parse("   chora   "),
-		Action::ListReminders(chat_id),
+		Action::ListReminders(
+			ListReminders { chat_id, timezone: Tz::UTC }
+		),
+	);
+}
+
+
+#[test]
+fn test_history() {
+	let commands = commands();
+	let words = words();
+	let chat_id = 0.into();
+
+	let parse = |input| super::parse(&commands, &words, chat_id, Tz::UTC, now(), None, &[], input);
+
+	let history = |count| Action::ListDeliveries(
+		ListDeliveries { chat_id, count, timezone: Tz::UTC }
+	);
+
+	assert_eq!(parse("historico"), Ok(history(HISTORY_DEFAULT_COUNT)));
+	assert_eq!(parse(" Historico  25 "), Ok(history(25)));
+	assert_eq!(parse("historico 1000"), Ok(history(HISTORY_MAX_COUNT)));
+	assert!(parse("historico 0").is_err());
+	assert!(parse("historico todos").is_err());
+}
+
+
+#[test]
+fn test_timezone() {
+	let commands = commands();
+	let words = words();
+	let chat_id = 0.into();
+
+	let parse = |input| super::parse(&commands, &words, chat_id, Tz::UTC, now(), None, &[], input);
+
+	assert_eq!(
+		parse("tz America/Sao_Paulo").expect("parse failed"),
+		Action::SetTimezone(
+			SetTimezone { chat_id, timezone: Tz::America__Sao_Paulo }
+		),
+	);
+
+	assert_eq!(
+		parse("  TZ   Europe/Lisbon  ").expect("parse failed"),
+		Action::SetTimezone(
+			SetTimezone { chat_id, timezone: Tz::Europe__Lisbon }
+		),
 	);
+
+	assert!(parse("tz Nowhere/Atlantis").is_err());
+	assert!(parse("tz").is_err());
+}
+
+
+#[test]
+fn test_edit() {
+	let commands = commands();
+	let words = words();
+	let chat_id = 0.into();
+
+	let parse = |input| super
+		::parse(&commands, &words, chat_id, Tz::UTC, now(), None, &[], input)
+		.expect("parse failed");
+
+	let edit = |reminder_id, due, recurrence, message: Option<&str>| Action::EditReminder(
+		EditReminder {
+			reminder_id,
+			due,
+			recurrence,
+			count: None,
+			until: None,
+			message: message.map(Into::into),
+			chat_id,
+			timezone: Tz::UTC,
+		}
+	);
+
+	let time = |str| Time
+		::parse_from_str(str, "%H:%M")
+		.expect("invalid time");
+
+	assert_eq!(
+		parse("muda 1 2021-04-01"),
+		edit(1, Some(DueChange::Date(date("2021-04-01").date(), None)), None, None)
+	);
+
+	assert_eq!(
+		parse("muda 1 tomorrow 09:30"),
+		edit(1, Some(DueChange::Date(date("2021-03-02").date(), Some(time("09:30")))), None, None)
+	);
+
+	assert_eq!(
+		parse("muda 2 18:00"),
+		edit(2, Some(DueChange::Time(time("18:00"))), None, None)
+	);
+
+	assert_eq!(
+		parse("muda 3 in 2h"),
+		edit(3, Some(DueChange::DateTime(datetime("2021-03-01 12:00"))), None, None)
+	);
+
+	assert_eq!(
+		parse("muda 4 +2d"),
+		edit(4, None, Some(Some(Recurrence::Period { ammount: 2, unit: RecurrenceUnit::Days })), None)
+	);
+
+	assert_eq!(
+		parse("muda 4 +once"),
+		edit(4, None, Some(None), None)
+	);
+
+	assert_eq!(
+		parse("muda 4 18:00 +ONCE stretch"),
+		edit(4, Some(DueChange::Time(time("18:00"))), Some(None), Some("stretch"))
+	);
+
+	assert_eq!(
+		parse("muda 4 +onceler"),
+		edit(4, None, None, Some("+onceler"))
+	);
+
+	assert_eq!(
+		parse("muda 5 buy milk  "),
+		edit(5, None, None, Some("buy milk"))
+	);
+
+	assert_eq!(
+		parse("muda 6 sexta 08:00 +1w water plants"),
+		edit(
+			6,
+			Some(DueChange::Date(date("2021-03-05").date(), Some(time("08:00")))),
+			Some(Some(Recurrence::Period { ammount: 1, unit: RecurrenceUnit::Weeks })),
+			Some("water plants")
+		)
+	);
+
+	assert!(super::parse(&commands, &words, chat_id, Tz::UTC, now(), None, &[], "muda 1").is_err());
+	assert!(super::parse(&commands, &words, chat_id, Tz::UTC, now(), None, &[], "muda 1   ").is_err());
 }
 
 
 #[test]
 fn test_remove() {
+	let commands = commands();
+	let words = words();
 	let chat_id = 0.into();
 
 	let parse = |input| super
-		::parse(chat_id, input)
+		::parse(&commands, &words, chat_id, Tz::UTC, now(), None, &[], input)
 		.expect("parse failed");
 
 	assert_eq!(
 		parse("cancela 1"),
-		Action::RemoveReminder(chat_id, 1),
+		Action::RemoveReminder(
+			RemoveReminder { chat_id, reminder_id: 1 }
+		),
 	);
 
 	assert_eq!(
 		parse("cancela 2147483647"),
-		Action::RemoveReminder(chat_id, 2147483647),
+		Action::RemoveReminder(
+			RemoveReminder { chat_id, reminder_id: 2147483647 }
+		),
 	);
 
 	assert_eq!(
 		parse("   cancela    2   "),
-		Action::RemoveReminder(chat_id, 2),
+		Action::RemoveReminder(
+			RemoveReminder { chat_id, reminder_id: 2 }
+		),
+	);
+}
+
+
+#[test]
+fn test_roles() {
+	let commands = commands();
+	let words = words();
+	let chat_id = 0.into();
+
+	let ana = bot::Mention::User {
+		id: 1.into(),
+		name: "Ana".into(),
+	};
+
+	let reply = bot::Reply {
+		message_id: 7.into(),
+		text: Some("hey".into()),
+		author: Some(
+			bot::Mention::User {
+				id: 2.into(),
+				name: "Bob".into(),
+			}
+		),
+	};
+
+	let mentioned = [ana];
+	let username = [bot::Mention::Username("ana".into())];
+
+	let parse = |reply_to, mentions, input| super::parse(&commands, &words, chat_id, Tz::UTC, now(), reply_to, mentions, input);
+
+	let set_role = |user_id: i64, name: &str, role| Action::SetRole(
+		SetRole {
+			chat_id,
+			user_id: user_id.into(),
+			name: name.into(),
+			role,
+		}
+	);
+
+	assert_eq!(
+		parse(None, &mentioned, "promove Ana"),
+		Ok(set_role(1, "Ana", Role::Admin))
+	);
+
+	assert_eq!(
+		parse(Some(&reply), &[], "rebaixa"),
+		Ok(set_role(2, "Bob", Role::Member))
+	);
+
+	// The bot's own mention, leading the message in groups, is skipped.
+	let addressed = [bot::Mention::Username("reminder_bot".into()), mentioned[0].clone()];
+
+	assert_eq!(
+		parse(None, &addressed, "promove Ana"),
+		Ok(set_role(1, "Ana", Role::Admin))
+	);
+
+	// Mentions take precedence over replies.
+	assert_eq!(
+		parse(Some(&reply), &mentioned, "promove Ana"),
+		Ok(set_role(1, "Ana", Role::Admin))
+	);
+
+	// Usernames don't identify users.
+	assert!(parse(Some(&reply), &username, "promove @ana").is_err());
+	assert!(parse(None, &[], "promove").is_err());
+
+	assert_eq!(
+		parse(None, &[], "geral on"),
+		Ok(Action::SetManagement(SetManagement { chat_id, everyone_manages: true }))
+	);
+
+	assert_eq!(
+		parse(None, &[], " GERAL  off "),
+		Ok(Action::SetManagement(SetManagement { chat_id, everyone_manages: false }))
+	);
+
+	assert!(parse(None, &[], "geral sim").is_err());
+}
+
+
+#[test]
+fn test_export_import() {
+	let commands = commands();
+	let words = words();
+	let chat_id = 0.into();
+
+	let parse = |input| super::parse(&commands, &words, chat_id, Tz::UTC, now(), None, &[], input);
+
+	let export = |format| Action::ExportReminders(
+		ExportReminders { chat_id, format, timezone: Tz::UTC }
+	);
+
+	assert_eq!(parse("exporta"), Ok(export(Format::Json)));
+	assert_eq!(parse("exporta json"), Ok(export(Format::Json)));
+	assert_eq!(parse(" Exporta  ICS "), Ok(export(Format::Ics)));
+	assert!(parse("exporta csv").is_err());
+
+	assert_eq!(
+		parse("importa"),
+		Ok(
+			Action::ImportReminders(
+				ImportReminders { media: None, creator: None, chat_id, timezone: Tz::UTC }
+			)
+		)
 	);
 }
 
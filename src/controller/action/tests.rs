@@ -0,0 +1,76 @@
+use chrono::{TimeZone, Utc};
+
+use super::*;
+
+
+const TIMEZONE: Tz = chrono_tz::America::Sao_Paulo;
+
+
+fn timestamp(hour: u32, minute: u32) -> DueTimestamp {
+	DueTimestamp(
+		Utc.ymd(2021, 3, 1).and_hms(hour, minute, 0)
+	)
+}
+
+
+fn hourly(remaining: Option<i32>) -> Reminder {
+	Reminder {
+		id: 1,
+		due: timestamp(13, 0),
+		recurrying: Some(
+			Recurrence::Period { ammount: 1, unit: RecurrenceUnit::Hours }
+		),
+		chat: ChatId(
+			bot::ChatId::new(1)
+		),
+		message: "drink water".into(),
+		recur_until: None,
+		recur_remaining: remaining,
+		recur_anchor: Some(1),
+		source_message: None,
+		media_kind: None,
+		media_file_id: None,
+		creator_id: None,
+		creator_name: None,
+		creator_username: None,
+		mentions: None,
+		recipient: None,
+	}
+}
+
+
+#[test]
+fn test_late_reminder_text() {
+	let text = |lateness| late_reminder_text("stretch", "late by", lateness);
+
+	assert_eq!(text(Duration::seconds(30)), "stretch\n\n⏰ late by 0m");
+	assert_eq!(text(Duration::minutes(125)), "stretch\n\n⏰ late by 2h5m");
+	assert_eq!(text(Duration::minutes(24 * 60 + 3)), "stretch\n\n⏰ late by 1d3m");
+}
+
+
+#[test]
+fn test_next_occurrence() {
+	// Delivered at 16:30, missing the 14:00, 15:00 and 16:00 occurrences.
+	let now = timestamp(16, 30);
+
+	let due = |reminder: Option<Reminder>| reminder.map(
+		|reminder| reminder.due
+	);
+
+	// Catching up on every occurrence moves to the following one, which is also late.
+	assert_eq!(due(next_occurrence(&hourly(None), TIMEZONE, None)), Some(timestamp(14, 0)));
+
+	// Otherwise, the ones already due are skipped, and count as delivered.
+	let next = next_occurrence(&hourly(Some(10)), TIMEZONE, Some(now));
+
+	assert_eq!(due(next.clone()), Some(timestamp(17, 0)));
+	assert_eq!(next.and_then(|next| next.recur_remaining), Some(6));
+
+	assert_eq!(next_occurrence(&hourly(Some(3)), TIMEZONE, Some(now)), None);
+
+	// One-shot reminders have no following occurrence.
+	let once = Reminder { recurrying: None, ..hourly(None) };
+
+	assert_eq!(next_occurrence(&once, TIMEZONE, None), None);
+}
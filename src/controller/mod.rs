@@ -1,57 +1,195 @@
 mod action;
+mod operator;
+#[cfg(test)]
+mod tests;
+
+use std::{
+	collections::HashSet,
+	time::Duration,
+};
 
 use futures::StreamExt;
 
-use action::Action;
+use tokio::sync::Notify;
+
+use chrono_tz::Tz;
+
+use action::{Action, Actor, Creator, ImportReminders, SnoozeReminder};
+use operator::OperatorAction;
 use crate::{
-	bot::{Bot, Message},
-	config,
+	bot::{self, Authenticator, Message, Messenger},
+	clock::Clock,
+	config::{self, CatchUp},
 	db::{
 		Db,
 		Error as DbError,
-		models::reminders::Reminder,
-		types::DueTimestamp
+		models::{
+			chat_roles::ChatRole,
+			deliveries::NewDelivery,
+			reminders::Reminder,
+		},
+		types::{ChatId, DueTimestamp, MessageId, Outcome, Role, UserId}
 	},
 };
 
 
-const NOTIFICATOR_INTERVAL: u64 = 5 * 60;
+/// The longest the notificator sleeps, even if no reminder is due before that.
+const NOTIFICATOR_MAX_SLEEP: u64 = 5 * 60;
+/// How long to wait before retrying reminders that failed to be sent.
+const NOTIFICATOR_RETRY_INTERVAL: u64 = 60;
+/// How often the notificator checks for reminders changed from the command line.
+const NOTIFICATOR_POLL_INTERVAL: u64 = 5;
+/// How many seconds past due a reminder may be delivered without being marked as late.
+/// Covers a few retries, so that transient failures don't make reminders late.
+const LATE_TOLERANCE: i64 = 5 * NOTIFICATOR_RETRY_INTERVAL as i64;
 
 
-pub async fn launch_bot<'a>(
+/// Executes the actions requested in the messages let through by the authenticator, until
+/// they end.
+#[allow(clippy::too_many_arguments)]
+pub async fn launch_bot<'a, M, C>(
 	db: &'a Db,
-	bot: &'a Bot<'a>,
+	bot: &'a M,
+	clock: &'a C,
+	authenticator: &'a Authenticator<'a, M, C>,
 	commands: &'a config::Commands,
+	words: &'a config::Words,
 	messages: &'a config::Messages,
-) {
+	default_timezone: Tz,
+	reschedule: &'a Notify,
+)
+where
+	M: Messenger,
+	C: Clock,
+{
 	log::info!("Bot online!");
 
-	let message_stream = bot.stream();
+	let message_stream = authenticator.stream();
 	futures::pin_mut!(message_stream);
 
+	// Chats whose roles were synced since the bot started.
+	let mut synced_chats = HashSet::new();
+
 	while let Some(message) = message_stream.next().await {
-		match message {
-			Message::Text { chat_id, text, .. } => {
-				match Action::parse(commands, chat_id, &text) {
-					Ok(action) => {
-						let result = action.execute(db, bot, messages).await;
-
-						if let Err(error) = result {
-							log::warn!("Error when executing action: {}", error);
-						}
-					}
-
-					Err(_) => {
-						let message = messages.misunderstanding_message();
-
-						let result = bot
-							.send_message(chat_id, message)
-							.await;
-
-						if let Err(error) = result {
-							log::warn!("Error when sending message: {}", error);
-						}
-					}
+		let chat_id = message.chat_id();
+
+		if authenticator.is_operator(chat_id) {
+			let action = match &message {
+				Message::Text { text, .. } => OperatorAction::parse(text),
+				_ => None,
+			};
+
+			// Anything else is handled like in any other chat.
+			if let Some(action) = action {
+				if let Err(error) = action.execute(db, bot, authenticator, chat_id).await {
+					log::warn!("Error when executing operator action: {}", error);
+				}
+
+				continue;
+			}
+		}
+
+		if synced_chats.insert(chat_id) {
+			sync_chat_roles(db, bot, chat_id).await;
+		}
+
+		let actor = chat_actor(db, chat_id, message.user_id());
+
+		let timezone = chat_timezone(db, chat_id, default_timezone);
+
+		// When the message was sent, in the chat's timezone.
+		let local = |date: chrono::DateTime<chrono::Utc>| date
+			.with_timezone(&timezone)
+			.naive_local();
+
+		let action = match message {
+			Message::Text { user_id, nickname, username, date, text, mentions, reply_to, .. } => Action
+				::parse(commands, words, chat_id, timezone, local(date), reply_to.as_ref(), &mentions, &text)
+				.ok()
+				.map(
+					|action| action.with_creator(
+						Creator { id: user_id, name: nickname, username }
+					)
+				),
+
+			Message::Media { user_id, nickname, username, date, caption, media, mentions, reply_to, .. } => Action
+				::parse(commands, words, chat_id, timezone, local(date), reply_to.as_ref(), &mentions, &caption)
+				.ok()
+				.map(
+					|action| action
+						.with_media(media)
+						.with_creator(
+							Creator { id: user_id, name: nickname, username }
+						)
+				),
+
+			Message::Calendar { user_id, nickname, username, media, .. } => Some(
+				Action::ImportReminders(
+					ImportReminders::calendar(
+						media,
+						Creator { id: user_id, name: nickname, username },
+						chat_id,
+						timezone
+					)
+				)
+			),
+
+			Message::Callback {
+				query_id,
+				message_id,
+				message_text,
+				media,
+				mentions,
+				reply_to,
+				data,
+				..
+			} => {
+				let action = SnoozeReminder::parse(
+					&data,
+					query_id,
+					chat_id,
+					message_id,
+					message_text,
+					reply_to,
+					media,
+					mentions,
+					clock.now(),
+					timezone
+				);
+
+				if action.is_none() {
+					log::warn!("Unknown callback data: {}", data);
+					continue;
+				}
+
+				action.map(Action::SnoozeReminder)
+			}
+		};
+
+		match action {
+			Some(action) => {
+				let changes_schedule = action.changes_schedule();
+
+				let result = action.execute(db, bot, messages, actor, clock.now()).await;
+
+				if let Err(error) = result {
+					log::warn!("Error when executing action: {}", error);
+				}
+
+				if changes_schedule {
+					reschedule.notify_one();
+				}
+			}
+
+			None => {
+				let message = messages.misunderstanding_message();
+
+				let result = bot
+					.send_message(chat_id, message)
+					.await;
+
+				if let Err(error) = result {
+					log::warn!("Error when sending message: {}", error);
 				}
 			}
 		}
@@ -59,37 +197,97 @@ pub async fn launch_bot<'a>(
 }
 
 
-pub async fn launch_notificator<'a>(db: &'a Db, bot: &'a Bot<'a>) {
+/// Sleeps until the next reminder is due, or until `reschedule` is notified, or the
+/// database is changed by another connection.
+pub async fn launch_notificator<'a, M: Messenger, C: Clock>(
+	db: &'a Db,
+	bot: &'a M,
+	clock: &'a C,
+	messages: &'a config::Messages,
+	default_timezone: Tz,
+	catch_up: CatchUp,
+	reschedule: &'a Notify,
+) {
 	log::info!("Notificator online!");
 
-	let mut interval = tokio::time::interval(
-		std::time::Duration::from_secs(NOTIFICATOR_INTERVAL)
-	);
-
 	loop {
-		interval
-			.tick()
-			.await;
+		let sleep = time_until_next_due(db, clock);
+
+		tokio::select! {
+			_ = tokio::time::sleep(sleep) => { }
 
-		if let Err(errors) = run_notifications(db, bot).await {
+			// The next due reminder may have changed.
+			_ = reschedule.notified() => continue,
+
+			// Like when reminders are added or removed from the command line.
+			_ = external_change(db) => continue,
+		}
+
+		if let Err(errors) = run_notifications(db, bot, clock, messages, default_timezone, catch_up).await {
 			for error in errors.into_vec() { // Box has no owned iterator.
 				log::error!("Failed to run reminder: {}", error);
 			}
+
+			// Reminders that failed are still due, so don't retry them right away.
+			tokio::time::sleep(
+				Duration::from_secs(NOTIFICATOR_RETRY_INTERVAL)
+			)
+				.await;
+		}
+	}
+}
+
+
+/// Resolves once another connection changes the database.
+async fn external_change(db: &Db) {
+	let version = db.data_version().ok();
+
+	loop {
+		tokio::time::sleep(
+			Duration::from_secs(NOTIFICATOR_POLL_INTERVAL)
+		)
+			.await;
+
+		match db.data_version() {
+			Ok(current) if Some(current) != version => return,
+			Ok(_) => { }
+			Err(error) => log::warn!("Failed to check for database changes: {}", error),
+		}
+	}
+}
+
+
+fn time_until_next_due<C: Clock>(db: &Db, clock: &C) -> Duration {
+	let max_sleep = Duration::from_secs(NOTIFICATOR_MAX_SLEEP);
+
+	match db.next_due_timestamp() {
+		Ok(Some(due)) => (due.0 - clock.now())
+			.to_std()
+			.unwrap_or(Duration::from_secs(0)) // Already due.
+			.min(max_sleep),
+
+		Ok(None) => max_sleep,
+
+		Err(error) => {
+			log::error!("Failed to retrieve next due reminder: {}", error);
+			max_sleep
 		}
 	}
 }
 
 
-async fn run_notifications<'a>(
+async fn run_notifications<'a, M: Messenger, C: Clock>(
 	db: &'a Db,
-	bot: &'a Bot<'a>
+	bot: &'a M,
+	clock: &'a C,
+	messages: &'a config::Messages,
+	default_timezone: Tz,
+	catch_up: CatchUp,
 ) -> Result<(), Box<[action::ExecutionError]>> {
 	log::info!("Running reminders...");
 
 	let now = DueTimestamp(
-		chrono::Local
-			::now()
-			.naive_local()
+		clock.now()
 	);
 
 	let reminders = db
@@ -102,9 +300,39 @@ async fn run_notifications<'a>(
 	let mut errors = Vec::new();
 
 	for reminder in reminders.into_vec() {
-		log::info!("Sending reminder to {:?}: {}", reminder.chat.0, reminder.message);
+		let lateness = now.0 - reminder.due.0;
+
+		let is_late = lateness > chrono::Duration::seconds(LATE_TOLERANCE);
+
+		let text =
+			if is_late {
+				action::late_reminder_text(&reminder.message, messages.late_message(), lateness)
+			} else {
+				reminder.message.clone()
+			};
+
+		let skipped = is_late && catch_up == CatchUp::Skip;
+
+		let chat = reminder.delivery_chat();
+
+		let result = send_reminder(bot, &reminder, chat, &text, skipped, messages).await;
+
+		// Users must have started a chat with the bot to be messaged privately.
+		let (chat, result) = match result {
+			Err(error) if reminder.recipient.is_some() => {
+				log::warn!("Failed to send reminder {} privately, sending to its chat: {}", reminder.id, error);
+
+				let text = format!("{}\n{}", messages.unreachable_message(), text);
+
+				let result = send_reminder(bot, &reminder, reminder.chat.0, &text, skipped, messages).await;
+
+				(reminder.chat.0, result)
+			}
+
+			result => (chat, result),
+		};
 
-		let result = bot.send_message(reminder.chat.0, &reminder.message).await;
+		record_delivery(db, &reminder, chat, now, &result, skipped);
 
 		if let Err(error) = result {
 			errors.push(
@@ -114,7 +342,17 @@ async fn run_notifications<'a>(
 			continue;
 		}
 
-		let result = reminder_done(db, &reminder);
+		let timezone = chat_timezone(db, reminder.chat.0, default_timezone);
+
+		// Unless catching up on every missed occurrence, skip the ones that are already due.
+		let skip_until =
+			if is_late && catch_up != CatchUp::All {
+				Some(now)
+			} else {
+				None
+			};
+
+		let result = reminder_done(db, &reminder, timezone, skip_until);
 
 		if let Err(error) = result {
 			errors.push(
@@ -134,9 +372,148 @@ async fn run_notifications<'a>(
 }
 
 
-fn reminder_done(db: &Db, reminder: &Reminder) -> Result<(), DbError> {
-	if reminder.is_recurrying() { // Reminder is recurrying, update.
-		let success = db .recur_reminder(&reminder)?;
+/// Send a reminder with the given text to the given chat, or just note that it was skipped.
+/// Returns the sent reminder, which skipped ones aren't.
+async fn send_reminder<M: Messenger>(
+	bot: &M,
+	reminder: &Reminder,
+	chat: bot::ChatId,
+	text: &str,
+	skipped: bool,
+	messages: &config::Messages,
+) -> Result<Option<bot::MessageId>, bot::BotError> {
+	if skipped {
+		log::info!("Skipping reminder to {:?}: {}", chat, reminder.message);
+
+		let text = format!("{}\n{}", messages.skipped_message(), text);
+
+		return bot
+			.send_message(chat, &text)
+			.await
+			.map(
+				|_| None
+			);
+	}
+
+	log::info!("Sending reminder to {:?}: {}", chat, reminder.message);
+
+	// Tag the recipient when a private reminder falls back to its chat.
+	let mut mentions = reminder.mentions().to_vec();
+
+	if chat == reminder.chat.0 {
+		mentions.extend(reminder.recipient_mention());
+	}
+
+	// The replied message is in the chat the reminder was added in.
+	let reply_to = reminder.source_message
+		.filter(
+			|_| chat == reminder.chat.0
+		)
+		.map(
+			|message| message.0
+		);
+
+	let result = deliver(bot, reminder, chat, text, reply_to, &mentions).await;
+
+	// The replied message may have been deleted since.
+	let result = match result {
+		Err(error) if reply_to.is_some() => {
+			log::warn!("Failed to send reminder {} as a reply, sending alone: {}", reminder.id, error);
+
+			deliver(bot, reminder, chat, text, None, &mentions).await
+		}
+
+		result => result,
+	};
+
+	result.map(Some)
+}
+
+
+/// Send a reminder with the given text, along with its attached file, if any, tagging the
+/// given users.
+async fn deliver<M: Messenger>(
+	bot: &M,
+	reminder: &Reminder,
+	chat: bot::ChatId,
+	text: &str,
+	reply_to: Option<bot::MessageId>,
+	mentions: &[bot::Mention],
+) -> Result<bot::MessageId, bot::BotError> {
+	match reminder.media() {
+		Some(media) => bot
+			.send_media_with_buttons(chat, &media, text, &action::SNOOZE_BUTTONS, reply_to, mentions)
+			.await,
+
+		None => bot
+			.send_message_with_buttons(chat, text, &action::SNOOZE_BUTTONS, reply_to, mentions)
+			.await,
+	}
+}
+
+
+/// Keep a record of an attempt to deliver a reminder. Failing to is only logged, as the
+/// reminder was already handled.
+fn record_delivery(
+	db: &Db,
+	reminder: &Reminder,
+	chat: bot::ChatId,
+	now: DueTimestamp,
+	result: &Result<Option<bot::MessageId>, bot::BotError>,
+	skipped: bool,
+) {
+	let (chat, message, outcome) = match result {
+		Ok(_) if skipped => (chat, None, Outcome::Skipped),
+		Ok(message) => (chat, message.map(MessageId), Outcome::Sent),
+		// Failed deliveries are listed in the chat the reminder was added in.
+		Err(_) => (reminder.chat.0, None, Outcome::Failed),
+	};
+
+	let delivery = NewDelivery {
+		reminder: reminder.id,
+		chat: ChatId(chat),
+		scheduled: reminder.due,
+		sent: now,
+		message,
+		outcome,
+		text: &reminder.message,
+		creator_id: reminder.creator_id,
+		creator_name: reminder.creator_name.as_deref(),
+		creator_username: reminder.creator_username.as_deref(),
+	};
+
+	// A reminder that keeps failing is listed once, as last attempted.
+	let retried = match outcome {
+		Outcome::Failed => db.retry_failed_delivery(&delivery),
+		_ => Ok(false),
+	};
+
+	let result = retried.and_then(
+		|retried| if retried {
+			Ok(())
+		} else {
+			db.new_delivery(&delivery)
+		}
+	);
+
+	if let Err(error) = result {
+		log::warn!("Failed to record delivery of reminder {}: {}", reminder.id, error);
+	}
+}
+
+
+/// Move a delivered reminder to its following occurrence, skipping occurrences up to
+/// `skip_until`, or delete it if it has none left.
+fn reminder_done(
+	db: &Db,
+	reminder: &Reminder,
+	timezone: Tz,
+	skip_until: Option<DueTimestamp>,
+) -> Result<(), DbError> {
+	let next = action::next_occurrence(reminder, timezone, skip_until);
+
+	if let Some(next) = next { // Reminder has occurrences left, update.
+		let success = db.recur_reminder(&next)?;
 
 		if !success {
 			log::warn!("Failed to update reminder {:?}.", reminder.id);
@@ -152,3 +529,100 @@ fn reminder_done(db: &Db, reminder: &Reminder) -> Result<(), DbError> {
 
 	Ok(())
 }
+
+
+/// Give the chat's owner its role, and its administrators without a role theirs, so that
+/// administrators added since are picked up, but revoked ones stay members.
+async fn sync_chat_roles<M: Messenger>(db: &Db, bot: &M, chat_id: bot::ChatId) {
+	let administrators = match bot.chat_administrators(chat_id).await {
+		Ok(administrators) => administrators,
+		Err(error) => {
+			log::warn!("Failed to retrieve administrators for chat {}: {}", chat_id, error);
+			return;
+		}
+	};
+
+	for administrator in administrators.iter() {
+		let user_id = UserId(administrator.user_id);
+
+		let current = match db.chat_role(ChatId(chat_id), user_id) {
+			Ok(current) => current,
+			Err(error) => {
+				log::warn!("Failed to retrieve role for chat {}: {}", chat_id, error);
+				continue;
+			}
+		};
+
+		let role = match (administrator.owner, current) {
+			(true, Some(Role::Owner)) => continue,
+			(true, _) => Role::Owner,
+			(false, None) => Role::Admin,
+			(false, Some(_)) => continue,
+		};
+
+		let role = ChatRole {
+			chat: ChatId(chat_id),
+			user_id,
+			role,
+		};
+
+		if let Err(error) = db.set_chat_role(&role) {
+			log::warn!("Failed to sync role for chat {}: {}", chat_id, error);
+		}
+	}
+}
+
+
+/// The user acting in the chat. Users whose role fails to be retrieved are members, except
+/// in their private chat with the bot, which they own. Chats without settings let everyone
+/// manage every reminder, like before roles.
+fn chat_actor(db: &Db, chat_id: bot::ChatId, user_id: bot::UserId) -> Actor {
+	let is_private = chat_id == user_id.into();
+
+	let role = db
+		.chat_role(ChatId(chat_id), UserId(user_id))
+		.unwrap_or_else(
+			|error| {
+				log::warn!("Failed to retrieve role for chat {}: {}", chat_id, error);
+				None
+			}
+		)
+		.unwrap_or(
+			if is_private { Role::Owner } else { Role::Member }
+		);
+
+	let everyone_manages = db
+		.chat_settings(ChatId(chat_id))
+		.unwrap_or_else(
+			|error| {
+				log::warn!("Failed to retrieve settings for chat {}: {}", chat_id, error);
+				None
+			}
+		)
+		.map_or(
+			true,
+			|settings| settings.everyone_manages
+		);
+
+	Actor { id: user_id, role, everyone_manages }
+}
+
+
+/// The chat's timezone, or the default if it has none or if it fails to be retrieved.
+fn chat_timezone(db: &Db, chat_id: bot::ChatId, default_timezone: Tz) -> Tz {
+	match db.chat_settings(ChatId(chat_id)) {
+		Ok(settings) => settings
+			.and_then(
+				|settings| settings.timezone
+			)
+			.map_or(
+				default_timezone,
+				|timezone| timezone.0
+			),
+
+		Err(error) => {
+			log::warn!("Failed to retrieve timezone for chat {}: {}", chat_id, error);
+			default_timezone
+		}
+	}
+}
@@ -0,0 +1,193 @@
+use std::fmt::Write;
+
+use combine::{
+	EasyParser,
+	Parser,
+	Stream,
+	attempt,
+	choice,
+	eof,
+	from_str,
+	many1,
+	optional,
+	parser::char::{char, digit, space, spaces, string}
+};
+
+use super::action::ExecutionError;
+use crate::{
+	bot::{self, Authenticator, Messenger},
+	clock::Clock,
+	db::{Db, types::ChatId},
+};
+
+
+/// A command from the operator chat, managing the chats the bot trusts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OperatorAction {
+	/// List every trusted chat: `chats`.
+	ListChats,
+	/// Stop trusting a chat and leave it, possibly deleting its reminders: `revoke <id>`, or
+	/// `revoke <id> purge`.
+	RevokeChat { chat_id: bot::ChatId, purge: bool },
+	/// Leave a chat, which remains trusted: `leave <id>`.
+	LeaveChat { chat_id: bot::ChatId },
+}
+
+
+impl OperatorAction {
+	pub fn parse(input: &str) -> Option<Self> {
+		let list = string("chats")
+			.map(
+				|_| OperatorAction::ListChats
+			);
+
+		let revoke = (
+			string("revoke"),
+			chat_id(),
+			optional(
+				attempt(
+					(space(), spaces(), string("purge"))
+				)
+			),
+		)
+			.map(
+				|(_, chat_id, purge)| OperatorAction::RevokeChat { chat_id, purge: purge.is_some() }
+			);
+
+		let leave = (
+			string("leave"),
+			chat_id(),
+		)
+			.map(
+				|(_, chat_id)| OperatorAction::LeaveChat { chat_id }
+			);
+
+		let mut parser = (
+			spaces(),
+			choice!(
+				attempt(list),
+				attempt(revoke),
+				leave
+			),
+			spaces(),
+			eof()
+		)
+			.map(
+				|(_, action, _, _)| action
+			);
+
+		parser
+			.easy_parse(input)
+			.ok()
+			.map(
+				|(action, _)| action
+			)
+	}
+
+
+	pub async fn execute<'a, M: Messenger, C: Clock>(
+		self,
+		db: &'a Db,
+		bot: &'a M,
+		authenticator: &'a Authenticator<'a, M, C>,
+		operator: bot::ChatId,
+	) -> Result<(), ExecutionError> {
+		let mut message = String::new();
+
+		match self {
+			OperatorAction::ListChats => {
+				let chats = db
+					.trusted_chats()
+					.map_err(ExecutionError::Db)?;
+
+				if chats.is_empty() {
+					message.push_str("No trusted chats.");
+				}
+
+				for chat in chats.iter() {
+					writeln!(message, "{}", chat)
+						.expect("write should not fail on string");
+				}
+			}
+
+			OperatorAction::RevokeChat { chat_id, purge } => {
+				let revoked = db
+					.delete_trusted_chat(ChatId(chat_id))
+					.map_err(ExecutionError::Db)?;
+
+				authenticator.distrust_chat(chat_id);
+
+				let result =
+					if revoked {
+						log::info!("Revoked trusted chat: {}", chat_id);
+						write!(message, "Revoked chat {}.", chat_id)
+					} else {
+						write!(message, "Chat {} wasn't trusted.", chat_id)
+					};
+
+				result.expect("write should not fail on string");
+
+				if purge {
+					let purged = db
+						.delete_chat_reminders(ChatId(chat_id))
+						.map_err(ExecutionError::Db)?;
+
+					log::info!("Purged {} reminders from chat: {}", purged, chat_id);
+
+					write!(message, " Purged {} reminders.", purged)
+						.expect("write should not fail on string");
+				}
+
+				leave_chat(bot, chat_id).await;
+			}
+
+			OperatorAction::LeaveChat { chat_id } => {
+				write!(message, "Left chat {}.", chat_id)
+					.expect("write should not fail on string");
+
+				leave_chat(bot, chat_id).await;
+			}
+		}
+
+		bot
+			.send_message(operator, &message)
+			.await
+			.map_err(ExecutionError::Bot)
+	}
+}
+
+
+/// A chat id, preceded by spaces. Group ids are negative.
+fn chat_id<Input>() -> impl Parser<Input, Output = bot::ChatId>
+where
+	Input: Stream<Token = char>
+{
+	(
+		space(),
+		spaces(),
+		from_str(
+			(
+				optional(
+					char('-')
+				),
+				many1::<String, _, _>(
+					digit()
+				),
+			)
+				.map(
+					|(sign, digits)| format!("{}{}", sign.map_or("", |_| "-"), digits)
+				)
+		),
+	)
+		.map(
+			|(_, _, id)| bot::ChatId::new(id)
+		)
+}
+
+
+/// Leaving may fail if the bot was already removed from the chat, which is fine.
+async fn leave_chat<M: Messenger>(bot: &M, chat_id: bot::ChatId) {
+	if let Err(error) = bot.leave_chat(chat_id).await {
+		log::warn!("Failed to leave chat {}: {}", chat_id, error);
+	}
+}
@@ -0,0 +1,1491 @@
+use chrono::{Duration as ChronoDuration, TimeZone, Utc};
+
+use super::*;
+
+use crate::{
+	bot::{
+		Authenticator,
+		fake::{self, FakeMessenger, Sent},
+	},
+	clock::ManualClock,
+	config::{Config, Password, Secret},
+	db::types::{Outcome, UserId},
+};
+
+
+fn config() -> Config {
+	Config
+		::from_toml(include_bytes!("../../config.toml"))
+		.expect("invalid config")
+}
+
+
+/// A messenger whose messages are sent at the clock's time, which starts at
+/// 2021-03-01 09:00 in the configured timezone.
+fn messenger() -> (FakeMessenger, ManualClock) {
+	let clock = ManualClock::new(
+		Utc.ymd(2021, 3, 1).and_hms(12, 0, 0)
+	);
+
+	(FakeMessenger::new(clock.clone()), clock)
+}
+
+
+/// An argon2id hash of "hunter2".
+const HASHED_PASSWORD: &str =
+	"$argon2id$v=19$m=19456,t=2,p=1$cmVtaW5kZXJib3RzYWx0IQ$j/e1XVnzoAY4NaYz+E8XKszzMrh5zun/O43kYf7hXZ8";
+
+
+/// The first plain text password in the config.
+fn plain_password(config: &Config) -> &str {
+	config.bot.authentication.passwords
+		.iter()
+		.find_map(
+			|password| match &password.secret {
+				Secret::Plain(plain) => Some(plain.as_ref()),
+				Secret::Hash(_) => None,
+			}
+		)
+		.expect("no plain password in config")
+}
+
+
+fn trusted_chat(db: &Db, chat_id: bot::ChatId) {
+	db
+		.new_trusted_chat(
+			&crate::db::models::trusted_chats::NewTrustedChat {
+				id: ChatId(chat_id),
+				username: None,
+				title: None,
+			}
+		)
+		.expect("failed to trust chat");
+}
+
+
+/// Runs the bot over the messages injected so far.
+async fn run_bot(db: &Db, messenger: &FakeMessenger, clock: &ManualClock, config: &Config) {
+	let authenticator = Authenticator
+		::new(messenger, db, clock, &config.bot.authentication)
+		.expect("failed to load trusted chats");
+
+	let reschedule = Notify::new();
+
+	launch_bot(
+		db,
+		messenger,
+		clock,
+		&authenticator,
+		&config.commands,
+		&config.words,
+		&config.messages,
+		config.bot.timezone,
+		&reschedule
+	)
+		.await;
+}
+
+
+#[tokio::test]
+async fn test_auth() {
+	let config = config();
+	let db = Db::open_in_memory();
+	let (messenger, clock) = messenger();
+
+	let chat_id = bot::ChatId::new(1);
+	let stranger = bot::ChatId::new(2);
+
+	messenger.push_joined(chat_id);
+	messenger.push_text(chat_id, plain_password(&config));
+	messenger.push_text(stranger, "chora");
+
+	run_bot(&db, &messenger, &clock, &config).await;
+
+	assert_eq!(
+		messenger.take_sent(),
+		vec![
+			Sent::Message {
+				chat: chat_id,
+				text: config.bot.authentication.prompt.to_string(),
+				buttons: Vec::new(),
+				reply_to: None,
+				mentions: Vec::new(),
+			},
+			Sent::Message {
+				chat: chat_id,
+				text: config.bot.authentication.authorized.to_string(),
+				buttons: Vec::new(),
+				reply_to: None,
+				mentions: Vec::new(),
+			},
+		]
+	);
+
+	// Trusted chats are remembered.
+	messenger.push_text(chat_id, "chora");
+
+	run_bot(&db, &messenger, &clock, &config).await;
+
+	assert_eq!(
+		messenger.take_texts(),
+		vec![config.messages.empty_message()]
+	);
+}
+
+
+#[tokio::test]
+async fn test_legacy_password() {
+	let toml = String
+		::from_utf8(include_bytes!("../../config.toml").to_vec())
+		.expect("config should be utf-8");
+
+	let start = toml.find("passwords = [").expect("no passwords in config");
+	let end = start + toml[start ..].find(']').expect("unterminated passwords") + 1;
+
+	let toml = format!("{}password = 'hunter2'{}", &toml[.. start], &toml[end ..]);
+
+	let config = Config
+		::from_toml(toml.as_bytes())
+		.expect("legacy password should be accepted");
+
+	let db = Db::open_in_memory();
+	let (messenger, clock) = messenger();
+
+	let chat_id = bot::ChatId::new(1);
+
+	messenger.push_text(chat_id, "hunter2");
+
+	run_bot(&db, &messenger, &clock, &config).await;
+
+	assert_eq!(
+		messenger.take_texts(),
+		vec![config.bot.authentication.authorized.to_string()]
+	);
+}
+
+
+#[tokio::test]
+async fn test_passwords() {
+	let mut config = config();
+	let db = Db::open_in_memory();
+	let (messenger, clock) = messenger();
+
+	config.bot.authentication.passwords = vec![
+		Password {
+			secret: Secret::Hash(HASHED_PASSWORD.into()),
+			expires: None,
+		},
+		Password {
+			secret: Secret::Plain("rotated".into()),
+			expires: Some(clock.now() - ChronoDuration::minutes(1)),
+		},
+	]
+		.into_boxed_slice();
+
+	let hashed = bot::ChatId::new(1);
+	let expired = bot::ChatId::new(2);
+
+	messenger.push_text(hashed, "hunter2");
+	messenger.push_text(expired, "rotated");
+	messenger.push_text(expired, "chora");
+
+	run_bot(&db, &messenger, &clock, &config).await;
+
+	assert_eq!(
+		messenger.take_texts(),
+		vec![config.bot.authentication.authorized.to_string()]
+	);
+
+	assert_eq!(
+		db.trusted_chat_ids().expect("failed to load trusted chats"),
+		vec![ChatId(hashed)].into_boxed_slice()
+	);
+}
+
+
+#[tokio::test]
+async fn test_throttling() {
+	let config = config();
+	let db = Db::open_in_memory();
+	let (messenger, clock) = messenger();
+
+	let chat_id = bot::ChatId::new(1);
+	let password = plain_password(&config);
+	let throttle = config.bot.authentication.throttle;
+
+	for _ in 0 .. throttle.attempts {
+		messenger.push_text(chat_id, "abre-te sésamo");
+	}
+
+	// Too late, even with the right password.
+	messenger.push_text(chat_id, password);
+	messenger.push_joined(chat_id);
+
+	run_bot(&db, &messenger, &clock, &config).await;
+
+	assert_eq!(
+		messenger.take_sent(),
+		vec![Sent::Left(chat_id), Sent::Left(chat_id)]
+	);
+
+	clock.advance(
+		ChronoDuration::minutes(throttle.period as i64)
+	);
+	messenger.push_text(chat_id, password);
+
+	run_bot(&db, &messenger, &clock, &config).await;
+
+	assert_eq!(
+		messenger.take_texts(),
+		vec![config.bot.authentication.authorized.to_string()]
+	);
+}
+
+
+#[tokio::test]
+async fn test_invites() {
+	let config = config();
+	let db = Db::open_in_memory();
+	let (messenger, clock) = messenger();
+
+	let chat_id = bot::ChatId::new(1);
+	trusted_chat(&db, chat_id);
+
+	let tester = bot::UserId::new(fake::USER_ID);
+
+	messenger.set_administrators(
+		chat_id,
+		vec![
+			bot::Administrator { user_id: tester, owner: true },
+		]
+	);
+
+	messenger.push_text(chat_id, "convite");
+	messenger.push_text(chat_id, "convite");
+
+	run_bot(&db, &messenger, &clock, &config).await;
+
+	let codes: Vec<String> = messenger
+		.take_texts()
+		.into_iter()
+		.map(
+			|text| {
+				let (header, code) = text
+					.split_once('\n')
+					.expect("missing invite code");
+
+				assert_eq!(header, config.messages.invite_message());
+				code.into()
+			}
+		)
+		.collect();
+
+	assert_ne!(codes[0], codes[1]);
+
+	// Invites can only be redeemed once.
+	let invited = bot::ChatId::new(2);
+	let late = bot::ChatId::new(3);
+
+	messenger.push_text(invited, &codes[0]);
+	messenger.push_text(late, &codes[0]);
+
+	run_bot(&db, &messenger, &clock, &config).await;
+
+	assert_eq!(
+		messenger.take_sent(),
+		vec![
+			Sent::Message {
+				chat: invited,
+				text: config.bot.authentication.authorized.to_string(),
+				buttons: Vec::new(),
+				reply_to: None,
+				mentions: Vec::new(),
+			}
+		]
+	);
+
+	// And expire.
+	clock.advance(
+		ChronoDuration::days(1)
+	);
+	messenger.push_text(late, &codes[1]);
+
+	run_bot(&db, &messenger, &clock, &config).await;
+
+	assert_eq!(messenger.take_sent(), Vec::new());
+
+	// Only admins create them.
+	messenger.set_sender(bot::UserId::new(200), "stranger");
+	messenger.push_text(chat_id, "convite");
+
+	run_bot(&db, &messenger, &clock, &config).await;
+
+	assert_eq!(
+		messenger.take_texts(),
+		vec![config.messages.forbidden_message()]
+	);
+}
+
+
+#[tokio::test]
+async fn test_operator() {
+	let mut config = config();
+	let db = Db::open_in_memory();
+	let (messenger, clock) = messenger();
+
+	let operator = bot::ChatId::new(10);
+	config.bot.authentication.operator = Some(operator.into());
+
+	let revoked = bot::ChatId::new(1);
+	let left = bot::ChatId::new(2);
+	trusted_chat(&db, revoked);
+	trusted_chat(&db, left);
+
+	messenger.push_text(revoked, "in 1h water plants");
+
+	run_bot(&db, &messenger, &clock, &config).await;
+	messenger.take_sent();
+
+	let operator_message = |text: &str| Sent::Message {
+		chat: operator,
+		text: text.into(),
+		buttons: Vec::new(),
+		reply_to: None,
+		mentions: Vec::new(),
+	};
+
+	messenger.push_text(operator, "chats");
+	messenger.push_text(operator, "revoke 1 purge");
+	messenger.push_text(operator, "leave 2");
+	messenger.push_text(operator, "revoke -3");
+	messenger.push_text(revoked, "chora");
+	messenger.push_text(left, "chora");
+
+	run_bot(&db, &messenger, &clock, &config).await;
+
+	let chat_id = bot::ChatId::new(-3);
+
+	assert_eq!(
+		messenger.take_sent(),
+		vec![
+			operator_message("1\n2\n"),
+			Sent::Left(revoked),
+			operator_message("Revoked chat 1. Purged 1 reminders."),
+			Sent::Left(left),
+			operator_message("Left chat 2."),
+			Sent::Left(chat_id),
+			operator_message("Chat -3 wasn't trusted."),
+			Sent::Message {
+				chat: left,
+				text: config.messages.empty_message().into(),
+				buttons: Vec::new(),
+				reply_to: None,
+				mentions: Vec::new(),
+			},
+		]
+	);
+
+	assert_eq!(
+		db.chat_reminders(ChatId(revoked)).expect("failed to load reminders"),
+		Vec::new().into_boxed_slice()
+	);
+
+	// The operator chat is trusted as any other.
+	messenger.push_text(operator, "chora");
+
+	run_bot(&db, &messenger, &clock, &config).await;
+
+	assert_eq!(
+		messenger.take_texts(),
+		vec![config.messages.empty_message()]
+	);
+}
+
+
+#[tokio::test]
+async fn test_add_list_remove() {
+	let config = config();
+	let db = Db::open_in_memory();
+	let (messenger, clock) = messenger();
+
+	let chat_id = bot::ChatId::new(1);
+	trusted_chat(&db, chat_id);
+
+	messenger.push_text(chat_id, "2021-03-02 10:00 +1d water plants");
+	messenger.push_text(chat_id, "chora");
+
+	run_bot(&db, &messenger, &clock, &config).await;
+
+	let reminder = "(1) 2021-03-02 10:00 +1d: water plants — tester";
+
+	assert_eq!(
+		messenger.take_texts(),
+		vec![
+			format!("{}\n2021-03-02 10:00 +1d: water plants", config.messages.added_message()),
+			format!("{}\n{}\n", config.messages.list_header_message(), reminder),
+		]
+	);
+
+	messenger.push_text(chat_id, "cancela 1");
+	messenger.push_text(chat_id, "cancela 1");
+	messenger.push_text(chat_id, "chora");
+
+	run_bot(&db, &messenger, &clock, &config).await;
+
+	assert_eq!(
+		messenger.take_texts(),
+		vec![
+			config.messages.removed_message(),
+			config.messages.not_found_message(),
+			config.messages.empty_message(),
+		]
+	);
+}
+
+
+#[tokio::test]
+async fn test_misunderstanding() {
+	let config = config();
+	let db = Db::open_in_memory();
+	let (messenger, clock) = messenger();
+
+	let chat_id = bot::ChatId::new(1);
+	trusted_chat(&db, chat_id);
+
+	messenger.push_text(chat_id, "what?");
+
+	run_bot(&db, &messenger, &clock, &config).await;
+
+	let texts = messenger.take_texts();
+
+	assert_eq!(texts.len(), 1);
+	assert!(
+		config.messages.misunderstanding_messages
+			.iter()
+			.any(
+				|message| message.as_ref() == texts[0]
+			)
+	);
+}
+
+
+async fn run_notifications_at(
+	db: &Db,
+	messenger: &FakeMessenger,
+	clock: &ManualClock,
+	config: &Config,
+	catch_up: CatchUp,
+) {
+	run_notifications(db, messenger, clock, &config.messages, config.bot.timezone, catch_up)
+		.await
+		.expect("failed to run notifications");
+}
+
+
+fn reminders(db: &Db, chat_id: bot::ChatId) -> Vec<Reminder> {
+	db
+		.chat_reminders(ChatId(chat_id))
+		.expect("failed to list reminders")
+		.into_vec()
+}
+
+
+#[tokio::test]
+async fn test_one_shot_delivery() {
+	let config = config();
+	let db = Db::open_in_memory();
+	let (messenger, clock) = messenger();
+
+	let chat_id = bot::ChatId::new(1);
+	trusted_chat(&db, chat_id);
+
+	messenger.push_text(chat_id, "in 1h stretch");
+
+	run_bot(&db, &messenger, &clock, &config).await;
+	messenger.take_sent();
+
+	assert_eq!(
+		time_until_next_due(&db, &clock),
+		Duration::from_secs(NOTIFICATOR_MAX_SLEEP)
+	);
+
+	clock.advance(ChronoDuration::minutes(59));
+
+	assert_eq!(time_until_next_due(&db, &clock), Duration::from_secs(60));
+
+	run_notifications_at(&db, &messenger, &clock, &config, CatchUp::Once).await;
+
+	assert_eq!(messenger.take_sent(), Vec::new());
+
+	clock.advance(ChronoDuration::minutes(1));
+
+	run_notifications_at(&db, &messenger, &clock, &config, CatchUp::Once).await;
+
+	assert_eq!(
+		messenger.take_sent(),
+		vec![
+			Sent::Message {
+				chat: chat_id,
+				text: "stretch".into(),
+				buttons: action::SNOOZE_BUTTONS
+					.iter()
+					.map(
+						|(_, data)| String::from(*data)
+					)
+					.collect(),
+				reply_to: None,
+				mentions: Vec::new(),
+			}
+		]
+	);
+
+	assert_eq!(reminders(&db, chat_id), Vec::new());
+}
+
+
+#[tokio::test]
+async fn test_recurring_delivery() {
+	let config = config();
+	let db = Db::open_in_memory();
+	let (messenger, clock) = messenger();
+
+	let chat_id = bot::ChatId::new(1);
+	trusted_chat(&db, chat_id);
+
+	messenger.push_text(chat_id, "2021-03-01 10:00 +1d x3 standup");
+
+	run_bot(&db, &messenger, &clock, &config).await;
+	messenger.take_sent();
+
+	clock.advance(ChronoDuration::hours(1));
+
+	for remaining in (0 .. 3).rev() {
+		run_notifications_at(&db, &messenger, &clock, &config, CatchUp::Once).await;
+
+		assert_eq!(messenger.take_texts(), vec!["standup"]);
+
+		let reminders = reminders(&db, chat_id);
+
+		if remaining > 0 {
+			assert_eq!(reminders.len(), 1);
+			assert_eq!(reminders[0].due.0, clock.now() + ChronoDuration::days(1));
+			assert_eq!(reminders[0].recur_remaining, Some(remaining));
+		}
+		else {
+			assert_eq!(reminders, Vec::new());
+		}
+
+		clock.advance(ChronoDuration::days(1));
+	}
+
+	run_notifications_at(&db, &messenger, &clock, &config, CatchUp::Once).await;
+
+	assert_eq!(messenger.take_sent(), Vec::new());
+}
+
+
+#[tokio::test]
+async fn test_catch_up() {
+	let config = config();
+
+	let late_text = |lateness| action::late_reminder_text(
+		"drink water",
+		config.messages.late_message(),
+		lateness
+	);
+
+	for &catch_up in [CatchUp::All, CatchUp::Once, CatchUp::Skip].iter() {
+		let db = Db::open_in_memory();
+		let (messenger, clock) = messenger();
+
+		let chat_id = bot::ChatId::new(1);
+		trusted_chat(&db, chat_id);
+
+		messenger.push_text(chat_id, "2021-03-01 10:00 +1h drink water");
+
+		run_bot(&db, &messenger, &clock, &config).await;
+		messenger.take_sent();
+
+		// Down from 09:00 to 13:30, missing the 10:00, 11:00, 12:00 and 13:00 occurrences.
+		clock.advance(
+			ChronoDuration::hours(4) + ChronoDuration::minutes(30)
+		);
+
+		let mut texts = Vec::new();
+
+		for _ in 0 .. 5 {
+			run_notifications_at(&db, &messenger, &clock, &config, catch_up).await;
+			texts.extend(messenger.take_texts());
+		}
+
+		let expected = match catch_up {
+			CatchUp::All => vec![
+				late_text(ChronoDuration::minutes(3 * 60 + 30)),
+				late_text(ChronoDuration::minutes(2 * 60 + 30)),
+				late_text(ChronoDuration::minutes(60 + 30)),
+				late_text(ChronoDuration::minutes(30)),
+			],
+			CatchUp::Once => vec![
+				late_text(ChronoDuration::minutes(3 * 60 + 30)),
+			],
+			CatchUp::Skip => vec![
+				format!(
+					"{}\n{}",
+					config.messages.skipped_message(),
+					late_text(ChronoDuration::minutes(3 * 60 + 30))
+				),
+			],
+		};
+
+		assert_eq!(texts, expected, "catch up: {:?}", catch_up);
+
+		// The next occurrence is in the future, at 14:00.
+		let reminders = reminders(&db, chat_id);
+
+		assert_eq!(reminders.len(), 1);
+		assert_eq!(reminders[0].due.0, clock.now() + ChronoDuration::minutes(30));
+	}
+
+	// Retries of a failed delivery aren't late, even when skipping late reminders.
+	let db = Db::open_in_memory();
+	let (messenger, clock) = messenger();
+
+	let chat_id = bot::ChatId::new(1);
+	trusted_chat(&db, chat_id);
+
+	messenger.push_text(chat_id, "2021-03-01 10:00 drink water");
+
+	run_bot(&db, &messenger, &clock, &config).await;
+	messenger.take_sent();
+
+	clock.advance(
+		ChronoDuration::hours(1) + ChronoDuration::seconds(2 * NOTIFICATOR_RETRY_INTERVAL as i64)
+	);
+
+	run_notifications_at(&db, &messenger, &clock, &config, CatchUp::Skip).await;
+
+	assert_eq!(messenger.take_texts(), vec!["drink water"]);
+}
+
+
+#[tokio::test]
+async fn test_reply() {
+	let config = config();
+	let db = Db::open_in_memory();
+	let (messenger, clock) = messenger();
+
+	let chat_id = bot::ChatId::new(1);
+	trusted_chat(&db, chat_id);
+
+	let source = bot::MessageId::new(7);
+
+	messenger.push_reply(
+		chat_id,
+		"in 1h",
+		bot::Reply {
+			message_id: source,
+			text: Some("pay the rent".into()),
+			author: None,
+		}
+	);
+
+	// Only replies may leave out the text.
+	messenger.push_text(chat_id, "in 1h");
+
+	run_bot(&db, &messenger, &clock, &config).await;
+
+	let texts = messenger.take_texts();
+
+	assert_eq!(texts.len(), 2);
+	assert!(texts[0].ends_with(": pay the rent"), "{}", texts[0]);
+	assert!(
+		config.messages.misunderstanding_messages
+			.iter()
+			.any(
+				|message| message.as_ref() == texts[1]
+			)
+	);
+
+	clock.advance(ChronoDuration::hours(1));
+
+	run_notifications_at(&db, &messenger, &clock, &config, CatchUp::Once).await;
+
+	assert_eq!(
+		messenger.take_sent(),
+		vec![
+			Sent::Message {
+				chat: chat_id,
+				text: "pay the rent".into(),
+				buttons: action::SNOOZE_BUTTONS
+					.iter()
+					.map(
+						|(_, data)| String::from(*data)
+					)
+					.collect(),
+				reply_to: Some(source),
+				mentions: Vec::new(),
+			}
+		]
+	);
+}
+
+
+#[tokio::test]
+async fn test_media() {
+	let config = config();
+	let db = Db::open_in_memory();
+	let (messenger, clock) = messenger();
+
+	let chat_id = bot::ChatId::new(1);
+	trusted_chat(&db, chat_id);
+
+	let media = bot::Media {
+		kind: bot::MediaKind::Document,
+		file_id: "boleto.pdf".into(),
+	};
+
+	messenger.push_media(chat_id, "in 1h pay the boleto", media.clone());
+
+	run_bot(&db, &messenger, &clock, &config).await;
+
+	let texts = messenger.take_texts();
+
+	assert_eq!(texts.len(), 1);
+	assert!(texts[0].ends_with(": 📎 pay the boleto"), "{}", texts[0]);
+
+	clock.advance(ChronoDuration::hours(1));
+
+	run_notifications_at(&db, &messenger, &clock, &config, CatchUp::Once).await;
+
+	assert_eq!(
+		messenger.take_sent(),
+		vec![
+			Sent::Media {
+				chat: chat_id,
+				media: media.clone(),
+				caption: "pay the boleto".into(),
+				buttons: action::SNOOZE_BUTTONS
+					.iter()
+					.map(
+						|(_, data)| String::from(*data)
+					)
+					.collect(),
+				reply_to: None,
+				mentions: Vec::new(),
+			}
+		]
+	);
+
+	// Users mentioned in the caption are tagged.
+	let joao = bot::Mention::User { id: bot::UserId::new(300), name: "João".into() };
+
+	messenger.push_media_mentions(chat_id, "in 1h João pay the boleto", media, &[joao.clone()]);
+
+	run_bot(&db, &messenger, &clock, &config).await;
+	messenger.take_sent();
+
+	clock.advance(ChronoDuration::hours(1));
+
+	run_notifications_at(&db, &messenger, &clock, &config, CatchUp::Once).await;
+
+	match messenger.take_sent().as_slice() {
+		[Sent::Media { mentions, .. }] => assert_eq!(mentions, &vec![joao]),
+		sent => panic!("unexpected messages: {:?}", sent),
+	}
+}
+
+
+#[tokio::test]
+async fn test_export_import() {
+	let config = config();
+	let db = Db::open_in_memory();
+	let (messenger, clock) = messenger();
+
+	let exporter = bot::ChatId::new(1);
+	let importer = bot::ChatId::new(2);
+	trusted_chat(&db, exporter);
+	trusted_chat(&db, importer);
+
+	messenger.push_text(exporter, "2021-03-02 10:00 +1d water plants");
+	messenger.push_text(exporter, "exporta");
+	messenger.push_text(exporter, "exporta ics");
+
+	run_bot(&db, &messenger, &clock, &config).await;
+
+	let files: Vec<_> = messenger
+		.take_sent()
+		.into_iter()
+		.filter_map(
+			|sent| match sent {
+				Sent::File { chat, name, data } => {
+					assert_eq!(chat, exporter);
+					Some((name, data))
+				}
+				_ => None,
+			}
+		)
+		.collect();
+
+	assert_eq!(files.len(), 2);
+	assert_eq!(files[0].0, "reminders.json");
+	assert_eq!(files[1].0, "reminders.ics");
+
+	for (name, data) in files.iter() {
+		messenger.add_file(name, data);
+
+		messenger.push_media(
+			importer,
+			"importa",
+			bot::Media {
+				kind: bot::MediaKind::Document,
+				file_id: name.as_str().into(),
+			}
+		);
+	}
+
+	messenger.push_text(importer, "importa");
+
+	run_bot(&db, &messenger, &clock, &config).await;
+
+	let imported = format!("{}\n2021-03-02 10:00 +1d: water plants", config.messages.imported_message());
+
+	assert_eq!(
+		messenger.take_texts(),
+		vec![
+			imported.clone(),
+			imported,
+			config.messages.invalid_file_message().into(),
+		]
+	);
+
+	let exported = reminders(&db, exporter);
+	let imported = reminders(&db, importer);
+
+	assert_eq!(imported.len(), 2);
+
+	for reminder in imported.iter() {
+		assert_eq!(reminder.due, exported[0].due);
+		assert_eq!(reminder.recurrying, exported[0].recurrying);
+		assert_eq!(reminder.message, exported[0].message);
+		assert_eq!(reminder.creator_id, exported[0].creator_id);
+	}
+}
+
+
+#[tokio::test]
+async fn test_calendar_upload() {
+	let config = config();
+	let db = Db::open_in_memory();
+	let (messenger, clock) = messenger();
+
+	let chat_id = bot::ChatId::new(1);
+	trusted_chat(&db, chat_id);
+
+	let ics = "\
+BEGIN:VCALENDAR\r
+BEGIN:VEVENT\r
+DTSTART:20210302T130000Z\r
+RRULE:FREQ=WEEKLY;BYDAY=TU,TH;COUNT=10\r
+SUMMARY:stand-up\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+DTSTART:20210305T130000Z\r
+RRULE:FREQ=YEARLY;BYMONTH=3\r
+SUMMARY:taxes\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+DTSTART:20210310T150000Z\r
+SUMMARY:dentist\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+
+	messenger.add_file("calendar", ics.as_bytes());
+
+	messenger.push_calendar(
+		chat_id,
+		bot::Media {
+			kind: bot::MediaKind::Document,
+			file_id: "calendar".into(),
+		}
+	);
+
+	run_bot(&db, &messenger, &clock, &config).await;
+
+	assert_eq!(
+		messenger.take_texts(),
+		vec![
+			format!(
+				"{}\n{}\n{}\n\n{}\n{}",
+				config.messages.imported_message(),
+				"2021-03-02 10:00 +tue,thu x10: stand-up",
+				"2021-03-10 12:00: dentist",
+				config.messages.unsupported_events_message(),
+				"#2 taxes: unsupported recurrence: FREQ=YEARLY;BYMONTH=3",
+			),
+		]
+	);
+
+	let reminders = reminders(&db, chat_id);
+
+	assert_eq!(reminders.len(), 2);
+	assert_eq!(
+		reminders[0].creator_id,
+		Some(
+			UserId(
+				bot::UserId::new(fake::USER_ID)
+			)
+		)
+	);
+}
+
+
+#[tokio::test]
+async fn test_mentions() {
+	let config = config();
+	let db = Db::open_in_memory();
+	let (messenger, clock) = messenger();
+
+	let chat_id = bot::ChatId::new(1);
+	trusted_chat(&db, chat_id);
+
+	let joao = bot::Mention::User {
+		id: bot::UserId::new(42),
+		name: "João".into(),
+	};
+
+	messenger.push_mentions(chat_id, "in 1h @ana João standup", std::slice::from_ref(&joao));
+	messenger.push_text(chat_id, "chora");
+
+	run_bot(&db, &messenger, &clock, &config).await;
+
+	let texts = messenger.take_texts();
+
+	assert_eq!(texts.len(), 2);
+	assert!(texts[0].ends_with(": standup → ana, João"), "{}", texts[0]);
+	assert!(texts[1].ends_with(": standup → ana, João — tester\n"), "{}", texts[1]);
+
+	let reminders = reminders(&db, chat_id);
+
+	assert_eq!(reminders.len(), 1);
+	assert_eq!(reminders[0].creator_id, Some(UserId(bot::UserId::new(fake::USER_ID))));
+	assert_eq!(reminders[0].creator_name.as_deref(), Some(fake::USER_NICKNAME));
+
+	clock.advance(ChronoDuration::hours(1));
+
+	run_notifications_at(&db, &messenger, &clock, &config, CatchUp::Once).await;
+
+	assert_eq!(
+		messenger.take_sent(),
+		vec![
+			Sent::Message {
+				chat: chat_id,
+				text: "standup".into(),
+				buttons: action::SNOOZE_BUTTONS
+					.iter()
+					.map(
+						|(_, data)| String::from(*data)
+					)
+					.collect(),
+				reply_to: None,
+				mentions: vec![bot::Mention::Username("ana".into()), joao],
+			}
+		]
+	);
+}
+
+
+#[tokio::test]
+async fn test_private() {
+	let config = config();
+	let db = Db::open_in_memory();
+	let (messenger, clock) = messenger();
+
+	let chat_id = bot::ChatId::new(1);
+	trusted_chat(&db, chat_id);
+
+	let user_id = bot::UserId::new(fake::USER_ID);
+	let private_chat = bot::ChatId::from(user_id);
+
+	let buttons: Vec<_> = action::SNOOZE_BUTTONS
+		.iter()
+		.map(
+			|(_, data)| String::from(*data)
+		)
+		.collect();
+
+	messenger.push_text(chat_id, "in 1h dm take the pills");
+
+	run_bot(&db, &messenger, &clock, &config).await;
+
+	let texts = messenger.take_texts();
+
+	assert_eq!(texts.len(), 1);
+	assert!(texts[0].ends_with(": 🔒 take the pills"), "{}", texts[0]);
+
+	clock.advance(ChronoDuration::hours(1));
+
+	run_notifications_at(&db, &messenger, &clock, &config, CatchUp::Once).await;
+
+	assert_eq!(
+		messenger.take_sent(),
+		vec![
+			Sent::Message {
+				chat: private_chat,
+				text: "take the pills".into(),
+				buttons: buttons.clone(),
+				reply_to: None,
+				mentions: Vec::new(),
+			}
+		]
+	);
+
+	// Buttons of other messages in untrusted private chats are ignored.
+	messenger.push_private_callback(private_chat, bot::MessageId::new(5), "take the pills", "done");
+	messenger.push_private_callback(bot::ChatId::new(400), bot::MessageId::new(1), "take the pills", "done");
+
+	run_bot(&db, &messenger, &clock, &config).await;
+
+	assert_eq!(messenger.take_sent(), Vec::new());
+
+	// Buttons of reminders delivered there work, though the private chat isn't trusted.
+	messenger.push_private_callback(private_chat, bot::MessageId::new(1), "take the pills", "done");
+
+	run_bot(&db, &messenger, &clock, &config).await;
+
+	let sent = messenger.take_sent();
+
+	assert!(
+		sent
+			.iter()
+			.any(
+				|sent| matches!(sent, Sent::Answer { .. })
+			),
+		"{:?}",
+		sent
+	);
+
+	assert!(!sent.contains(&Sent::Left(private_chat)));
+
+	// Users who never started a chat with the bot are reminded in the group.
+	messenger.set_unreachable(private_chat);
+	messenger.push_text(chat_id, "in 1h dm call the bank");
+
+	run_bot(&db, &messenger, &clock, &config).await;
+	messenger.take_sent();
+
+	clock.advance(ChronoDuration::hours(1));
+
+	run_notifications_at(&db, &messenger, &clock, &config, CatchUp::Once).await;
+
+	let unreachable = &config.messages.unreachable_messages[0];
+
+	assert_eq!(
+		messenger.take_sent(),
+		vec![
+			Sent::Message {
+				chat: chat_id,
+				text: format!("{}\ncall the bank", unreachable),
+				buttons,
+				reply_to: None,
+				mentions: vec![
+					bot::Mention::User {
+						id: user_id,
+						name: fake::USER_NICKNAME.into(),
+					}
+				],
+			}
+		]
+	);
+
+	// Snoozing it keeps the reminder's own text.
+	let delivered = db
+		.chat_deliveries(ChatId(chat_id), 1)
+		.expect("failed to list deliveries")[0]
+		.message
+		.expect("reminder should have been delivered");
+
+	messenger.push_callback(chat_id, delivered.0, &format!("{}\ncall the bank", unreachable), "snooze 10");
+
+	run_bot(&db, &messenger, &clock, &config).await;
+	messenger.take_sent();
+
+	let reminders = reminders(&db, chat_id);
+
+	assert_eq!(reminders.len(), 1);
+	assert_eq!(reminders[0].message, "call the bank");
+}
+
+
+#[tokio::test]
+async fn test_history() {
+	let config = config();
+	let db = Db::open_in_memory();
+	let (messenger, clock) = messenger();
+
+	let chat_id = bot::ChatId::new(1);
+	trusted_chat(&db, chat_id);
+
+	let unreachable_chat = bot::ChatId::new(2);
+	trusted_chat(&db, unreachable_chat);
+
+	messenger.push_text(chat_id, "in 1h stretch");
+	messenger.push_text(chat_id, "2021-03-01 11:00 call mom");
+	messenger.push_text(unreachable_chat, "in 1h pay rent");
+	messenger.push_text(chat_id, "historico");
+
+	run_bot(&db, &messenger, &clock, &config).await;
+
+	assert_eq!(
+		messenger.take_texts().last(),
+		Some(&config.messages.empty_message().into())
+	);
+
+	messenger.set_unreachable(unreachable_chat);
+
+	clock.advance(ChronoDuration::hours(1));
+
+	let result = run_notifications(&db, &messenger, &clock, &config.messages, config.bot.timezone, CatchUp::Once).await;
+
+	assert!(result.is_err());
+	assert_eq!(messenger.take_texts(), vec!["stretch"]);
+
+	// Retries of a failing reminder don't add deliveries.
+	clock.advance(ChronoDuration::minutes(1));
+
+	let result = run_notifications(&db, &messenger, &clock, &config.messages, config.bot.timezone, CatchUp::Once).await;
+
+	assert!(result.is_err());
+
+	// Failures are kept after the reminder is gone, and its id isn't reused by the snoozed one.
+	db
+		.delete_reminder(3)
+		.expect("failed to delete reminder");
+
+	let failed = db
+		.chat_deliveries(ChatId(unreachable_chat), 10)
+		.expect("failed to list deliveries");
+
+	assert_eq!(failed.len(), 1);
+	assert_eq!(failed[0].reminder, 3);
+	assert_eq!(failed[0].outcome, Outcome::Failed);
+	assert_eq!(failed[0].message, None);
+	assert_eq!(failed[0].sent.0, failed[0].scheduled.0 + ChronoDuration::minutes(1));
+
+	messenger.push_callback(chat_id, bot::MessageId::new(1), "stretch", "snooze 10");
+
+	run_bot(&db, &messenger, &clock, &config).await;
+	messenger.take_sent();
+
+	clock.advance(
+		ChronoDuration::hours(1) + ChronoDuration::minutes(30)
+	);
+
+	run_notifications_at(&db, &messenger, &clock, &config, CatchUp::Once).await;
+	messenger.take_sent();
+
+	messenger.push_text(chat_id, "historico");
+	messenger.push_text(chat_id, "historico 1");
+
+	run_bot(&db, &messenger, &clock, &config).await;
+
+	let header = config.messages.history_header_message();
+
+	assert_eq!(
+		messenger.take_texts(),
+		vec![
+			format!(
+				"{}\n{}\n{}\n{}\n",
+				header,
+				"(1) 2021-03-01 10:00 snoozed: stretch",
+				"(4) 2021-03-01 10:11 → 2021-03-01 11:31 sent: stretch",
+				"(2) 2021-03-01 11:00 → 2021-03-01 11:31 sent: call mom",
+			),
+			format!("{}\n(2) 2021-03-01 11:00 → 2021-03-01 11:31 sent: call mom\n", header),
+		]
+	);
+}
+
+
+#[tokio::test]
+async fn test_roles() {
+	let config = config();
+	let db = Db::open_in_memory();
+	let (messenger, clock) = messenger();
+
+	let chat_id = bot::ChatId::new(1);
+	trusted_chat(&db, chat_id);
+
+	let tester = bot::UserId::new(fake::USER_ID);
+	let owner = bot::UserId::new(200);
+
+	messenger.set_administrators(
+		chat_id,
+		vec![
+			bot::Administrator { user_id: owner, owner: true },
+		]
+	);
+
+	let as_owner = || messenger.set_sender(owner, "boss");
+	let as_tester = || messenger.set_sender(tester, fake::USER_NICKNAME);
+
+	as_tester();
+	messenger.push_text(chat_id, "in 1h water plants");
+	as_owner();
+	messenger.push_text(chat_id, "in 1h feed the cat");
+
+	run_bot(&db, &messenger, &clock, &config).await;
+	messenger.take_sent();
+
+	let forbidden = config.messages.forbidden_message();
+
+	// Everyone manages every reminder, until admins restrict it.
+	as_tester();
+	messenger.push_text(chat_id, "muda 2 10:00");
+	as_owner();
+	messenger.push_text(chat_id, "geral off");
+
+	run_bot(&db, &messenger, &clock, &config).await;
+
+	let texts = messenger.take_texts();
+
+	assert_eq!(texts.len(), 2);
+	assert_ne!(texts[0], forbidden);
+	assert_eq!(texts[1], config.messages.admins_manage_message());
+
+	// Members only manage their own reminders.
+	as_tester();
+	messenger.push_text(chat_id, "cancela 2");
+	messenger.push_text(chat_id, "muda 2 10:00");
+	messenger.push_text(chat_id, "geral on");
+	messenger.push_mentions(
+		chat_id,
+		"promove tester",
+		&[bot::Mention::User { id: tester, name: "tester".into() }]
+	);
+	messenger.push_text(chat_id, "cancela 1");
+
+	run_bot(&db, &messenger, &clock, &config).await;
+
+	assert_eq!(
+		messenger.take_texts(),
+		vec![
+			forbidden,
+			forbidden,
+			forbidden,
+			forbidden,
+			config.messages.removed_message(),
+		]
+	);
+
+	// Admins manage everything, but owners keep their role.
+	as_owner();
+	messenger.push_reply(
+		chat_id,
+		"promove",
+		bot::Reply {
+			message_id: bot::MessageId::new(3),
+			text: Some("cancela 2".into()),
+			author: Some(
+				bot::Mention::User { id: tester, name: "tester".into() }
+			),
+		}
+	);
+	as_tester();
+	messenger.push_mentions(
+		chat_id,
+		"rebaixa boss",
+		&[bot::Mention::User { id: owner, name: "boss".into() }]
+	);
+	messenger.push_text(chat_id, "cancela 2");
+
+	run_bot(&db, &messenger, &clock, &config).await;
+
+	assert_eq!(
+		messenger.take_texts(),
+		vec![
+			format!("{}\ntester", config.messages.granted_message()),
+			forbidden.into(),
+			config.messages.removed_message().into(),
+		]
+	);
+
+	// Unless everyone may manage everything.
+	as_owner();
+	messenger.push_text(chat_id, "in 1h walk the dog");
+	messenger.push_mentions( // Led by the bot's own mention, as in groups.
+		chat_id,
+		"rebaixa tester",
+		&[
+			bot::Mention::Username("reminder_bot".into()),
+			bot::Mention::User { id: tester, name: "tester".into() },
+		]
+	);
+	messenger.push_text(chat_id, "geral on");
+	as_tester();
+	messenger.push_text(chat_id, "cancela 3"); // Ids aren't reused, even once the table is empty.
+
+	run_bot(&db, &messenger, &clock, &config).await;
+
+	let texts = messenger.take_texts();
+
+	assert_eq!(texts.len(), 4);
+	assert_eq!(texts[1], format!("{}\ntester", config.messages.revoked_message()));
+	assert_eq!(texts[2], config.messages.everyone_manages_message());
+	assert_eq!(texts[3], config.messages.removed_message());
+
+	// Administrators added later get their role once the bot restarts, while revoked ones
+	// stay members.
+	let newcomer = bot::UserId::new(300);
+
+	messenger.set_administrators(
+		chat_id,
+		vec![
+			bot::Administrator { user_id: owner, owner: true },
+			bot::Administrator { user_id: tester, owner: false },
+			bot::Administrator { user_id: newcomer, owner: false },
+		]
+	);
+
+	messenger.push_text(chat_id, "chora");
+
+	run_bot(&db, &messenger, &clock, &config).await;
+	messenger.take_sent();
+
+	let role = |user_id| db
+		.chat_role(ChatId(chat_id), UserId(user_id))
+		.expect("failed to get role");
+
+	assert_eq!(role(newcomer), Some(Role::Admin));
+	assert_eq!(role(tester), Some(Role::Member));
+	assert_eq!(role(owner), Some(Role::Owner));
+
+	// Private chats have no admins, so their user manages them.
+	let private_chat = bot::ChatId::new(fake::USER_ID);
+	trusted_chat(&db, private_chat);
+
+	as_tester();
+	messenger.push_text(private_chat, "geral off");
+	messenger.push_text(private_chat, "geral on");
+
+	run_bot(&db, &messenger, &clock, &config).await;
+
+	assert_eq!(
+		messenger.take_texts(),
+		vec![
+			config.messages.admins_manage_message(),
+			config.messages.everyone_manages_message(),
+		]
+	);
+}
+
+
+#[tokio::test]
+async fn test_snooze_forbidden() {
+	let config = config();
+	let db = Db::open_in_memory();
+	let (messenger, clock) = messenger();
+
+	let chat_id = bot::ChatId::new(1);
+	trusted_chat(&db, chat_id);
+
+	let tester = bot::UserId::new(fake::USER_ID);
+	let owner = bot::UserId::new(200);
+
+	messenger.set_administrators(
+		chat_id,
+		vec![
+			bot::Administrator { user_id: owner, owner: true },
+		]
+	);
+
+	messenger.push_text(chat_id, "in 1h stretch");
+	messenger.set_sender(owner, "boss");
+	messenger.push_text(chat_id, "geral off");
+
+	run_bot(&db, &messenger, &clock, &config).await;
+	messenger.take_sent();
+
+	clock.advance(ChronoDuration::hours(1));
+
+	run_notifications_at(&db, &messenger, &clock, &config, CatchUp::Once).await;
+	messenger.take_sent();
+
+	// Only those who may manage the reminder press its buttons.
+	messenger.set_sender(bot::UserId::new(300), "someone");
+	messenger.push_callback(chat_id, bot::MessageId::new(1), "stretch", "snooze 10");
+	messenger.push_callback(chat_id, bot::MessageId::new(1), "stretch", "done");
+	messenger.set_sender(tester, fake::USER_NICKNAME);
+	messenger.push_callback(chat_id, bot::MessageId::new(1), "stretch", "done");
+
+	run_bot(&db, &messenger, &clock, &config).await;
+
+	let forbidden = Sent::Answer { text: config.messages.forbidden_message().into() };
+	let done = config.messages.done_message();
+
+	assert_eq!(
+		messenger.take_sent(),
+		vec![
+			forbidden.clone(),
+			forbidden,
+			Sent::Answer { text: done.into() },
+			Sent::Edit {
+				chat: chat_id,
+				message: bot::MessageId::new(1),
+				text: format!("stretch\n\n{}", done),
+			},
+		]
+	);
+
+	assert!(reminders(&db, chat_id).is_empty());
+}
+
+
+#[tokio::test]
+async fn test_snooze() {
+	let config = config();
+	let db = Db::open_in_memory();
+	let (messenger, clock) = messenger();
+
+	let chat_id = bot::ChatId::new(1);
+	trusted_chat(&db, chat_id);
+
+	messenger.push_text(chat_id, "in 1h stretch");
+
+	run_bot(&db, &messenger, &clock, &config).await;
+	messenger.take_sent();
+
+	clock.advance(ChronoDuration::hours(1));
+
+	run_notifications_at(&db, &messenger, &clock, &config, CatchUp::Once).await;
+	messenger.take_sent();
+
+	// Two members press before the buttons are gone.
+	messenger.set_sender(bot::UserId::new(200), "boss");
+	messenger.push_callback(chat_id, bot::MessageId::new(1), "stretch", "snooze tomorrow");
+	messenger.push_callback(chat_id, bot::MessageId::new(1), "stretch", "snooze 10");
+
+	run_bot(&db, &messenger, &clock, &config).await;
+
+	let snoozed = format!("{} 2021-03-02 10:00", config.messages.snoozed_message());
+
+	assert_eq!(
+		messenger.take_sent(),
+		vec![
+			Sent::Answer { text: snoozed.clone() },
+			Sent::Edit {
+				chat: chat_id,
+				message: bot::MessageId::new(1),
+				text: format!("stretch\n\n{}", snoozed),
+			},
+			Sent::Answer { text: config.messages.not_found_message().into() },
+		]
+	);
+
+	let reminders = reminders(&db, chat_id);
+
+	assert_eq!(reminders.len(), 1);
+	assert_eq!(reminders[0].due.local(config.bot.timezone).to_string(), "2021-03-02 10:00:00");
+	assert_eq!(reminders[0].creator_id, Some(UserId(bot::UserId::new(fake::USER_ID))));
+	assert_eq!(reminders[0].creator_name.as_deref(), Some(fake::USER_NICKNAME));
+}
@@ -0,0 +1,209 @@
+use chrono::{NaiveDateTime, TimeZone};
+use diesel::{
+	connection::SimpleConnection,
+	Connection,
+	ExpressionMethods,
+	QueryDsl,
+	RunQueryDsl,
+	SqliteConnection,
+};
+
+use super::{
+	schema::reminders,
+	types::DueTimestamp,
+	Error,
+};
+
+
+/// The table diesel's CLI records applied migrations in, so that databases migrated by
+/// either are interchangeable.
+const CREATE_MIGRATIONS_TABLE: &str = "
+	create table if not exists __diesel_schema_migrations (
+		version varchar(50) primary key not null,
+		run_on  timestamp not null default current_timestamp
+	);
+";
+
+
+table! {
+	__diesel_schema_migrations (version) {
+		version -> Text,
+	}
+}
+
+
+table! {
+	sqlite_master (name) {
+		name -> Text,
+	}
+}
+
+
+/// A migration from the `migrations` directory, embedded in the binary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Migration {
+	/// The migration's directory name.
+	pub name: &'static str,
+	up: &'static str,
+}
+
+
+macro_rules! migration {
+	($name:literal) => {
+		Migration {
+			name: $name,
+			up: include_str!(concat!("../../migrations/", $name, "/up.sql")),
+		}
+	};
+}
+
+
+/// Every migration, oldest first.
+pub const MIGRATIONS: [Migration; 14] = [
+	migration!("2021-02-09-035332_create_reminders"),
+	migration!("2021-02-18-031654_create_trusted_chats"),
+	migration!("2021-03-06-184210_create_chat_settings"),
+	migration!("2021-03-13-152733_add_reminders_recurrence_limits"),
+	migration!("2021-03-20-141508_change_reminders_recurrence_to_text"),
+	migration!("2021-03-27-193025_add_reminders_recur_anchor"),
+	migration!("2021-04-03-172140_add_reminders_source_message"),
+	migration!("2021-04-10-143012_add_reminders_media"),
+	migration!("2021-04-17-160521_add_reminders_creator_and_mentions"),
+	migration!("2021-04-24-113047_add_reminders_recipient"),
+	migration!("2021-05-01-094512_create_chat_roles"),
+	migration!("2021-05-08-150238_create_invites"),
+	migration!("2021-05-15-104211_create_deliveries"),
+	migration!("2021-05-22-101734_add_deliveries_creator"),
+];
+
+
+impl Migration {
+	/// The version diesel records for the migration: the timestamp in its name, without
+	/// dashes, e.g.: 20210209035332.
+	pub fn version(&self) -> String {
+		self.name
+			.split('_')
+			.next()
+			.unwrap_or(self.name)
+			.replace('-', "")
+	}
+
+
+	/// Run the migration and record it, atomically.
+	fn run<Z: TimeZone + Copy>(&self, connection: &SqliteConnection, timezone: Z) -> Result<(), Error> {
+		connection.transaction(
+			|| {
+				connection.batch_execute(self.up)?;
+
+				// Due times meant the server's local time until the chats got timezones.
+				if self.name == "2021-03-06-184210_create_chat_settings" {
+					local_due_to_utc(connection, timezone)?;
+				}
+
+				diesel
+					::insert_into(__diesel_schema_migrations::table)
+					.values(
+						__diesel_schema_migrations::version.eq(self.version())
+					)
+					.execute(connection)
+					.map(
+						|_| ()
+					)
+			}
+		)
+	}
+}
+
+
+/// How the database's schema compares to the migrations embedded in the binary.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Status {
+	/// Migrations yet to be run, oldest first.
+	pub pending: Box<[Migration]>,
+	/// Versions of migrations the database has, but the binary doesn't know of, which
+	/// means the database is newer.
+	pub unknown: Box<[String]>,
+}
+
+
+/// Compare the database's schema to the embedded migrations, without changing it.
+pub fn status(connection: &SqliteConnection) -> Result<Status, Error> {
+	let tracked = sqlite_master::table
+		.filter(
+			sqlite_master::name.eq("__diesel_schema_migrations")
+		)
+		.count()
+		.get_result::<i64>(connection)?;
+
+	let applied =
+		if tracked > 0 {
+			__diesel_schema_migrations::table
+				.select(__diesel_schema_migrations::version)
+				.load::<String>(connection)?
+		} else {
+			Vec::new()
+		};
+
+	let pending = MIGRATIONS
+		.iter()
+		.filter(
+			|migration| !applied.contains(&migration.version())
+		)
+		.copied()
+		.collect();
+
+	let unknown = applied
+		.into_iter()
+		.filter(
+			|version| !MIGRATIONS
+				.iter()
+				.any(
+					|migration| &migration.version() == version
+				)
+		)
+		.collect();
+
+	Ok(
+		Status { pending, unknown }
+	)
+}
+
+
+/// Run the given migrations in order, stopping at the first that fails. Due times stored
+/// before timezone support are taken as wall-clock times in the given timezone, which was
+/// the server's.
+pub fn run<Z: TimeZone + Copy>(connection: &SqliteConnection, migrations: &[Migration], timezone: Z) -> Result<(), Error> {
+	connection.batch_execute(CREATE_MIGRATIONS_TABLE)?;
+
+	for migration in migrations {
+		log::info!("Running migration: {}", migration.name);
+		migration.run(connection, timezone)?;
+	}
+
+	Ok(())
+}
+
+
+/// Convert the reminders' due times from wall-clock times, stored as if they were UTC, to
+/// actual UTC.
+fn local_due_to_utc<Z: TimeZone + Copy>(connection: &SqliteConnection, timezone: Z) -> Result<(), Error> {
+	let reminders = reminders::table
+		.select((reminders::id, reminders::due))
+		.load::<(i32, i64)>(connection)?;
+
+	for (id, due) in reminders {
+		let DueTimestamp(utc) = DueTimestamp::from_local(
+			NaiveDateTime::from_timestamp(due, 0),
+			timezone
+		);
+
+		diesel
+			::update(reminders::table.find(id))
+			.set(
+				reminders::due.eq(utc.timestamp())
+			)
+			.execute(connection)?;
+	}
+
+	Ok(())
+}
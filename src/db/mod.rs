@@ -1,10 +1,16 @@
+pub mod migrations;
 pub mod models;
 pub mod schema;
 pub mod types;
+#[cfg(test)]
+mod tests;
+
+use std::path::Path;
 
 use diesel::{
 	BoolExpressionMethods,
 	Connection,
+	OptionalExtension,
 	SqliteConnection,
 	QueryDsl,
 	RunQueryDsl,
@@ -13,15 +19,49 @@ use diesel::{
 pub use diesel::result::Error;
 
 use self::{
+	migrations::Migration,
 	models::{
-		reminders::{NewReminder, Reminder},
-		trusted_chats::NewTrustedChat,
+		chat_roles::ChatRole,
+		chat_settings::{ChatSettings, NewChatSettings},
+		deliveries::{Delivery, NewDelivery},
+		invites::Invite,
+		reminders::{NewReminder, Reminder, ReminderChanges},
+		trusted_chats::{NewTrustedChat, TrustedChat},
 	},
 	schema::{
+		chat_roles::dsl::{
+			chat as role_chat,
+			user_id as role_user_id,
+			role as role_role,
+			chat_roles as chat_roles_db,
+		},
+		chat_settings::dsl::{
+			chat as settings_chat,
+			timezone as settings_timezone,
+			everyone_manages as settings_everyone_manages,
+			chat_settings as chat_settings_db,
+		},
+		deliveries::dsl::{
+			id as delivery_id,
+			reminder as delivery_reminder,
+			chat as delivery_chat,
+			scheduled as delivery_scheduled,
+			sent as delivery_sent,
+			message as delivery_message,
+			outcome as delivery_outcome,
+			deliveries as deliveries_db,
+		},
+		invites::dsl::{
+			code as invite_code,
+			expires as invite_expires,
+			invites as invites_db,
+		},
 		reminders::dsl::{
 			id as reminder_id,
 			chat as reminder_chat,
 			due as reminder_due,
+			recur_remaining as reminder_recur_remaining,
+			recur_anchor as reminder_recur_anchor,
 			reminders as reminders_db
 		},
 		trusted_chats::dsl::{
@@ -29,20 +69,93 @@ use self::{
 			trusted_chats as trusted_chats_db,
 		},
 	},
-	types::{ChatId, DueTimestamp}
+	types::{ChatId, DueTimestamp, MessageId, Outcome, Role, Timezone, UserId}
 };
 
 
+/// Why a database couldn't be opened.
+#[derive(Debug, thiserror::Error)]
+pub enum OpenError {
+	#[error("failed to connect: {0}")]
+	Connection(diesel::ConnectionError),
+	#[error("failed to migrate: {0}")]
+	Migration(Error),
+	#[error("the schema is newer than this binary, which doesn't know of migrations: {}", .0.join(", "))]
+	UnknownMigrations(Box<[String]>),
+	#[error("no database")]
+	Missing,
+}
+
+
 pub struct Db(SqliteConnection);
 
 
+#[derive(QueryableByName)]
+struct DataVersion {
+	#[sql_type = "diesel::sql_types::BigInt"]
+	data_version: i64,
+}
+
+
 impl Db {
-	pub fn open(path: &str) -> diesel::ConnectionResult<Self> {
-		let connection = SqliteConnection::establish(path)?;
+	/// Open the database, running any pending migrations. Databases with migrations this
+	/// binary doesn't know of are refused, as they were migrated by a newer version. Due
+	/// times stored before timezone support are in the server's timezone.
+	pub fn open(path: &str) -> Result<Self, OpenError> {
+		let db = Self::connect(path)?;
 
-		Ok(
-			Self(connection)
-		)
+		let pending = db.pending_migrations()?;
+
+		migrations
+			::run(&db.0, &pending, chrono::Local)
+			.map_err(OpenError::Migration)?;
+
+		Ok(db)
+	}
+
+
+	/// The migrations that opening the database would run, without running them, or
+	/// creating the database if it's missing.
+	pub fn check_schema(path: &str) -> Result<Box<[Migration]>, OpenError> {
+		if !Path::new(path).exists() {
+			return Err(OpenError::Missing);
+		}
+
+		Self
+			::connect(path)?
+			.pending_migrations()
+	}
+
+
+	/// An empty in-memory database, for tests.
+	#[cfg(test)]
+	pub fn open_in_memory() -> Self {
+		Self
+			::open(":memory:")
+			.expect("failed to open in-memory database")
+	}
+
+
+	fn connect(path: &str) -> Result<Self, OpenError> {
+		SqliteConnection
+			::establish(path)
+			.map(Self)
+			.map_err(OpenError::Connection)
+	}
+
+
+	fn pending_migrations(&self) -> Result<Box<[Migration]>, OpenError> {
+		let status = migrations
+			::status(&self.0)
+			.map_err(OpenError::Migration)?;
+
+		if !status.unknown.is_empty() {
+			return Err(
+				OpenError::UnknownMigrations(status.unknown)
+			);
+		}
+
+		Ok(status.pending)
 	}
 
 
@@ -54,6 +167,13 @@ impl Db {
 	}
 
 
+	pub fn trusted_chats(&self) -> Result<Box<[TrustedChat]>, Error> {
+		trusted_chats_db
+			.load::<TrustedChat>(&self.0)
+			.map(Vec::into_boxed_slice)
+	}
+
+
 	pub fn new_trusted_chat<'a>(&self, chat: &NewTrustedChat<'a>) -> Result<(), Error> {
 		diesel
 			::insert_into(trusted_chats_db)
@@ -65,6 +185,159 @@ impl Db {
 	}
 
 
+	pub fn delete_trusted_chat(&self, chat_id: ChatId) -> Result<bool, Error> {
+		diesel
+			::delete(
+				trusted_chats_db.filter(
+					trusted_chat_id.eq(chat_id)
+				)
+			)
+			.execute(&self.0)
+			.map(
+				|rows_affected| rows_affected == 1
+			)
+	}
+
+
+	pub fn new_invite(&self, invite: &Invite) -> Result<(), Error> {
+		diesel
+			::insert_into(invites_db)
+			.values(invite)
+			.execute(&self.0)
+			.map(
+				|_| ()
+			)
+	}
+
+
+	/// Consume the invite with the given code, if it hasn't expired. Expired invites are
+	/// purged along the way.
+	pub fn redeem_invite(&self, code: &str, now: DueTimestamp) -> Result<bool, Error> {
+		diesel
+			::delete(
+				invites_db.filter(
+					invite_expires.le(now)
+				)
+			)
+			.execute(&self.0)?;
+
+		diesel
+			::delete(
+				invites_db.filter(
+					invite_code.eq(code)
+				)
+			)
+			.execute(&self.0)
+			.map(
+				|rows_affected| rows_affected == 1
+			)
+	}
+
+
+	pub fn chat_settings(&self, chat_id: ChatId) -> Result<Option<ChatSettings>, Error> {
+		chat_settings_db
+			.filter(
+				settings_chat.eq(chat_id)
+			)
+			.first::<ChatSettings>(&self.0)
+			.optional()
+	}
+
+
+	pub fn set_chat_timezone(&self, chat_id: ChatId, timezone: Timezone) -> Result<(), Error> {
+		let rows_affected = diesel
+			::update(
+				chat_settings_db.filter(
+					settings_chat.eq(chat_id)
+				)
+			)
+			.set(
+				settings_timezone.eq(timezone)
+			)
+			.execute(&self.0)?;
+
+		if rows_affected == 0 {
+			diesel
+				::insert_into(chat_settings_db)
+				.values(
+					&NewChatSettings {
+						chat: chat_id,
+						timezone: Some(timezone),
+						everyone_manages: true,
+					}
+				)
+				.execute(&self.0)?;
+		}
+
+		Ok(())
+	}
+
+
+	pub fn set_chat_everyone_manages(&self, chat_id: ChatId, everyone_manages: bool) -> Result<(), Error> {
+		let rows_affected = diesel
+			::update(
+				chat_settings_db.filter(
+					settings_chat.eq(chat_id)
+				)
+			)
+			.set(
+				settings_everyone_manages.eq(everyone_manages)
+			)
+			.execute(&self.0)?;
+
+		if rows_affected == 0 {
+			diesel
+				::insert_into(chat_settings_db)
+				.values(
+					&NewChatSettings {
+						chat: chat_id,
+						timezone: None,
+						everyone_manages,
+					}
+				)
+				.execute(&self.0)?;
+		}
+
+		Ok(())
+	}
+
+
+	/// The user's role in the chat, if any was given.
+	pub fn chat_role(&self, chat_id: ChatId, user_id: UserId) -> Result<Option<Role>, Error> {
+		chat_roles_db
+			.select(role_role)
+			.filter(
+				role_chat
+					.eq(chat_id)
+					.and(
+						role_user_id.eq(user_id)
+					)
+			)
+			.first::<Role>(&self.0)
+			.optional()
+	}
+
+
+	/// Give a user a role in a chat, replacing the previous one.
+	pub fn set_chat_role(&self, role: &ChatRole) -> Result<(), Error> {
+		diesel
+			::replace_into(chat_roles_db)
+			.values(role)
+			.execute(&self.0)
+			.map(
+				|_| ()
+			)
+	}
+
+
+	pub fn reminders(&self) -> Result<Box<[Reminder]>, Error> {
+		reminders_db
+			.order(reminder_id)
+			.load::<Reminder>(&self.0)
+			.map(Vec::into_boxed_slice)
+	}
+
+
 	pub fn chat_reminders(&self, chat_id: ChatId) -> Result<Box<[Reminder]>, Error> {
 		reminders_db
 			.filter(
@@ -75,16 +348,54 @@ impl Db {
 	}
 
 
+	pub fn chat_reminder(&self, id: i32, chat_id: ChatId) -> Result<Option<Reminder>, Error> {
+		reminders_db
+			.filter(
+				reminder_id
+					.eq(id)
+					.and(
+						reminder_chat.eq(chat_id)
+					)
+			)
+			.first::<Reminder>(&self.0)
+			.optional()
+	}
+
+
 	pub fn past_due_reminders(&self, now: DueTimestamp) -> Result<Box<[Reminder]>, Error> {
 		reminders_db
 			.filter(
-				reminder_due.lt(now)
+				reminder_due.le(now)
 			)
 			.load::<Reminder>(&self.0)
 			.map(Vec::into_boxed_slice)
 	}
 
 
+	/// A number that changes whenever another connection, like the command line's, commits
+	/// changes to the database.
+	pub fn data_version(&self) -> Result<i64, Error> {
+		diesel
+			::sql_query("pragma data_version")
+			.get_result::<DataVersion>(&self.0)
+			.map(
+				|version| version.data_version
+			)
+	}
+
+
+	/// The earliest due timestamp among all reminders, if any.
+	pub fn next_due_timestamp(&self) -> Result<Option<DueTimestamp>, Error> {
+		reminders_db
+			.select(reminder_due)
+			.order(
+				reminder_due.asc()
+			)
+			.first::<DueTimestamp>(&self.0)
+			.optional()
+	}
+
+
 	pub fn new_reminder<'a>(&self, reminder: &NewReminder<'a>) -> Result<(), Error> {
 		diesel
 			::insert_into(reminders_db)
@@ -96,23 +407,96 @@ impl Db {
 	}
 
 
-	pub fn recur_reminder(&self, reminder: &Reminder) -> Result<bool, Error> {
-		let id = reminder.id;
-		let recurrence = reminder
-			.recurrying
-			.ok_or(Error::NotFound)?;
+	/// Store new reminders. Either all are stored, or none is.
+	pub fn new_reminders<'a>(&self, reminders: &[NewReminder<'a>]) -> Result<(), Error> {
+		self.0.transaction(
+			|| diesel
+				::insert_into(reminders_db)
+				.values(reminders)
+				.execute(&self.0)
+				.map(
+					|_| ()
+				)
+		)
+	}
+
+
+	/// Store the reminders along with their ids, replacing any with the same id if asked to,
+	/// or failing otherwise. Either all are stored, or none is.
+	pub fn import_reminders(&self, reminders: &[Reminder], replace: bool) -> Result<(), Error> {
+		self.0.transaction(
+			|| {
+				let result =
+					if replace {
+						diesel
+							::replace_into(reminders_db)
+							.values(reminders)
+							.execute(&self.0)
+					} else {
+						diesel
+							::insert_into(reminders_db)
+							.values(reminders)
+							.execute(&self.0)
+					};
+
+				result.map(
+					|_| ()
+				)
+			}
+		)
+	}
+
+
+	/// The given reminder ids that are already taken.
+	pub fn taken_reminder_ids(&self, ids: &[i32]) -> Result<Box<[i32]>, Error> {
+		reminders_db
+			.select(reminder_id)
+			.filter(
+				reminder_id.eq_any(ids)
+			)
+			.order(reminder_id)
+			.load::<i32>(&self.0)
+			.map(Vec::into_boxed_slice)
+	}
 
-		let new_due = recurrence.advance(reminder.due);
 
+	/// Store the following occurrence of a reminder.
+	pub fn recur_reminder(&self, next: &Reminder) -> Result<bool, Error> {
 		diesel
 			::update(
 				reminders_db.filter(
-					reminder_id.eq(id)
+					reminder_id.eq(next.id)
 				)
 			)
-			.set(
-				reminder_due.eq(new_due)
+			.set((
+				reminder_due.eq(next.due),
+				reminder_recur_remaining.eq(next.recur_remaining),
+				reminder_recur_anchor.eq(next.recur_anchor),
+			))
+			.execute(&self.0)
+			.map(
+				|rows_affected| rows_affected == 1
 			)
+	}
+
+
+	pub fn update_chat_reminder<'a>(
+		&self,
+		id: i32,
+		chat_id: ChatId,
+		changes: &ReminderChanges<'a>,
+	) -> Result<bool, Error> {
+		diesel
+			::update(
+				reminders_db.filter(
+					reminder_id
+						.eq(id)
+						.and(
+							reminder_chat.eq(chat_id)
+						)
+				)
+			)
+			.set(changes)
 			.execute(&self.0)
 			.map(
 				|rows_affected| rows_affected == 1
@@ -151,4 +535,113 @@ impl Db {
 				|rows_affected| rows_affected == 1
 			)
 	}
+
+
+	/// Delete every reminder in the chat, returning how many there were.
+	pub fn delete_chat_reminders(&self, chat_id: ChatId) -> Result<usize, Error> {
+		diesel
+			::delete(
+				reminders_db.filter(
+					reminder_chat.eq(chat_id)
+				)
+			)
+			.execute(&self.0)
+	}
+
+
+	pub fn new_delivery<'a>(&self, delivery: &NewDelivery<'a>) -> Result<(), Error> {
+		diesel
+			::insert_into(deliveries_db)
+			.values(delivery)
+			.execute(&self.0)
+			.map(
+				|_| ()
+			)
+	}
+
+
+	/// Update when a failed delivery was last attempted, if the reminder already failed at
+	/// the same scheduled time, so retries don't add a delivery each.
+	pub fn retry_failed_delivery<'a>(&self, delivery: &NewDelivery<'a>) -> Result<bool, Error> {
+		diesel
+			::update(
+				deliveries_db.filter(
+					delivery_reminder
+						.eq(delivery.reminder)
+						.and(
+							delivery_chat.eq(delivery.chat)
+						)
+						.and(
+							delivery_scheduled.eq(delivery.scheduled)
+						)
+						.and(
+							delivery_outcome.eq(Outcome::Failed)
+						)
+				)
+			)
+			.set(
+				delivery_sent.eq(delivery.sent)
+			)
+			.execute(&self.0)
+			.map(
+				|rows_affected| rows_affected > 0
+			)
+	}
+
+
+	/// The chat's latest deliveries, oldest first.
+	pub fn chat_deliveries(&self, chat_id: ChatId, count: i64) -> Result<Box<[Delivery]>, Error> {
+		let mut deliveries = deliveries_db
+			.filter(
+				delivery_chat.eq(chat_id)
+			)
+			.order(
+				delivery_id.desc()
+			)
+			.limit(count)
+			.load::<Delivery>(&self.0)?;
+
+		deliveries.reverse();
+
+		Ok(
+			deliveries.into_boxed_slice()
+		)
+	}
+
+
+	/// The delivery of the reminder in the given message.
+	pub fn message_delivery(&self, chat_id: ChatId, message_id: MessageId) -> Result<Option<Delivery>, Error> {
+		deliveries_db
+			.filter(
+				delivery_chat
+					.eq(chat_id)
+					.and(
+						delivery_message.eq(message_id)
+					)
+			)
+			.first::<Delivery>(&self.0)
+			.optional()
+	}
+
+
+	/// Mark the delivery in the given message as snoozed.
+	pub fn snooze_delivery(&self, chat_id: ChatId, message_id: MessageId) -> Result<bool, Error> {
+		diesel
+			::update(
+				deliveries_db.filter(
+					delivery_chat
+						.eq(chat_id)
+						.and(
+							delivery_message.eq(message_id)
+						)
+				)
+			)
+			.set(
+				delivery_outcome.eq(Outcome::Snoozed)
+			)
+			.execute(&self.0)
+			.map(
+				|rows_affected| rows_affected > 0
+			)
+	}
 }
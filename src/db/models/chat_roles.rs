@@ -0,0 +1,13 @@
+use super::schema::chat_roles;
+pub use super::types::*;
+
+
+/// A user's role in a chat. Users without one are members.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Queryable, Insertable)]
+#[table_name = "chat_roles"]
+pub struct ChatRole {
+	pub chat: ChatId,
+	pub user_id: UserId,
+	pub role: Role,
+}
@@ -0,0 +1,22 @@
+use super::schema::chat_settings;
+pub use super::types::*;
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Queryable)]
+pub struct ChatSettings {
+	pub chat: ChatId,
+	pub timezone: Option<Timezone>,
+	/// Whether members may manage every reminder, instead of only their own.
+	pub everyone_manages: bool,
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Insertable)]
+#[table_name = "chat_settings"]
+pub struct NewChatSettings {
+	pub chat: ChatId,
+	pub timezone: Option<Timezone>,
+	pub everyone_manages: bool,
+}
@@ -0,0 +1,76 @@
+use std::fmt::{self, Display};
+
+use chrono_tz::Tz;
+
+use super::schema::deliveries;
+pub use super::types::*;
+
+
+/// An attempt to deliver a reminder, kept after the reminder is done.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Queryable)]
+pub struct Delivery {
+	pub id: i32,
+	pub reminder: i32,
+	/// The chat it was delivered to, or the chat it was added in, if it failed.
+	pub chat: ChatId,
+	pub scheduled: DueTimestamp,
+	/// When the delivery was attempted.
+	pub sent: DueTimestamp,
+	/// The delivered message, unless it failed.
+	pub message: Option<MessageId>,
+	pub outcome: Outcome,
+	pub text: String,
+	/// Who added the reminder.
+	pub creator_id: Option<UserId>,
+	pub creator_name: Option<String>,
+	pub creator_username: Option<String>,
+}
+
+
+impl Delivery {
+	pub fn localized(&self, timezone: Tz) -> Localized<'_, Self> {
+		Localized { value: self, timezone }
+	}
+}
+
+
+impl<'a> Display for Localized<'a, Delivery> {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let delivery = self.value;
+
+		let scheduled = delivery.scheduled
+			.localized(self.timezone)
+			.to_string();
+
+		write!(f, "({}) {}", delivery.reminder, scheduled)?;
+
+		// Late deliveries also show when they were attempted.
+		let sent = delivery.sent
+			.localized(self.timezone)
+			.to_string();
+
+		if sent != scheduled {
+			write!(f, " → {}", sent)?;
+		}
+
+		write!(f, " {}: {}", delivery.outcome, delivery.text)
+	}
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Insertable)]
+#[table_name = "deliveries"]
+pub struct NewDelivery<'a> {
+	pub reminder: i32,
+	pub chat: ChatId,
+	pub scheduled: DueTimestamp,
+	pub sent: DueTimestamp,
+	pub message: Option<MessageId>,
+	pub outcome: Outcome,
+	pub text: &'a str,
+	pub creator_id: Option<UserId>,
+	pub creator_name: Option<&'a str>,
+	pub creator_username: Option<&'a str>,
+}
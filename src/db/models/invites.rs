@@ -0,0 +1,15 @@
+use super::schema::invites;
+pub use super::types::*;
+
+
+/// A one-time code that authenticates a chat.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Queryable, Insertable)]
+#[table_name = "invites"]
+pub struct Invite {
+	pub code: String,
+	/// The chat where the invite was created.
+	pub chat: ChatId,
+	pub created_by: UserId,
+	pub expires: DueTimestamp,
+}
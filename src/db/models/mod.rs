@@ -1,3 +1,7 @@
+pub mod chat_roles;
+pub mod chat_settings;
+pub mod deliveries;
+pub mod invites;
 pub mod reminders;
 pub mod trusted_chats;
 
@@ -1,17 +1,43 @@
 use std::fmt::{self, Display};
 
+use chrono::Datelike;
+
+use chrono_tz::Tz;
+
 use super::schema::reminders;
 pub use super::types::*;
+use crate::bot;
 
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-#[derive(Queryable)]
+#[derive(Queryable, Insertable)]
+#[table_name = "reminders"]
 pub struct Reminder {
 	pub id: i32,
 	pub due: DueTimestamp,
 	pub recurrying: Option<Recurrence>,
+	/// The chat the reminder was added in.
 	pub chat: ChatId,
 	pub message: String,
+	/// The last possible occurrence of a recurring reminder.
+	pub recur_until: Option<DueTimestamp>,
+	/// How many occurrences of a recurring reminder are left, including the next.
+	pub recur_remaining: Option<i32>,
+	/// The day of the month of the first occurrence of a recurring reminder.
+	pub recur_anchor: Option<i32>,
+	/// The chat message the reminder is sent in reply to.
+	pub source_message: Option<MessageId>,
+	/// The kind of the attached file, if any.
+	pub media_kind: Option<MediaKind>,
+	pub media_file_id: Option<String>,
+	/// The user who added the reminder, unknown for reminders added before it was stored.
+	pub creator_id: Option<UserId>,
+	pub creator_name: Option<String>,
+	pub creator_username: Option<String>,
+	/// The users tagged when the reminder is delivered.
+	pub mentions: Option<Mentions>,
+	/// The user the reminder is delivered to privately, instead of in its chat.
+	pub recipient: Option<UserId>,
 }
 
 
@@ -19,30 +45,160 @@ impl Reminder {
 	pub fn is_recurrying(&self) -> bool {
 		self.recurrying.is_some()
 	}
+
+
+	/// The attached file, if any.
+	pub fn media(&self) -> Option<bot::Media> {
+		let kind = self.media_kind?;
+		let file_id = self.media_file_id.as_deref()?;
+
+		Some(
+			bot::Media {
+				kind: kind.0,
+				file_id: file_id.into(),
+			}
+		)
+	}
+
+
+	/// The chat the reminder is delivered in.
+	pub fn delivery_chat(&self) -> bot::ChatId {
+		self.recipient.map_or(
+			self.chat.0,
+			|recipient| recipient.0.into()
+		)
+	}
+
+
+	/// A mention of the user the reminder is delivered to privately, if any.
+	pub fn recipient_mention(&self) -> Option<bot::Mention> {
+		let recipient = self.recipient?;
+
+		// Recipients are the creators of their reminders.
+		let mention = match (self.creator_username.as_deref(), self.creator_name.as_deref()) {
+			(Some(username), _) => bot::Mention::Username(username.into()),
+
+			(None, name) => bot::Mention::User {
+				id: recipient.0,
+				name: name
+					.unwrap_or("?")
+					.into(),
+			},
+		};
+
+		Some(mention)
+	}
+
+
+	/// The users to be tagged.
+	pub fn mentions(&self) -> &[bot::Mention] {
+		self.mentions
+			.as_ref()
+			.map_or(&[], |mentions| &mentions.0[..])
+	}
+
+
+	/// The following occurrence of a recurring reminder, if it has any left.
+	pub fn next_due(&self, timezone: Tz) -> Option<DueTimestamp> {
+		let recurrence = self.recurrying?;
+
+		if self.recur_remaining.map_or(false, |remaining| remaining <= 1) {
+			return None;
+		}
+
+		let due = recurrence.advance(self.due, self.anchor_day(timezone), timezone);
+
+		match self.recur_until {
+			Some(until) if due > until => None,
+			_ => Some(due),
+		}
+	}
+
+
+	/// The reminder at its following occurrence, if it has any left.
+	pub fn next_occurrence(&self, timezone: Tz) -> Option<Self> {
+		let due = self.next_due(timezone)?;
+
+		Some(
+			Reminder {
+				due,
+				recur_remaining: self.recur_remaining.map(
+					|remaining| remaining - 1
+				),
+				// Keep the anchor day for reminders that didn't have one stored.
+				recur_anchor: Some(
+					self.anchor_day(timezone) as i32
+				),
+				..self.clone()
+			}
+		)
+	}
+
+
+	/// The day of the month monthly and yearly recurrences return to. Defaults to the due day
+	/// for reminders created before it was stored.
+	pub fn anchor_day(&self, timezone: Tz) -> u32 {
+		self.recur_anchor.map_or_else(
+			|| self.due
+				.local(timezone)
+				.day(),
+			|anchor| anchor as u32
+		)
+	}
+
+
+	pub fn localized(&self, timezone: Tz) -> Localized<'_, Self> {
+		Localized { value: self, timezone }
+	}
+
+
+	/// The reminder's fields, to store it again under a new id.
+	pub fn as_new(&self) -> NewReminder<'_> {
+		NewReminder {
+			due: self.due,
+			recurrying: self.recurrying,
+			chat: self.chat,
+			message: &self.message,
+			recur_until: self.recur_until,
+			recur_remaining: self.recur_remaining,
+			recur_anchor: self.recur_anchor,
+			source_message: self.source_message,
+			media_kind: self.media_kind,
+			media_file_id: self.media_file_id.as_deref(),
+			creator_id: self.creator_id,
+			creator_name: self.creator_name.as_deref(),
+			creator_username: self.creator_username.as_deref(),
+			mentions: self.mentions.as_ref(),
+			recipient: self.recipient,
+		}
+	}
 }
 
 
-impl Display for Reminder {
+impl<'a> Display for Localized<'a, Reminder> {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-    if let Some(recurrence) = self.recurrying {
-			write!(
+		let reminder = self.value;
+
+		write!(
+			f,
+			"({}) {}",
+			reminder.id,
+			reminder.due.localized(self.timezone)
+		)?;
+
+		if let Some(recurrence) = reminder.recurrying {
+			fmt_recurrence(
 				f,
-				"({}) {} {}: {}",
-				self.id,
-				self.due,
 				recurrence,
-				self.message
-			)
-		}
-		else {
-			write!(
-				f,
-				"({}) {}: {}",
-				self.id,
-				self.due,
-				self.message
-			)
+				reminder.recur_until,
+				reminder.recur_remaining,
+				self.timezone
+			)?;
 		}
+
+		fmt_message(f, &reminder.message, reminder.media_kind, reminder.recipient.is_some())?;
+
+		fmt_people(f, reminder.mentions.as_ref(), reminder.creator_name.as_deref())
 	}
 }
 
@@ -55,27 +211,132 @@ pub struct NewReminder<'a> {
 	pub recurrying: Option<Recurrence>,
 	pub chat: ChatId,
 	pub message: &'a str,
+	pub recur_until: Option<DueTimestamp>,
+	pub recur_remaining: Option<i32>,
+	pub recur_anchor: Option<i32>,
+	pub source_message: Option<MessageId>,
+	pub media_kind: Option<MediaKind>,
+	pub media_file_id: Option<&'a str>,
+	pub creator_id: Option<UserId>,
+	pub creator_name: Option<&'a str>,
+	pub creator_username: Option<&'a str>,
+	pub mentions: Option<&'a Mentions>,
+	pub recipient: Option<UserId>,
 }
 
 
-impl<'a> Display for NewReminder<'a> {
+impl<'a> NewReminder<'a> {
+	pub fn localized(&self, timezone: Tz) -> Localized<'_, Self> {
+		Localized { value: self, timezone }
+	}
+}
+
+
+impl<'a, 'b> Display for Localized<'a, NewReminder<'b>> {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-    if let Some(recurrence) = self.recurrying {
-			write!(
+		let reminder = self.value;
+
+		write!(
+			f,
+			"{}",
+			reminder.due.localized(self.timezone)
+		)?;
+
+		if let Some(recurrence) = reminder.recurrying {
+			fmt_recurrence(
 				f,
-				"{} {}: {}",
-				self.due,
 				recurrence,
-				self.message
-			)
+				reminder.recur_until,
+				reminder.recur_remaining,
+				self.timezone
+			)?;
 		}
-		else {
-			write!(
-				f,
-				"{}: {}",
-				self.due,
-				self.message
-			)
+
+		fmt_message(f, reminder.message, reminder.media_kind, reminder.recipient.is_some())?;
+
+		// Whoever is adding the reminder knows who they are.
+		fmt_people(f, reminder.mentions, None)
+	}
+}
+
+
+/// Changes to a reminder. Fields that are `None` are left unchanged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(AsChangeset)]
+#[table_name = "reminders"]
+pub struct ReminderChanges<'a> {
+	pub due: Option<DueTimestamp>,
+	pub recurrying: Option<Option<Recurrence>>,
+	pub message: Option<&'a str>,
+	pub recur_until: Option<Option<DueTimestamp>>,
+	pub recur_remaining: Option<Option<i32>>,
+	pub recur_anchor: Option<Option<i32>>,
+}
+
+
+fn fmt_recurrence(
+	f: &mut fmt::Formatter,
+	recurrence: Recurrence,
+	until: Option<DueTimestamp>,
+	remaining: Option<i32>,
+	timezone: Tz,
+) -> fmt::Result {
+	write!(f, " {}", recurrence)?;
+
+	if let Some(remaining) = remaining {
+		write!(f, " x{}", remaining)?;
+	}
+
+	if let Some(until) = until {
+		write!(
+			f,
+			" until {}",
+			until
+				.local(timezone)
+				.format("%Y-%m-%d")
+		)?;
+	}
+
+	Ok(())
+}
+
+
+fn fmt_message(
+	f: &mut fmt::Formatter,
+	message: &str,
+	media_kind: Option<MediaKind>,
+	private: bool,
+) -> fmt::Result {
+	write!(f, ": ")?;
+
+	if private {
+		write!(f, "🔒 ")?;
+	}
+
+	if media_kind.is_some() {
+		write!(f, "📎 ")?;
+	}
+
+	write!(f, "{}", message)
+}
+
+
+/// The tagged users, and the creator. Tagged usernames are written without the `@`, so
+/// listing reminders doesn't ping them.
+fn fmt_people(f: &mut fmt::Formatter, mentions: Option<&Mentions>, creator: Option<&str>) -> fmt::Result {
+	let mentions = mentions.map_or(&[][..], |mentions| &mentions.0[..]);
+
+	for (i, mention) in mentions.iter().enumerate() {
+		let separator = if i == 0 { " → " } else { ", " };
+
+		match mention {
+			bot::Mention::Username(name) | bot::Mention::User { name, .. } => write!(f, "{}{}", separator, name)?,
 		}
 	}
+
+	if let Some(creator) = creator {
+		write!(f, " — {}", creator)?;
+	}
+
+	Ok(())
 }
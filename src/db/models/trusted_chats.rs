@@ -15,7 +15,7 @@ pub struct TrustedChat {
 
 impl Display for TrustedChat {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-		write!(f, "{:?}", self.id.0)?;
+		write!(f, "{}", self.id.0)?;
 
 		if let Some(username) = &self.username {
 			write!(f, " {}", username)?;
@@ -42,7 +42,7 @@ pub struct NewTrustedChat<'a> {
 
 impl<'a> Display for NewTrustedChat<'a> {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-    write!(f, "{:?}", self.id.0)?;
+    write!(f, "{}", self.id.0)?;
 
 		if let Some(username) = self.username {
 			write!(f, " {}", username)?;
@@ -1,10 +1,62 @@
+table! {
+    chat_roles (chat, user_id) {
+        chat -> BigInt,
+        user_id -> BigInt,
+        role -> Text,
+    }
+}
+
+table! {
+    chat_settings (chat) {
+        chat -> BigInt,
+        timezone -> Nullable<Text>,
+        everyone_manages -> Bool,
+    }
+}
+
+table! {
+    deliveries (id) {
+        id -> Integer,
+        reminder -> Integer,
+        chat -> BigInt,
+        scheduled -> BigInt,
+        sent -> BigInt,
+        message -> Nullable<BigInt>,
+        outcome -> Text,
+        text -> Text,
+        creator_id -> Nullable<BigInt>,
+        creator_name -> Nullable<Text>,
+        creator_username -> Nullable<Text>,
+    }
+}
+
+table! {
+    invites (code) {
+        code -> Text,
+        chat -> BigInt,
+        created_by -> BigInt,
+        expires -> BigInt,
+    }
+}
+
 table! {
     reminders (id) {
         id -> Integer,
         due -> BigInt,
-        recurrying -> Nullable<Integer>,
+        recurrying -> Nullable<Text>,
         chat -> BigInt,
         message -> Text,
+        recur_until -> Nullable<BigInt>,
+        recur_remaining -> Nullable<Integer>,
+        recur_anchor -> Nullable<Integer>,
+        source_message -> Nullable<BigInt>,
+        media_kind -> Nullable<Text>,
+        media_file_id -> Nullable<Text>,
+        creator_id -> Nullable<BigInt>,
+        creator_name -> Nullable<Text>,
+        creator_username -> Nullable<Text>,
+        mentions -> Nullable<Text>,
+        recipient -> Nullable<BigInt>,
     }
 }
 
@@ -17,6 +69,10 @@ table! {
 }
 
 allow_tables_to_appear_in_same_query!(
+    chat_roles,
+    chat_settings,
+    deliveries,
+    invites,
     reminders,
     trusted_chats,
 );
@@ -0,0 +1,168 @@
+use chrono::{NaiveDate, TimeZone, Utc};
+use chrono_tz::Tz;
+use diesel::{connection::SimpleConnection, Connection, SqliteConnection};
+
+use super::*;
+
+
+fn temp_path(name: &str) -> String {
+	std::env::temp_dir()
+		.join(
+			format!("reminder-bot-test-{}-{}.sqlite", name, std::process::id())
+		)
+		.to_str()
+		.expect("temporary path should be valid unicode")
+		.to_string()
+}
+
+
+#[test]
+fn test_migrations() {
+	let path = &temp_path("migrations");
+
+	let _ = std::fs::remove_file(path);
+
+	// Checking doesn't create the database.
+	assert!(matches!(Db::check_schema(path), Err(OpenError::Missing)));
+	assert!(!std::path::Path::new(path).exists());
+
+	SqliteConnection::establish(path).expect("failed to create database");
+
+	let pending = Db::check_schema(path).expect("failed to check schema");
+	assert_eq!(pending.as_ref(), &migrations::MIGRATIONS[..]);
+
+	Db::open(path).expect("failed to migrate");
+
+	let pending = Db::check_schema(path).expect("failed to check schema");
+	assert!(pending.is_empty());
+
+	// A newer binary ran a migration this one doesn't know of.
+	SqliteConnection
+		::establish(path)
+		.expect("failed to connect")
+		.batch_execute("insert into __diesel_schema_migrations (version) values ('29990101000000');")
+		.expect("failed to record migration");
+
+	let result = Db::open(path);
+
+	std::fs::remove_file(path).expect("failed to remove database");
+
+	match result {
+		Err(OpenError::UnknownMigrations(versions)) => assert_eq!(
+			versions.as_ref(),
+			&["29990101000000".to_string()]
+		),
+		_ => panic!("newer schema should be refused"),
+	}
+}
+
+
+#[test]
+fn test_embedded_migrations() {
+	let mut names: Vec<String> = std::fs::read_dir(
+		concat!(env!("CARGO_MANIFEST_DIR"), "/migrations")
+	)
+		.expect("failed to list migrations")
+		.map(
+			|entry| entry
+				.expect("failed to read migrations")
+				.file_name()
+				.into_string()
+				.expect("migration names should be valid unicode")
+		)
+		.filter(
+			|name| !name.starts_with('.')
+		)
+		.collect();
+
+	names.sort();
+
+	let embedded: Vec<&str> = migrations::MIGRATIONS
+		.iter()
+		.map(
+			|migration| migration.name
+		)
+		.collect();
+
+	assert_eq!(embedded, names);
+}
+
+
+#[test]
+fn test_local_due_to_utc() {
+	let path = &temp_path("local-due");
+
+	let _ = std::fs::remove_file(path);
+
+	// A database from before timezone support, with a reminder due at 09:00 server time,
+	// stored as if it were UTC.
+	let local = NaiveDate::from_ymd(2021, 3, 1).and_hms(9, 0, 0);
+
+	let connection = SqliteConnection::establish(path).expect("failed to connect");
+
+	migrations
+		::run(&connection, &migrations::MIGRATIONS[.. 2], Tz::UTC)
+		.expect("failed to migrate");
+
+	connection
+		.batch_execute(
+			&format!(
+				"insert into reminders (due, chat, message) values ({}, 1, 'stretch');",
+				local.timestamp()
+			)
+		)
+		.expect("failed to insert reminder");
+
+	// Like opening it on a server in that timezone.
+	migrations
+		::run(&connection, &migrations::MIGRATIONS[2 ..], Tz::America__Sao_Paulo)
+		.expect("failed to migrate");
+
+	drop(connection);
+
+	let db = Db::open(path).expect("failed to open database");
+	let reminders = db.reminders();
+
+	std::fs::remove_file(path).expect("failed to remove database");
+
+	let reminders = reminders.expect("failed to get reminders");
+	assert_eq!(reminders.len(), 1);
+	assert_eq!(
+		reminders[0].due,
+		DueTimestamp(
+			Utc.from_utc_datetime(
+				&NaiveDate::from_ymd(2021, 3, 1).and_hms(12, 0, 0)
+			)
+		)
+	);
+}
+
+
+#[test]
+fn test_data_version() {
+	let path = &temp_path("data-version");
+
+	let _ = std::fs::remove_file(path);
+
+	let db = Db::open(path).expect("failed to open database");
+	let version = db.data_version().expect("failed to get data version");
+
+	// Its own changes don't count.
+	db.0
+		.batch_execute("insert into reminders (due, chat, message) values (0, 1, 'stretch');")
+		.expect("failed to insert reminder");
+
+	assert_eq!(db.data_version().expect("failed to get data version"), version);
+
+	SqliteConnection
+		::establish(path)
+		.expect("failed to connect")
+		.batch_execute("insert into reminders (due, chat, message) values (0, 1, 'stretch');")
+		.expect("failed to insert reminder");
+
+	let changed = db.data_version().expect("failed to get data version");
+
+	std::fs::remove_file(path).expect("failed to remove database");
+
+	assert_ne!(changed, version);
+}
@@ -0,0 +1,1106 @@
+#[cfg(test)]
+mod tests;
+
+use std::{
+	fmt::{self, Display},
+	io,
+	iter::FromIterator,
+	str::FromStr,
+};
+
+use thiserror::Error;
+
+use chrono::{Datelike, DateTime, Duration, LocalResult, NaiveDate, NaiveDateTime, TimeZone, Utc, Weekday};
+
+use chrono_tz::Tz;
+
+use diesel::{backend::Backend, deserialize, serialize::{self, Output}, sql_types::{BigInt, Text}, types::{FromSql, ToSql}};
+
+use crate::bot;
+
+
+/// A value to be displayed in a given timezone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Localized<'a, T> {
+	pub value: &'a T,
+	pub timezone: Tz,
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(AsExpression, FromSqlRow)]
+#[sql_type = "BigInt"]
+pub struct DueTimestamp(pub DateTime<Utc>);
+
+
+impl DueTimestamp {
+	/// Convert a wall-clock time in the given timezone. Ambiguous times resolve to the
+	/// earliest instant, and times skipped by a DST transition are shifted forward.
+	pub fn from_local<Z: TimeZone>(datetime: NaiveDateTime, timezone: Z) -> Self {
+		let mut local = datetime;
+
+		// DST gaps are at most a couple of hours long.
+		for _ in 0 .. 8 {
+			match timezone.from_local_datetime(&local) {
+				LocalResult::Single(datetime) | LocalResult::Ambiguous(datetime, _) => {
+					return Self(
+						datetime.with_timezone(&Utc)
+					);
+				}
+
+				LocalResult::None => local += Duration::minutes(30),
+			}
+		}
+
+		Self(
+			Utc.from_utc_datetime(&datetime)
+		)
+	}
+
+
+	/// The wall-clock time in the given timezone.
+	pub fn local(&self, timezone: Tz) -> NaiveDateTime {
+		self.0
+			.with_timezone(&timezone)
+			.naive_local()
+	}
+
+
+	pub fn localized(&self, timezone: Tz) -> Localized<'_, Self> {
+		Localized { value: self, timezone }
+	}
+}
+
+
+impl Display for DueTimestamp {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(
+			f,
+			"{}",
+			self.0.format("%Y-%m-%d %H:%M UTC")
+		)
+	}
+}
+
+
+impl<'a> Display for Localized<'a, DueTimestamp> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(
+			f,
+			"{}",
+			self.value
+				.local(self.timezone)
+				.format("%Y-%m-%d %H:%M")
+		)
+	}
+}
+
+
+impl<DB: Backend> ToSql<BigInt, DB> for DueTimestamp
+where
+	i64: ToSql<BigInt, DB>,
+{
+	fn to_sql<W>(&self, out: &mut Output<W, DB>) -> serialize::Result
+	where
+		W: io::Write,
+	{
+		let epoch_seconds = self.0
+			.timestamp();
+
+		epoch_seconds.to_sql(out)
+	}
+}
+
+
+impl<DB: Backend> FromSql<BigInt, DB> for DueTimestamp
+where
+	i64: FromSql<BigInt, DB>,
+{
+	fn from_sql(bytes: Option<&DB::RawValue>) -> deserialize::Result<Self> {
+		let epoch_seconds = i64::from_sql(bytes)?;
+
+		Ok(
+			DueTimestamp(
+				Utc.timestamp(epoch_seconds, 0)
+			)
+		)
+	}
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum RecurrenceUnit {
+	Minutes,
+	Hours,
+	Days,
+	Weeks,
+	Months,
+	Years,
+}
+
+
+impl RecurrenceUnit {
+	pub fn from_char(c: char) -> Option<Self> {
+		match c {
+			'm' => Some(RecurrenceUnit::Minutes),
+			'h' => Some(RecurrenceUnit::Hours),
+			'd' => Some(RecurrenceUnit::Days),
+			'w' => Some(RecurrenceUnit::Weeks),
+			'M' => Some(RecurrenceUnit::Months),
+			'y' => Some(RecurrenceUnit::Years),
+			_ => None,
+		}
+	}
+
+
+	pub fn to_char(self) -> char {
+		match self {
+			RecurrenceUnit::Minutes => 'm',
+			RecurrenceUnit::Hours   => 'h',
+			RecurrenceUnit::Days    => 'd',
+			RecurrenceUnit::Weeks   => 'w',
+			RecurrenceUnit::Months  => 'M',
+			RecurrenceUnit::Years   => 'y',
+		}
+	}
+}
+
+
+/// A set of weekdays.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct WeekdaySet(u8);
+
+
+impl WeekdaySet {
+	pub fn insert(&mut self, weekday: Weekday) {
+		self.0 |= 1 << weekday.num_days_from_monday();
+	}
+
+
+	pub fn contains(&self, weekday: Weekday) -> bool {
+		self.0 & (1 << weekday.num_days_from_monday()) != 0
+	}
+
+
+	pub fn is_empty(&self) -> bool {
+		self.0 == 0
+	}
+
+
+	/// The weekdays in the set, starting from monday.
+	pub fn iter(self) -> impl Iterator<Item = Weekday> {
+		WEEKDAYS
+			.iter()
+			.copied()
+			.filter(
+				move |&weekday| self.contains(weekday)
+			)
+	}
+}
+
+
+impl FromIterator<Weekday> for WeekdaySet {
+	fn from_iter<I>(iter: I) -> Self
+	where
+		I: IntoIterator<Item = Weekday>
+	{
+		let mut set = Self::default();
+
+		for weekday in iter {
+			set.insert(weekday);
+		}
+
+		set
+	}
+}
+
+
+/// Which occurrence of a weekday in a month.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Nth {
+	/// The given occurrence, from 1 to 5.
+	Nth(u8),
+	Last,
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(AsExpression, FromSqlRow)]
+#[sql_type = "Text"]
+pub enum Recurrence {
+	/// A fixed period, like every 2 days.
+	Period {
+		ammount: u8,
+		unit: RecurrenceUnit,
+	},
+	/// Every week, on the given weekdays.
+	Weekdays(WeekdaySet),
+	/// Every month, on the nth weekday, like the second tuesday.
+	MonthlyWeekday {
+		nth: Nth,
+		weekday: Weekday,
+	},
+}
+
+
+const WEEKDAYS: [Weekday; 7] = [
+	Weekday::Mon,
+	Weekday::Tue,
+	Weekday::Wed,
+	Weekday::Thu,
+	Weekday::Fri,
+	Weekday::Sat,
+	Weekday::Sun,
+];
+
+
+fn weekday_name(weekday: Weekday) -> &'static str {
+	match weekday {
+		Weekday::Mon => "mon",
+		Weekday::Tue => "tue",
+		Weekday::Wed => "wed",
+		Weekday::Thu => "thu",
+		Weekday::Fri => "fri",
+		Weekday::Sat => "sat",
+		Weekday::Sun => "sun",
+	}
+}
+
+
+/// This is also the storage format.
+impl Display for Recurrence {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Recurrence::Period { ammount, unit } => write!(f, "+{}{}", ammount, unit.to_char()),
+
+			Recurrence::Weekdays(weekdays) => {
+				write!(f, "+")?;
+
+				for (ix, weekday) in weekdays.iter().enumerate() {
+					if ix > 0 {
+						write!(f, ",")?;
+					}
+
+					write!(f, "{}", weekday_name(weekday))?;
+				}
+
+				Ok(())
+			}
+
+			Recurrence::MonthlyWeekday { nth: Nth::Nth(nth), weekday } => {
+				write!(f, "+{}{}", nth, weekday_name(*weekday))
+			}
+
+			Recurrence::MonthlyWeekday { nth: Nth::Last, weekday } => {
+				write!(f, "+last{}", weekday_name(*weekday))
+			}
+		}
+	}
+}
+
+
+#[derive(Debug, Error)]
+#[error("invalid recurrence: {0}")]
+pub struct RecurrenceParseError(String);
+
+
+/// Parses the format produced by `Display`.
+impl FromStr for Recurrence {
+	type Err = RecurrenceParseError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let error = || RecurrenceParseError(s.into());
+
+		let rule = s
+			.strip_prefix('+')
+			.ok_or_else(error)?;
+
+		let digits = rule
+			.find(
+				|c: char| !c.is_ascii_digit()
+			)
+			.unwrap_or(rule.len());
+
+		let (number, name) = rule.split_at(digits);
+
+		let parse_weekday = |name: &str| name
+			.parse::<Weekday>()
+			.map_err(
+				|_| error()
+			);
+
+		if let Some(weekday) = name.strip_prefix("last") {
+			return Ok(
+				Recurrence::MonthlyWeekday { nth: Nth::Last, weekday: parse_weekday(weekday)? }
+			);
+		}
+
+		if number.is_empty() {
+			let weekdays = name
+				.split(',')
+				.map(parse_weekday)
+				.collect::<Result<WeekdaySet, _>>()?;
+
+			return Ok(
+				Recurrence::Weekdays(weekdays)
+			);
+		}
+
+		let number = number
+			.parse::<u8>()
+			.map_err(
+				|_| error()
+			)?;
+
+		let mut chars = name.chars();
+
+		match (chars.next().and_then(RecurrenceUnit::from_char), chars.next()) {
+			(Some(unit), None) if number > 0 => Ok(
+				Recurrence::Period { ammount: number, unit }
+			),
+
+			_ if (1 ..= 5).contains(&number) => Ok(
+				Recurrence::MonthlyWeekday { nth: Nth::Nth(number), weekday: parse_weekday(name)? }
+			),
+
+			_ => Err(error()),
+		}
+	}
+}
+
+
+impl Recurrence {
+	/// Advance the timestamp to the next occurrence. Minutes and hours are elapsed time,
+	/// while longer periods advance in wall-clock time of the given timezone, so that a
+	/// daily reminder keeps its time of day across DST transitions.
+	///
+	/// The `anchor_day` is the day of the month of the first occurrence. Monthly and yearly
+	/// periods are clamped to the last day of shorter months, and return to the anchor day
+	/// when possible, e.g.: Jan 31, Feb 28, Mar 31.
+	pub fn advance(&self, timestamp: DueTimestamp, anchor_day: u32, timezone: Tz) -> DueTimestamp {
+		match self {
+			Recurrence::Period { ammount, unit: RecurrenceUnit::Minutes } => {
+				DueTimestamp(timestamp.0 + Duration::minutes(*ammount as i64))
+			}
+
+			Recurrence::Period { ammount, unit: RecurrenceUnit::Hours } => {
+				DueTimestamp(timestamp.0 + Duration::hours(*ammount as i64))
+			}
+
+			_ => DueTimestamp::from_local(
+				self.advance_local(
+					timestamp.local(timezone),
+					anchor_day
+				),
+				timezone
+			),
+		}
+	}
+
+
+	/// Skip whole fixed periods of a timestamp at once, stopping before `until`, and return
+	/// the occurrence it lands on along with how many it skipped. Other recurrences aren't
+	/// skipped, and have to be advanced one occurrence at a time.
+	pub fn skip_before(
+		&self,
+		timestamp: DueTimestamp,
+		until: DueTimestamp,
+		anchor_day: u32,
+		timezone: Tz,
+	) -> (DueTimestamp, u32) {
+		let (ammount, unit) = match *self {
+			Recurrence::Period { ammount, unit } if ammount > 0 => (ammount as i64, unit),
+			_ => return (timestamp, 0),
+		};
+
+		let minutes = match unit {
+			RecurrenceUnit::Minutes => Some(1),
+			RecurrenceUnit::Hours   => Some(60),
+			RecurrenceUnit::Days    => Some(60 * 24),
+			RecurrenceUnit::Weeks   => Some(60 * 24 * 7),
+			RecurrenceUnit::Months | RecurrenceUnit::Years => None,
+		};
+
+		let months = |timestamp: DueTimestamp| {
+			let date = timestamp.local(timezone);
+
+			date.year() as i64 * 12 + date.month0() as i64
+		};
+
+		let periods = match minutes {
+			Some(minutes) => (until.0 - timestamp.0).num_minutes() / (ammount * minutes),
+			None if unit == RecurrenceUnit::Years => (months(until) - months(timestamp)) / (ammount * 12),
+			None => (months(until) - months(timestamp)) / ammount,
+		};
+
+		// Leave a period of margin, for DST transitions and shorter months.
+		let skipped = (periods - 1).max(0);
+		let ammount = ammount * skipped;
+
+		let timestamp = match unit {
+			RecurrenceUnit::Minutes => DueTimestamp(timestamp.0 + Duration::minutes(ammount)),
+			RecurrenceUnit::Hours   => DueTimestamp(timestamp.0 + Duration::hours(ammount)),
+			_ => {
+				let local = timestamp.local(timezone);
+
+				let local = match unit {
+					RecurrenceUnit::Days  => local + Duration::days(ammount),
+					RecurrenceUnit::Weeks => local + Duration::weeks(ammount),
+					RecurrenceUnit::Years => NaiveDateTime::new(
+						add_months(local.date(), (ammount * 12) as u32, anchor_day),
+						local.time()
+					),
+					_ => NaiveDateTime::new(
+						add_months(local.date(), ammount as u32, anchor_day),
+						local.time()
+					),
+				};
+
+				DueTimestamp::from_local(local, timezone)
+			},
+		};
+
+		(timestamp, skipped as u32)
+	}
+
+
+	/// Advance a wall-clock time to the next occurrence. See `advance` for the `anchor_day`.
+	pub fn advance_local(&self, timestamp: NaiveDateTime, anchor_day: u32) -> NaiveDateTime {
+		let date = timestamp.date();
+
+		let date = match *self {
+			Recurrence::Period { ammount, unit } => {
+				return advance_period(ammount, unit, anchor_day, timestamp)
+			}
+
+			Recurrence::Weekdays(weekdays) if weekdays.is_empty() => date,
+
+			Recurrence::Weekdays(weekdays) => date
+				.iter_days()
+				.skip(1)
+				.find(
+					|date| weekdays.contains(date.weekday())
+				)
+				.expect("a week should contain some weekday of a non empty set"),
+
+			Recurrence::MonthlyWeekday { nth, weekday } => {
+				let (year, month) = (date.year(), date.month());
+
+				// Some months have no fifth weekday, so look a few months ahead.
+				(0 .. 12)
+					.filter_map(
+						|offset| {
+							let month = month - 1 + offset;
+
+							nth_weekday(
+								year + (month / 12) as i32,
+								month % 12 + 1,
+								nth,
+								weekday
+							)
+						}
+					)
+					.find(
+						|&candidate| candidate > date
+					)
+					.unwrap_or(date)
+			}
+		};
+
+		NaiveDateTime::new(date, timestamp.time())
+	}
+}
+
+
+fn advance_period(
+	ammount: u8,
+	unit: RecurrenceUnit,
+	anchor_day: u32,
+	timestamp: NaiveDateTime,
+) -> NaiveDateTime {
+	let months = match unit {
+		RecurrenceUnit::Minutes => return timestamp + Duration::minutes(ammount as i64),
+		RecurrenceUnit::Hours   => return timestamp + Duration::hours(ammount as i64),
+		RecurrenceUnit::Days    => return timestamp + Duration::days(ammount as i64),
+		RecurrenceUnit::Weeks   => return timestamp + Duration::weeks(ammount as i64),
+		RecurrenceUnit::Months  => ammount as u32,
+		RecurrenceUnit::Years   => ammount as u32 * 12,
+	};
+
+	NaiveDateTime::new(
+		add_months(timestamp.date(), months, anchor_day),
+		timestamp.time()
+	)
+}
+
+
+/// Add months to a date, landing on the anchor day, or on the last day of the month if it
+/// is shorter.
+fn add_months(date: NaiveDate, months: u32, anchor_day: u32) -> NaiveDate {
+	let months = date.month0() + months;
+	let year = date.year() + (months / 12) as i32;
+	let month = months % 12 + 1;
+
+	last_day_of_month(year, month)
+		.and_then(
+			|last_day| last_day.with_day(
+				anchor_day.max(1).min(last_day.day())
+			)
+		)
+		.unwrap_or(chrono::naive::MAX_DATE)
+}
+
+
+fn last_day_of_month(year: i32, month: u32) -> Option<NaiveDate> {
+	let (next_year, next_month) =
+		if month == 12 {
+			(year + 1, 1)
+		} else {
+			(year, month + 1)
+		};
+
+	NaiveDate
+		::from_ymd_opt(next_year, next_month, 1)?
+		.pred_opt()
+}
+
+
+/// The date of the nth weekday in the given month, if there is such a day.
+fn nth_weekday(year: i32, month: u32, nth: Nth, weekday: Weekday) -> Option<NaiveDate> {
+	match nth {
+		Nth::Nth(nth) => NaiveDate::from_weekday_of_month_opt(year, month, weekday, nth),
+
+		Nth::Last => {
+			let last_day = last_day_of_month(year, month)?;
+
+			let days_back = (
+				last_day.weekday().num_days_from_monday() + 7 - weekday.num_days_from_monday()
+			) % 7;
+
+			last_day.checked_sub_signed(
+				Duration::days(days_back as i64)
+			)
+		}
+	}
+}
+
+
+impl<DB: Backend> ToSql<Text, DB> for Recurrence
+where
+	str: ToSql<Text, DB>,
+{
+	fn to_sql<W>(&self, out: &mut Output<W, DB>) -> serialize::Result
+	where
+		W: io::Write,
+	{
+		self
+			.to_string()
+			.as_str()
+			.to_sql(out)
+	}
+}
+
+
+impl<DB: Backend> FromSql<Text, DB> for Recurrence
+where
+	String: FromSql<Text, DB>,
+{
+	fn from_sql(bytes: Option<&DB::RawValue>) -> deserialize::Result<Self> {
+		let serialized = String::from_sql(bytes)?;
+
+		let recurrence = serialized.parse::<Recurrence>()?;
+
+		Ok(recurrence)
+	}
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(AsExpression, FromSqlRow)]
+#[sql_type = "BigInt"]
+pub struct ChatId(pub bot::ChatId);
+
+
+impl From<i64> for ChatId {
+	fn from(value: i64) -> Self {
+		Self(value.into())
+	}
+}
+
+
+impl<'a> Into<i64> for &'a ChatId {
+	fn into(self) -> i64 {
+		self.0.into()
+	}
+}
+
+
+impl<DB: Backend> ToSql<BigInt, DB> for ChatId
+where
+	i64: ToSql<BigInt, DB>,
+{
+	fn to_sql<W>(&self, out: &mut Output<W, DB>) -> serialize::Result
+	where
+		W: io::Write,
+	{
+		let id: i64 = self.into();
+
+		id.to_sql(out)
+	}
+}
+
+
+impl<DB: Backend> FromSql<BigInt, DB> for ChatId
+where
+	i64: FromSql<BigInt, DB>,
+{
+	fn from_sql(bytes: Option<&DB::RawValue>) -> deserialize::Result<Self> {
+		let id = i64::from_sql(bytes)?;
+
+		Ok(
+			ChatId::from(id)
+		)
+	}
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(AsExpression, FromSqlRow)]
+#[sql_type = "BigInt"]
+pub struct MessageId(pub bot::MessageId);
+
+
+impl<DB: Backend> ToSql<BigInt, DB> for MessageId
+where
+	i64: ToSql<BigInt, DB>,
+{
+	fn to_sql<W>(&self, out: &mut Output<W, DB>) -> serialize::Result
+	where
+		W: io::Write,
+	{
+		let id: i64 = self.0.into();
+
+		id.to_sql(out)
+	}
+}
+
+
+impl<DB: Backend> FromSql<BigInt, DB> for MessageId
+where
+	i64: FromSql<BigInt, DB>,
+{
+	fn from_sql(bytes: Option<&DB::RawValue>) -> deserialize::Result<Self> {
+		let id = i64::from_sql(bytes)?;
+
+		Ok(
+			MessageId(id.into())
+		)
+	}
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(AsExpression, FromSqlRow)]
+#[sql_type = "Text"]
+pub struct MediaKind(pub bot::MediaKind);
+
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Error)]
+#[error("invalid media kind: {0}")]
+pub struct MediaKindParseError(Box<str>);
+
+
+impl MediaKind {
+	pub fn as_str(&self) -> &'static str {
+		match self.0 {
+			bot::MediaKind::Photo => "photo",
+			bot::MediaKind::Document => "document",
+		}
+	}
+}
+
+
+impl FromStr for MediaKind {
+	type Err = MediaKindParseError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"photo" => Ok(Self(bot::MediaKind::Photo)),
+			"document" => Ok(Self(bot::MediaKind::Document)),
+			_ => Err(
+				MediaKindParseError(s.into())
+			),
+		}
+	}
+}
+
+
+impl<DB: Backend> ToSql<Text, DB> for MediaKind
+where
+	str: ToSql<Text, DB>,
+{
+	fn to_sql<W>(&self, out: &mut Output<W, DB>) -> serialize::Result
+	where
+		W: io::Write,
+	{
+		self
+			.as_str()
+			.to_sql(out)
+	}
+}
+
+
+impl<DB: Backend> FromSql<Text, DB> for MediaKind
+where
+	String: FromSql<Text, DB>,
+{
+	fn from_sql(bytes: Option<&DB::RawValue>) -> deserialize::Result<Self> {
+		let serialized = String::from_sql(bytes)?;
+
+		let kind = serialized.parse::<MediaKind>()?;
+
+		Ok(kind)
+	}
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(AsExpression, FromSqlRow)]
+#[sql_type = "BigInt"]
+pub struct UserId(pub bot::UserId);
+
+
+impl<DB: Backend> ToSql<BigInt, DB> for UserId
+where
+	i64: ToSql<BigInt, DB>,
+{
+	fn to_sql<W>(&self, out: &mut Output<W, DB>) -> serialize::Result
+	where
+		W: io::Write,
+	{
+		let id: i64 = self.0.into();
+
+		id.to_sql(out)
+	}
+}
+
+
+impl<DB: Backend> FromSql<BigInt, DB> for UserId
+where
+	i64: FromSql<BigInt, DB>,
+{
+	fn from_sql(bytes: Option<&DB::RawValue>) -> deserialize::Result<Self> {
+		let id = i64::from_sql(bytes)?;
+
+		Ok(
+			UserId(id.into())
+		)
+	}
+}
+
+
+/// Users to be tagged, one per line: `@username`, or the id and name of users without one.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(AsExpression, FromSqlRow)]
+#[sql_type = "Text"]
+pub struct Mentions(pub Box<[bot::Mention]>);
+
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Error)]
+#[error("invalid mention: {0}")]
+pub struct MentionsParseError(Box<str>);
+
+
+impl Display for Mentions {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		for (i, mention) in self.0.iter().enumerate() {
+			if i > 0 {
+				writeln!(f)?;
+			}
+
+			match mention {
+				bot::Mention::Username(username) => write!(f, "@{}", username)?,
+
+				bot::Mention::User { id, name } => {
+					let id: i64 = (*id).into();
+
+					// Keep each mention in its line.
+					write!(f, "{} {}", id, name.replace('\n', " "))?
+				}
+			}
+		}
+
+		Ok(())
+	}
+}
+
+
+impl FromStr for Mentions {
+	type Err = MentionsParseError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		s
+			.lines()
+			.map(
+				|line| {
+					if let Some(username) = line.strip_prefix('@') {
+						return Ok(
+							bot::Mention::Username(username.into())
+						);
+					}
+
+					let mut parts = line.splitn(2, ' ');
+
+					let id = parts
+						.next()
+						.and_then(
+							|id| id.parse::<i64>().ok()
+						);
+
+					match (id, parts.next()) {
+						(Some(id), Some(name)) => Ok(
+							bot::Mention::User {
+								id: id.into(),
+								name: name.into(),
+							}
+						),
+						_ => Err(
+							MentionsParseError(line.into())
+						),
+					}
+				}
+			)
+			.collect::<Result<_, _>>()
+			.map(Mentions)
+	}
+}
+
+
+impl<DB: Backend> ToSql<Text, DB> for Mentions
+where
+	str: ToSql<Text, DB>,
+{
+	fn to_sql<W>(&self, out: &mut Output<W, DB>) -> serialize::Result
+	where
+		W: io::Write,
+	{
+		self
+			.to_string()
+			.as_str()
+			.to_sql(out)
+	}
+}
+
+
+impl<DB: Backend> FromSql<Text, DB> for Mentions
+where
+	String: FromSql<Text, DB>,
+{
+	fn from_sql(bytes: Option<&DB::RawValue>) -> deserialize::Result<Self> {
+		let serialized = String::from_sql(bytes)?;
+
+		let mentions = serialized.parse::<Mentions>()?;
+
+		Ok(mentions)
+	}
+}
+
+
+/// What a user may do in a chat. Ordered by privilege.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(AsExpression, FromSqlRow)]
+#[sql_type = "Text"]
+pub enum Role {
+	/// May manage their own reminders.
+	Member,
+	/// May manage every reminder, and the chat's roles.
+	Admin,
+	/// Like an admin, but can't be demoted. Whoever created the chat.
+	Owner,
+}
+
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Error)]
+#[error("invalid role: {0}")]
+pub struct RoleParseError(Box<str>);
+
+
+impl Role {
+	fn as_str(&self) -> &'static str {
+		match self {
+			Role::Member => "member",
+			Role::Admin => "admin",
+			Role::Owner => "owner",
+		}
+	}
+}
+
+
+impl FromStr for Role {
+	type Err = RoleParseError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"member" => Ok(Role::Member),
+			"admin" => Ok(Role::Admin),
+			"owner" => Ok(Role::Owner),
+			_ => Err(
+				RoleParseError(s.into())
+			),
+		}
+	}
+}
+
+
+impl<DB: Backend> ToSql<Text, DB> for Role
+where
+	str: ToSql<Text, DB>,
+{
+	fn to_sql<W>(&self, out: &mut Output<W, DB>) -> serialize::Result
+	where
+		W: io::Write,
+	{
+		self
+			.as_str()
+			.to_sql(out)
+	}
+}
+
+
+impl<DB: Backend> FromSql<Text, DB> for Role
+where
+	String: FromSql<Text, DB>,
+{
+	fn from_sql(bytes: Option<&DB::RawValue>) -> deserialize::Result<Self> {
+		let serialized = String::from_sql(bytes)?;
+
+		let role = serialized.parse::<Role>()?;
+
+		Ok(role)
+	}
+}
+
+
+/// How an attempt to deliver a reminder went.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(AsExpression, FromSqlRow)]
+#[sql_type = "Text"]
+pub enum Outcome {
+	Sent,
+	/// Missed while the bot was offline, and only noted as such.
+	Skipped,
+	Failed,
+	/// Sent, and then snoozed from its buttons.
+	Snoozed,
+}
+
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Error)]
+#[error("invalid outcome: {0}")]
+pub struct OutcomeParseError(Box<str>);
+
+
+impl Outcome {
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			Outcome::Sent => "sent",
+			Outcome::Skipped => "skipped",
+			Outcome::Failed => "failed",
+			Outcome::Snoozed => "snoozed",
+		}
+	}
+}
+
+
+impl Display for Outcome {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}", self.as_str())
+	}
+}
+
+
+impl FromStr for Outcome {
+	type Err = OutcomeParseError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"sent" => Ok(Outcome::Sent),
+			"skipped" => Ok(Outcome::Skipped),
+			"failed" => Ok(Outcome::Failed),
+			"snoozed" => Ok(Outcome::Snoozed),
+			_ => Err(
+				OutcomeParseError(s.into())
+			),
+		}
+	}
+}
+
+
+impl<DB: Backend> ToSql<Text, DB> for Outcome
+where
+	str: ToSql<Text, DB>,
+{
+	fn to_sql<W>(&self, out: &mut Output<W, DB>) -> serialize::Result
+	where
+		W: io::Write,
+	{
+		self
+			.as_str()
+			.to_sql(out)
+	}
+}
+
+
+impl<DB: Backend> FromSql<Text, DB> for Outcome
+where
+	String: FromSql<Text, DB>,
+{
+	fn from_sql(bytes: Option<&DB::RawValue>) -> deserialize::Result<Self> {
+		let serialized = String::from_sql(bytes)?;
+
+		let outcome = serialized.parse::<Outcome>()?;
+
+		Ok(outcome)
+	}
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(AsExpression, FromSqlRow)]
+#[sql_type = "Text"]
+pub struct Timezone(pub Tz);
+
+
+impl<DB: Backend> ToSql<Text, DB> for Timezone
+where
+	str: ToSql<Text, DB>,
+{
+	fn to_sql<W>(&self, out: &mut Output<W, DB>) -> serialize::Result
+	where
+		W: io::Write,
+	{
+		self.0
+			.name()
+			.to_sql(out)
+	}
+}
+
+
+impl<DB: Backend> FromSql<Text, DB> for Timezone
+where
+	String: FromSql<Text, DB>,
+{
+	fn from_sql(bytes: Option<&DB::RawValue>) -> deserialize::Result<Self> {
+		let name = String::from_sql(bytes)?;
+
+		let timezone = name.parse::<Tz>()?;
+
+		Ok(
+			Timezone(timezone)
+		)
+	}
+}
@@ -0,0 +1,385 @@
+use super::*;
+
+
+fn datetime(str: &str) -> NaiveDateTime {
+	NaiveDateTime
+		::parse_from_str(str, "%Y-%m-%d %H:%M")
+		.expect("invalid datetime")
+}
+
+
+fn weekdays(weekdays: &[Weekday]) -> Recurrence {
+	Recurrence::Weekdays(
+		weekdays
+			.iter()
+			.copied()
+			.collect()
+	)
+}
+
+
+#[test]
+fn test_recurrence_roundtrip() {
+	let recurrences = [
+		Recurrence::Period { ammount: 2, unit: RecurrenceUnit::Days },
+		Recurrence::Period { ammount: 3, unit: RecurrenceUnit::Months },
+		Recurrence::Period { ammount: 10, unit: RecurrenceUnit::Minutes },
+		weekdays(&[Weekday::Mon, Weekday::Wed, Weekday::Fri]),
+		weekdays(&[Weekday::Sun]),
+		Recurrence::MonthlyWeekday { nth: Nth::Nth(2), weekday: Weekday::Tue },
+		Recurrence::MonthlyWeekday { nth: Nth::Last, weekday: Weekday::Fri },
+	];
+
+	for recurrence in recurrences.iter() {
+		assert_eq!(
+			recurrence
+				.to_string()
+				.parse::<Recurrence>()
+				.expect("parse failed"),
+			*recurrence
+		);
+	}
+
+	assert_eq!(
+		weekdays(&[Weekday::Fri, Weekday::Mon]).to_string(),
+		"+mon,fri"
+	);
+
+	assert!("2d".parse::<Recurrence>().is_err());
+	assert!("+".parse::<Recurrence>().is_err());
+	assert!("+2x".parse::<Recurrence>().is_err());
+	assert!("+6mon".parse::<Recurrence>().is_err());
+	assert!("+mon,".parse::<Recurrence>().is_err());
+}
+
+
+#[test]
+fn test_mentions_roundtrip() {
+	let mentions = Mentions(
+		vec![
+			bot::Mention::Username("ana".into()),
+			bot::Mention::User { id: 42.into(), name: "João Silva".into() },
+		]
+			.into_boxed_slice()
+	);
+
+	assert_eq!(mentions.to_string(), "@ana\n42 João Silva");
+
+	assert_eq!(
+		mentions
+			.to_string()
+			.parse::<Mentions>()
+			.expect("parse failed"),
+		mentions
+	);
+
+	assert!("ana".parse::<Mentions>().is_err());
+	assert!("x João".parse::<Mentions>().is_err());
+}
+
+
+#[test]
+fn test_advance_weekdays() {
+	let recurrence = weekdays(&[Weekday::Mon, Weekday::Wed, Weekday::Fri]);
+
+	let advance = |str| recurrence.advance_local(datetime(str), 1);
+
+	// 2021-03-01 is a monday.
+	assert_eq!(advance("2021-03-01 09:00"), datetime("2021-03-03 09:00"));
+	assert_eq!(advance("2021-03-02 09:00"), datetime("2021-03-03 09:00"));
+	assert_eq!(advance("2021-03-05 09:00"), datetime("2021-03-08 09:00"));
+	assert_eq!(advance("2021-03-06 09:00"), datetime("2021-03-08 09:00"));
+
+	let sundays = weekdays(&[Weekday::Sun]);
+
+	assert_eq!(
+		sundays.advance_local(datetime("2021-03-07 18:30"), 7),
+		datetime("2021-03-14 18:30")
+	);
+}
+
+
+#[test]
+fn test_advance_monthly_weekday() {
+	let advance = |nth, weekday, str| Recurrence::MonthlyWeekday { nth, weekday }
+		.advance_local(datetime(str), 1);
+
+	// First monday: 2021-03-01, 2021-04-05.
+	assert_eq!(advance(Nth::Nth(1), Weekday::Mon, "2021-03-01 10:00"), datetime("2021-04-05 10:00"));
+	// Before this month's occurrence.
+	assert_eq!(advance(Nth::Nth(2), Weekday::Tue, "2021-03-01 10:00"), datetime("2021-03-09 10:00"));
+	// Across the year.
+	assert_eq!(advance(Nth::Nth(2), Weekday::Tue, "2021-12-14 10:00"), datetime("2022-01-11 10:00"));
+	// Skips months without a fifth monday: march 2021 has one, april has none, may has one.
+	assert_eq!(advance(Nth::Nth(5), Weekday::Mon, "2021-03-29 10:00"), datetime("2021-05-31 10:00"));
+	// Last friday.
+	assert_eq!(advance(Nth::Last, Weekday::Fri, "2021-03-26 10:00"), datetime("2021-04-30 10:00"));
+	assert_eq!(advance(Nth::Last, Weekday::Fri, "2021-12-31 10:00"), datetime("2022-01-28 10:00"));
+	assert_eq!(advance(Nth::Last, Weekday::Sun, "2021-02-01 10:00"), datetime("2021-02-28 10:00"));
+}
+
+
+#[test]
+fn test_advance_keeps_local_time() {
+	let timezone = chrono_tz::America::New_York;
+
+	let due = DueTimestamp::from_local(datetime("2021-03-12 09:00"), timezone);
+
+	// DST starts on sunday, 2021-03-14.
+	let next = weekdays(&[Weekday::Mon]).advance(due, 12, timezone);
+
+	assert_eq!(next.local(timezone), datetime("2021-03-15 09:00"));
+	assert_eq!(next.0 - due.0, Duration::hours(71));
+}
+
+
+#[test]
+fn test_advance_months_clamps_to_anchor() {
+	let monthly = Recurrence::Period { ammount: 1, unit: RecurrenceUnit::Months };
+
+	let mut due = datetime("2021-01-31 08:00");
+
+	let expected = [
+		"2021-02-28 08:00",
+		"2021-03-31 08:00",
+		"2021-04-30 08:00",
+		"2021-05-31 08:00",
+	];
+
+	for expected in expected.iter() {
+		due = monthly.advance_local(due, 31);
+		assert_eq!(due, datetime(expected));
+	}
+
+	// December targets.
+	assert_eq!(
+		monthly.advance_local(datetime("2021-11-15 08:00"), 15),
+		datetime("2021-12-15 08:00")
+	);
+
+	assert_eq!(
+		Recurrence::Period { ammount: 13, unit: RecurrenceUnit::Months }
+			.advance_local(datetime("2021-11-30 08:00"), 30),
+		datetime("2022-12-30 08:00")
+	);
+}
+
+
+#[test]
+fn test_advance_years_clamps_to_anchor() {
+	let yearly = Recurrence::Period { ammount: 1, unit: RecurrenceUnit::Years };
+
+	let mut due = datetime("2020-02-29 08:00");
+
+	let expected = [
+		"2021-02-28 08:00",
+		"2022-02-28 08:00",
+		"2023-02-28 08:00",
+		"2024-02-29 08:00",
+	];
+
+	for expected in expected.iter() {
+		due = yearly.advance_local(due, 29);
+		assert_eq!(due, datetime(expected));
+	}
+}
+
+
+mod properties {
+	use proptest::prelude::*;
+
+	use super::*;
+
+
+	fn last_day(year: i32, month: u32) -> u32 {
+		super::super::last_day_of_month(year, month)
+			.expect("invalid month")
+			.day()
+	}
+
+
+	prop_compose! {
+		fn local_datetime()(
+			days in 0 .. 365 * 200i64,
+			minutes in 0 .. 24 * 60i64,
+		) -> NaiveDateTime {
+			NaiveDate::from_ymd(1950, 1, 1).and_hms(0, 0, 0)
+				+ Duration::days(days)
+				+ Duration::minutes(minutes)
+		}
+	}
+
+
+	fn unit() -> impl Strategy<Value = RecurrenceUnit> {
+		prop_oneof![
+			Just(RecurrenceUnit::Minutes),
+			Just(RecurrenceUnit::Hours),
+			Just(RecurrenceUnit::Days),
+			Just(RecurrenceUnit::Weeks),
+			Just(RecurrenceUnit::Months),
+			Just(RecurrenceUnit::Years),
+		]
+	}
+
+
+	fn weekday() -> impl Strategy<Value = Weekday> {
+		(0 .. 7usize).prop_map(
+			|ix| WEEKDAYS[ix]
+		)
+	}
+
+
+	fn recurrence() -> impl Strategy<Value = Recurrence> {
+		prop_oneof![
+			(1 .. 100u8, unit()).prop_map(
+				|(ammount, unit)| Recurrence::Period { ammount, unit }
+			),
+			(1 .. 128u8).prop_map(
+				|bits| Recurrence::Weekdays(WeekdaySet(bits))
+			),
+			(1 ..= 5u8, weekday()).prop_map(
+				|(nth, weekday)| Recurrence::MonthlyWeekday { nth: Nth::Nth(nth), weekday }
+			),
+			weekday().prop_map(
+				|weekday| Recurrence::MonthlyWeekday { nth: Nth::Last, weekday }
+			),
+		]
+	}
+
+
+	fn timezone() -> impl Strategy<Value = Tz> {
+		prop_oneof![
+			Just(Tz::UTC),
+			Just(chrono_tz::America::Sao_Paulo),
+			Just(chrono_tz::America::New_York),
+			Just(chrono_tz::Europe::Lisbon),
+			Just(chrono_tz::Australia::Lord_Howe),
+			Just(chrono_tz::Asia::Kathmandu),
+		]
+	}
+
+
+	proptest! {
+		#[test]
+		fn advance_local_moves_forward(
+			recurrence in recurrence(),
+			due in local_datetime(),
+			anchor_day in 1 ..= 31u32,
+		) {
+			prop_assert!(recurrence.advance_local(due, anchor_day) > due);
+		}
+
+
+		#[test]
+		fn advance_moves_forward(
+			recurrence in recurrence(),
+			due in local_datetime(),
+			timezone in timezone(),
+		) {
+			let due = DueTimestamp::from_local(due, timezone);
+			let anchor_day = due.local(timezone).day();
+
+			prop_assert!(recurrence.advance(due, anchor_day, timezone) > due);
+		}
+
+
+		#[test]
+		fn advance_months_lands_on_anchor(
+			due in local_datetime(),
+			ammount in 1 .. 100u8,
+			anchor_day in 1 ..= 31u32,
+			yearly in any::<bool>(),
+		) {
+			let (unit, months) =
+				if yearly {
+					(RecurrenceUnit::Years, ammount as i32 * 12)
+				} else {
+					(RecurrenceUnit::Months, ammount as i32)
+				};
+
+			let next = Recurrence::Period { ammount, unit }.advance_local(due, anchor_day);
+
+			let month_index = |date: NaiveDateTime| date.year() * 12 + date.month0() as i32;
+
+			prop_assert_eq!(month_index(next), month_index(due) + months);
+			prop_assert_eq!(next.day(), anchor_day.min(last_day(next.year(), next.month())));
+			prop_assert_eq!(next.time(), due.time());
+		}
+
+
+		#[test]
+		fn advance_months_composes(
+			day in 1 ..= 31u32,
+			month in 1 ..= 12u32,
+			year in 1950 .. 2150i32,
+			steps in 1 .. 30u8,
+		) {
+			let day = day.min(last_day(year, month));
+			let due = NaiveDate::from_ymd(year, month, day).and_hms(8, 0, 0);
+
+			let monthly = Recurrence::Period { ammount: 1, unit: RecurrenceUnit::Months };
+
+			let stepped = (0 .. steps).fold(
+				due,
+				|due, _| monthly.advance_local(due, day)
+			);
+
+			let jumped = Recurrence::Period { ammount: steps, unit: RecurrenceUnit::Months }
+				.advance_local(due, day);
+
+			prop_assert_eq!(stepped, jumped);
+		}
+
+
+		#[test]
+		fn advance_weekdays_finds_next_in_set(
+			bits in 1 .. 128u8,
+			due in local_datetime(),
+		) {
+			let weekdays = WeekdaySet(bits);
+			let next = Recurrence::Weekdays(weekdays).advance_local(due, 1);
+
+			prop_assert!(weekdays.contains(next.weekday()));
+			prop_assert_eq!(next.time(), due.time());
+
+			// No day of the set is skipped.
+			prop_assert!(
+				due.date()
+					.iter_days()
+					.skip(1)
+					.take_while(
+						|&date| date < next.date()
+					)
+					.all(
+						|date| !weekdays.contains(date.weekday())
+					)
+			);
+		}
+
+
+		#[test]
+		fn advance_monthly_weekday_finds_nth(
+			nth in prop_oneof![(1 ..= 5u8).prop_map(Nth::Nth), Just(Nth::Last)],
+			weekday in weekday(),
+			due in local_datetime(),
+		) {
+			let next = Recurrence::MonthlyWeekday { nth, weekday }.advance_local(due, 1);
+
+			prop_assert_eq!(next.weekday(), weekday);
+			prop_assert_eq!(next.time(), due.time());
+
+			let date = next.date();
+
+			match nth {
+				Nth::Nth(nth) => prop_assert_eq!((date.day() - 1) / 7 + 1, nth as u32),
+				Nth::Last => prop_assert!(date.day() + 7 > last_day(date.year(), date.month())),
+			}
+		}
+
+
+		#[test]
+		fn recurrence_roundtrips_through_text(recurrence in recurrence()) {
+			prop_assert_eq!(recurrence.to_string().parse::<Recurrence>().ok(), Some(recurrence));
+		}
+	}
+}
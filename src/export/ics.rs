@@ -0,0 +1,757 @@
+//! iCalendar files, as of RFC 5545. Only what reminders can represent is supported: a
+//! start, a summary, and simple recurrence rules. Attachments and mentions are left out.
+
+use std::fmt::Write;
+
+use thiserror::Error;
+
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc, Weekday};
+
+use chrono_tz::Tz;
+
+use crate::db::{
+	models::reminders::{NewReminder, Reminder},
+	types::{ChatId, DueTimestamp, Nth, Recurrence, RecurrenceUnit},
+};
+
+
+const PRODUCT_ID: &str = "-//reminder-bot//reminder-bot//EN";
+
+/// Date and time without a timezone, e.g.: 20210302T100000.
+const LOCAL_FORMAT: &str = "%Y%m%dT%H%M%S";
+const UTC_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+const DATE_FORMAT: &str = "%Y%m%d";
+
+/// Content lines are folded to at most this many bytes.
+const LINE_LIMIT: usize = 75;
+
+/// The most past occurrences of an imported event stepped through to reach the next one.
+const MAX_PAST_OCCURRENCES: u32 = 10_000;
+
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Error)]
+pub enum ImportError {
+	#[error("not an iCalendar file")]
+	NotCalendar,
+}
+
+
+/// Why an event can't be a reminder.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Error)]
+pub enum EventError {
+	#[error("missing start")]
+	MissingStart,
+	#[error("invalid start: {0}")]
+	InvalidStart(Box<str>),
+	#[error("unsupported recurrence: {0}")]
+	UnsupportedRecurrence(Box<str>),
+	/// Exceptions to recurrences, and extra occurrences, which reminders can't have.
+	#[error("unsupported property: {0}")]
+	UnsupportedProperty(Box<str>),
+	#[error("already over")]
+	Past,
+	#[error("too many past occurrences")]
+	TooManyOccurrences,
+}
+
+
+/// An event that can't be a reminder.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Error)]
+#[error("#{number} {summary}: {error}")]
+pub struct UnsupportedEvent {
+	/// The position of the event in the file, counting from 1.
+	pub number: usize,
+	pub summary: String,
+	pub error: EventError,
+}
+
+
+/// The events of an iCalendar file.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Calendar {
+	pub events: Box<[Event]>,
+	/// Events left out, which reminders can't represent.
+	pub unsupported: Box<[UnsupportedEvent]>,
+}
+
+
+/// A reminder read from an event.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Event {
+	pub due: DueTimestamp,
+	pub recurrence: Option<Recurrence>,
+	pub message: String,
+	pub recur_until: Option<DueTimestamp>,
+	pub recur_remaining: Option<i32>,
+	pub recur_anchor: Option<i32>,
+}
+
+
+impl Event {
+	/// The reminder for the event, in the given chat, by no one in particular.
+	pub fn new_reminder(&self, chat: ChatId) -> NewReminder<'_> {
+		NewReminder {
+			due: self.due,
+			recurrying: self.recurrence,
+			chat,
+			message: &self.message,
+			recur_until: self.recur_until,
+			recur_remaining: self.recur_remaining,
+			recur_anchor: self.recur_anchor,
+			source_message: None,
+			media_kind: None,
+			media_file_id: None,
+			creator_id: None,
+			creator_name: None,
+			creator_username: None,
+			mentions: None,
+			recipient: None,
+		}
+	}
+}
+
+
+impl Event {
+	/// The event at its first occurrence after `now`, if it has any left. Fixed periods are
+	/// skipped at once, other recurrences one occurrence at a time, up to
+	/// `MAX_PAST_OCCURRENCES`.
+	fn upcoming(mut self, timezone: Tz, now: DueTimestamp) -> Result<Self, EventError> {
+		let anchor = |event: &Self| event.recur_anchor.map_or_else(
+			|| event.due
+				.local(timezone)
+				.day(),
+			|anchor| anchor as u32
+		);
+
+		if let Some(recurrence) = self.recurrence {
+			let (due, skipped) = recurrence.skip_before(self.due, now, anchor(&self), timezone);
+
+			if self.recur_remaining.map_or(false, |remaining| remaining as i64 <= skipped as i64) {
+				return Err(EventError::Past);
+			}
+
+			self.due = due;
+
+			self.recur_remaining = self.recur_remaining.map(
+				|remaining| remaining - skipped as i32
+			);
+
+			if self.recur_until.map_or(false, |until| self.due > until) {
+				return Err(EventError::Past);
+			}
+		}
+
+		for _ in 0 .. MAX_PAST_OCCURRENCES {
+			if self.due > now {
+				return Ok(self);
+			}
+
+			let recurrence = self.recurrence.ok_or(EventError::Past)?;
+
+			if self.recur_remaining.map_or(false, |remaining| remaining <= 1) {
+				return Err(EventError::Past);
+			}
+
+			self.due = recurrence.advance(self.due, anchor(&self), timezone);
+
+			self.recur_remaining = self.recur_remaining.map(
+				|remaining| remaining - 1
+			);
+
+			if self.recur_until.map_or(false, |until| self.due > until) {
+				return Err(EventError::Past);
+			}
+		}
+
+		Err(EventError::TooManyOccurrences)
+	}
+}
+
+
+/// A content line: `NAME;PARAM=value:value`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Property {
+	/// Upper case, like parameter names.
+	name: String,
+	params: Vec<(String, String)>,
+	value: String,
+}
+
+
+impl Property {
+	fn parse(line: &str) -> Option<Self> {
+		// Parameter values may be quoted, and contain colons.
+		let mut quoted = false;
+
+		let colon = line
+			.char_indices()
+			.find(
+				|&(_, c)| {
+					if c == '"' {
+						quoted = !quoted;
+					}
+					c == ':' && !quoted
+				}
+			)
+			.map(
+				|(ix, _)| ix
+			)?;
+
+		let mut parts = line[.. colon].split(';');
+
+		let name = parts
+			.next()?
+			.trim()
+			.to_uppercase();
+
+		let params = parts
+			.filter_map(
+				|param| param.split_once('=')
+			)
+			.map(
+				|(name, value)| (name.to_uppercase(), value.trim_matches('"').into())
+			)
+			.collect();
+
+		Some(
+			Self {
+				name,
+				params,
+				value: line[colon + 1 ..].into(),
+			}
+		)
+	}
+
+
+	fn param(&self, name: &str) -> Option<&str> {
+		self.params
+			.iter()
+			.find(
+				|(param, _)| param == name
+			)
+			.map(
+				|(_, value)| value.as_str()
+			)
+	}
+}
+
+
+/// The reminders as an iCalendar file, with times in the timezone of their chats. `now`
+/// stamps the events.
+///
+/// Timezones are referred to by their IANA names, without definitions, which calendar
+/// applications understand.
+pub fn export<F>(reminders: &[Reminder], timezone: F, now: DateTime<Utc>) -> String
+where
+	F: Fn(ChatId) -> Tz,
+{
+	let mut lines = vec![
+		String::from("BEGIN:VCALENDAR"),
+		String::from("VERSION:2.0"),
+		format!("PRODID:{}", PRODUCT_ID),
+	];
+
+	for reminder in reminders {
+		let timezone = timezone(reminder.chat);
+
+		lines.push(
+			String::from("BEGIN:VEVENT")
+		);
+
+		lines.push(
+			format!("UID:{}.{}@reminder-bot", reminder.id, reminder.chat.0)
+		);
+
+		lines.push(
+			format!("DTSTAMP:{}", now.format(UTC_FORMAT))
+		);
+
+		lines.push(
+			format!(
+				"DTSTART;TZID={}:{}",
+				timezone.name(),
+				reminder.due
+					.local(timezone)
+					.format(LOCAL_FORMAT)
+			)
+		);
+
+		if let Some(recurrence) = reminder.recurrying {
+			lines.push(
+				format!(
+					"RRULE:{}",
+					rule(recurrence, reminder.recur_remaining, reminder.recur_until)
+				)
+			);
+		}
+
+		lines.push(
+			format!("SUMMARY:{}", escape(&reminder.message))
+		);
+
+		lines.push(
+			String::from("END:VEVENT")
+		);
+	}
+
+	lines.push(
+		String::from("END:VCALENDAR")
+	);
+
+	let mut ics = String::new();
+
+	for line in lines {
+		fold(&mut ics, &line);
+	}
+
+	ics
+}
+
+
+/// The events in an iCalendar file. Floating times are in the given timezone. Recurring
+/// events start at their first occurrence after `now`. Events a reminder can't represent,
+/// and the ones already over, are left out, and reported.
+pub fn import(ics: &str, timezone: Tz, now: DateTime<Utc>) -> Result<Calendar, ImportError> {
+	let lines = unfold(ics);
+
+	let is_calendar = lines
+		.first()
+		.map_or(false, |line| line.eq_ignore_ascii_case("BEGIN:VCALENDAR"));
+
+	if !is_calendar {
+		return Err(ImportError::NotCalendar);
+	}
+
+	let mut events = Vec::new();
+	let mut unsupported = Vec::new();
+	let mut number = 0;
+	// The components the line is in, e.g.: an alarm in an event in the calendar.
+	let mut components: Vec<String> = Vec::new();
+	let mut properties = Vec::new();
+
+	for line in lines {
+		let property = match Property::parse(&line) {
+			Some(property) => property,
+			None => continue,
+		};
+
+		match property.name.as_str() {
+			"BEGIN" => {
+				let component = property.value.to_uppercase();
+
+				if component == "VEVENT" {
+					properties.clear();
+				}
+
+				components.push(component);
+			}
+
+			"END" => {
+				let component = components.pop();
+
+				if component.as_deref() != Some("VEVENT") {
+					continue;
+				}
+
+				number += 1;
+
+				match event(&properties, timezone, DueTimestamp(now)) {
+					Ok(event) => events.push(event),
+
+					Err(error) => unsupported.push(
+						UnsupportedEvent {
+							number,
+							summary: summary(&properties),
+							error,
+						}
+					),
+				}
+			}
+
+			_ if components.last().map(String::as_str) == Some("VEVENT") => {
+				properties.push(property);
+			}
+
+			_ => (),
+		}
+	}
+
+	Ok(
+		Calendar {
+			events: events.into_boxed_slice(),
+			unsupported: unsupported.into_boxed_slice(),
+		}
+	)
+}
+
+
+fn find<'p>(properties: &'p [Property], name: &str) -> Option<&'p Property> {
+	properties
+		.iter()
+		.find(
+			|property| property.name == name
+		)
+}
+
+
+fn summary(properties: &[Property]) -> String {
+	find(properties, "SUMMARY").map_or_else(String::new, |summary| unescape(&summary.value))
+}
+
+
+/// The reminder for an event, at its first occurrence after `now`.
+fn event(properties: &[Property], timezone: Tz, now: DueTimestamp) -> Result<Event, EventError> {
+	let start = find(properties, "DTSTART").ok_or(EventError::MissingStart)?;
+
+	for name in ["EXDATE", "RDATE", "RECURRENCE-ID"].iter() {
+		if find(properties, name).is_some() {
+			return Err(
+				EventError::UnsupportedProperty((*name).into())
+			);
+		}
+	}
+
+	let due = parse_time(start, timezone, NaiveTime::from_hms(0, 0, 0))
+		.ok_or_else(
+			|| EventError::InvalidStart(start.value.as_str().into())
+		)?;
+
+	let rule = find(properties, "RRULE")
+		.map(
+			|rule| parse_rule(&rule.value, timezone)
+				.ok_or_else(
+					|| EventError::UnsupportedRecurrence(rule.value.as_str().into())
+				)
+		)
+		.transpose()?;
+
+	let message = summary(properties);
+
+	let (recurrence, recur_remaining, recur_until) = match rule {
+		Some((recurrence, count, until)) => (Some(recurrence), count, until),
+		None => (None, None, None),
+	};
+
+	let event = Event {
+		due,
+		recurrence,
+		message,
+		recur_until,
+		recur_remaining,
+		recur_anchor: recurrence.map(
+			|_| due
+				.local(timezone)
+				.day() as i32
+		),
+	};
+
+	event.upcoming(timezone, now)
+}
+
+
+/// A date or date and time property. Times in UTC end with `Z`, others are in the
+/// property's timezone, or else the given one. Dates are at the given time of day.
+fn parse_time(property: &Property, timezone: Tz, time_of_day: NaiveTime) -> Option<DueTimestamp> {
+	let timezone = match property.param("TZID") {
+		Some(name) => name.parse().ok()?,
+		None => timezone,
+	};
+
+	parse_value_time(property.value.trim(), timezone, time_of_day)
+}
+
+
+fn parse_value_time(value: &str, timezone: Tz, time_of_day: NaiveTime) -> Option<DueTimestamp> {
+	if let Some(utc) = value.strip_suffix('Z') {
+		let datetime = NaiveDateTime::parse_from_str(utc, LOCAL_FORMAT).ok()?;
+
+		return Some(
+			DueTimestamp(
+				Utc.from_utc_datetime(&datetime)
+			)
+		);
+	}
+
+	let datetime = match NaiveDate::parse_from_str(value, DATE_FORMAT) {
+		Ok(date) => date.and_time(time_of_day),
+		Err(_) => NaiveDateTime::parse_from_str(value, LOCAL_FORMAT).ok()?,
+	};
+
+	Some(
+		DueTimestamp::from_local(datetime, timezone)
+	)
+}
+
+
+/// A recurrence rule, with its count and end, e.g.: `FREQ=WEEKLY;BYDAY=MO,WE;COUNT=10`.
+fn parse_rule(rule: &str, timezone: Tz) -> Option<(Recurrence, Option<i32>, Option<DueTimestamp>)> {
+	let mut frequency = None;
+	let mut interval = 1;
+	let mut count = None;
+	let mut until = None;
+	let mut days = Vec::new();
+
+	for part in rule.trim().split(';') {
+		let (name, value) = part.split_once('=')?;
+
+		match name.to_uppercase().as_str() {
+			"FREQ" => frequency = Some(value.to_uppercase()),
+
+			"INTERVAL" => interval = value
+				.parse()
+				.ok()
+				.filter(
+					|&interval| interval > 0
+				)?,
+
+			"COUNT" => count = Some(
+				value
+					.parse()
+					.ok()
+					.filter(
+						|&count| count > 0
+					)?
+			),
+
+			// The whole last day is included.
+			"UNTIL" => until = Some(
+				parse_value_time(value, timezone, NaiveTime::from_hms(23, 59, 59))?
+			),
+
+			"BYDAY" => days = value
+				.split(',')
+				.map(parse_day)
+				.collect::<Option<_>>()?,
+
+			// Weeks starting on another day only matter with intervals.
+			"WKST" => (),
+
+			_ => return None,
+		}
+	}
+
+	let recurrence = match (frequency?.as_str(), &days[..]) {
+		(frequency, []) => Recurrence::Period {
+			ammount: interval,
+			unit: unit(frequency)?,
+		},
+
+		("WEEKLY", days) if interval == 1 && days.iter().all(|(nth, _)| nth.is_none()) => {
+			Recurrence::Weekdays(
+				days
+					.iter()
+					.map(
+						|&(_, weekday)| weekday
+					)
+					.collect()
+			)
+		}
+
+		("MONTHLY", &[(Some(nth), weekday)]) if interval == 1 => Recurrence::MonthlyWeekday {
+			nth: match nth {
+				-1 => Nth::Last,
+				1 ..= 5 => Nth::Nth(nth as u8),
+				_ => return None,
+			},
+			weekday,
+		},
+
+		_ => return None,
+	};
+
+	Some(
+		(recurrence, count, until)
+	)
+}
+
+
+/// A weekday, possibly preceded by which of the month it is, e.g.: `TU`, `2TU`, `-1FR`.
+fn parse_day(day: &str) -> Option<(Option<i8>, Weekday)> {
+	let day = day.trim();
+	let split = day.len().checked_sub(2)?;
+
+	// Weekday codes are ASCII, so anything else isn't one.
+	if !day.is_char_boundary(split) {
+		return None;
+	}
+
+	let nth = match &day[.. split] {
+		"" => None,
+		nth => Some(
+			nth
+				.trim_start_matches('+')
+				.parse()
+				.ok()?
+		),
+	};
+
+	let weekday = WEEKDAY_CODES
+		.iter()
+		.find(
+			|(_, code)| code.eq_ignore_ascii_case(&day[split ..])
+		)
+		.map(
+			|&(weekday, _)| weekday
+		)?;
+
+	Some(
+		(nth, weekday)
+	)
+}
+
+
+fn unit(frequency: &str) -> Option<RecurrenceUnit> {
+	match frequency {
+		"MINUTELY" => Some(RecurrenceUnit::Minutes),
+		"HOURLY"   => Some(RecurrenceUnit::Hours),
+		"DAILY"    => Some(RecurrenceUnit::Days),
+		"WEEKLY"   => Some(RecurrenceUnit::Weeks),
+		"MONTHLY"  => Some(RecurrenceUnit::Months),
+		"YEARLY"   => Some(RecurrenceUnit::Years),
+		_ => None,
+	}
+}
+
+
+fn frequency(unit: RecurrenceUnit) -> &'static str {
+	match unit {
+		RecurrenceUnit::Minutes => "MINUTELY",
+		RecurrenceUnit::Hours   => "HOURLY",
+		RecurrenceUnit::Days    => "DAILY",
+		RecurrenceUnit::Weeks   => "WEEKLY",
+		RecurrenceUnit::Months  => "MONTHLY",
+		RecurrenceUnit::Years   => "YEARLY",
+	}
+}
+
+
+const WEEKDAY_CODES: [(Weekday, &str); 7] = [
+	(Weekday::Mon, "MO"),
+	(Weekday::Tue, "TU"),
+	(Weekday::Wed, "WE"),
+	(Weekday::Thu, "TH"),
+	(Weekday::Fri, "FR"),
+	(Weekday::Sat, "SA"),
+	(Weekday::Sun, "SU"),
+];
+
+
+fn weekday_code(weekday: Weekday) -> &'static str {
+	WEEKDAY_CODES[weekday.num_days_from_monday() as usize].1
+}
+
+
+/// The recurrence rule of a reminder.
+fn rule(recurrence: Recurrence, remaining: Option<i32>, until: Option<DueTimestamp>) -> String {
+	let mut rule = match recurrence {
+		Recurrence::Period { ammount, unit } => format!(
+			"FREQ={};INTERVAL={}",
+			frequency(unit),
+			ammount
+		),
+
+		Recurrence::Weekdays(weekdays) if weekdays.is_empty() => String::from("FREQ=WEEKLY"),
+
+		Recurrence::Weekdays(weekdays) => format!(
+			"FREQ=WEEKLY;BYDAY={}",
+			weekdays
+				.iter()
+				.map(weekday_code)
+				.collect::<Vec<_>>()
+				.join(",")
+		),
+
+		Recurrence::MonthlyWeekday { nth, weekday } => format!(
+			"FREQ=MONTHLY;BYDAY={}{}",
+			match nth {
+				Nth::Nth(nth) => nth as i8,
+				Nth::Last => -1,
+			},
+			weekday_code(weekday)
+		),
+	};
+
+	// Rules may only have one of them.
+	let result = match (remaining, until) {
+		(Some(count), _) => write!(rule, ";COUNT={}", count),
+		(None, Some(until)) => write!(rule, ";UNTIL={}", until.0.format(UTC_FORMAT)),
+		(None, None) => Ok(()),
+	};
+
+	result.expect("write should not fail on string");
+
+	rule
+}
+
+
+fn escape(text: &str) -> String {
+	text
+		.replace('\\', "\\\\")
+		.replace(';', "\\;")
+		.replace(',', "\\,")
+		.replace('\n', "\\n")
+}
+
+
+fn unescape(text: &str) -> String {
+	let mut unescaped = String::with_capacity(text.len());
+	let mut chars = text.chars();
+
+	while let Some(c) = chars.next() {
+		if c != '\\' {
+			unescaped.push(c);
+			continue;
+		}
+
+		match chars.next() {
+			Some('n') | Some('N') => unescaped.push('\n'),
+			Some(escaped) => unescaped.push(escaped),
+			None => unescaped.push(c),
+		}
+	}
+
+	unescaped
+}
+
+
+/// Append the line, split into lines of at most `LINE_LIMIT` bytes, continued by a space.
+fn fold(ics: &mut String, line: &str) {
+	let mut rest = line;
+	let mut limit = LINE_LIMIT;
+
+	loop {
+		let mut split = rest.len().min(limit);
+
+		while !rest.is_char_boundary(split) {
+			split -= 1;
+		}
+
+		ics.push_str(&rest[.. split]);
+		ics.push_str("\r\n");
+
+		rest = &rest[split ..];
+
+		if rest.is_empty() {
+			break;
+		}
+
+		ics.push(' ');
+		limit = LINE_LIMIT - 1;
+	}
+}
+
+
+/// The content lines, with folded ones joined back.
+fn unfold(ics: &str) -> Vec<String> {
+	let mut lines: Vec<String> = Vec::new();
+
+	for line in ics.trim_start_matches('\u{feff}').lines() {
+		match (line.strip_prefix(|c| c == ' ' || c == '\t'), lines.last_mut()) {
+			(Some(continued), Some(last)) => last.push_str(continued),
+			_ if line.trim().is_empty() => (),
+			_ => lines.push(line.into()),
+		}
+	}
+
+	lines
+}
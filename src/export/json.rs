@@ -0,0 +1,201 @@
+use std::convert::TryFrom;
+
+use serde::{Deserialize, Serialize};
+
+use thiserror::Error;
+
+use chrono::{DateTime, Utc};
+
+use crate::{
+	bot,
+	db::{
+		models::reminders::Reminder,
+		types::{
+			ChatId,
+			DueTimestamp,
+			MediaKind,
+			MediaKindParseError,
+			Mentions,
+			MentionsParseError,
+			MessageId,
+			Recurrence,
+			RecurrenceParseError,
+			UserId,
+		},
+	},
+};
+
+
+#[derive(Debug, Error)]
+pub enum ImportError {
+	#[error("invalid json: {0}")]
+	Json(serde_json::Error),
+	#[error("reminder {0}: {1}")]
+	Recurrence(i32, RecurrenceParseError),
+	#[error("reminder {0}: {1}")]
+	MediaKind(i32, MediaKindParseError),
+	#[error("reminder {0}: {1}")]
+	Mentions(i32, MentionsParseError),
+}
+
+
+/// A reminder as stored in JSON, with every field of its row, so that nothing is lost
+/// when importing it back. Recurrences and mentions are in the same text format as in the
+/// database.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Serialize, Deserialize)]
+pub struct ReminderRecord {
+	pub id: i32,
+	pub chat: i64,
+	pub due: DateTime<Utc>,
+	pub recurrence: Option<String>,
+	pub message: String,
+	pub recur_until: Option<DateTime<Utc>>,
+	pub recur_remaining: Option<i32>,
+	pub recur_anchor: Option<i32>,
+	pub source_message: Option<i64>,
+	pub media_kind: Option<String>,
+	pub media_file_id: Option<String>,
+	pub creator_id: Option<i64>,
+	pub creator_name: Option<String>,
+	pub creator_username: Option<String>,
+	pub mentions: Option<String>,
+	pub recipient: Option<i64>,
+}
+
+
+impl From<&Reminder> for ReminderRecord {
+	fn from(reminder: &Reminder) -> Self {
+		Self {
+			id: reminder.id,
+			chat: reminder.chat.0.into(),
+			due: reminder.due.0,
+			recurrence: reminder.recurrying.map(
+				|recurrence| recurrence.to_string()
+			),
+			message: reminder.message.clone(),
+			recur_until: reminder.recur_until.map(
+				|until| until.0
+			),
+			recur_remaining: reminder.recur_remaining,
+			recur_anchor: reminder.recur_anchor,
+			source_message: reminder.source_message.map(
+				|message_id| message_id.0.into()
+			),
+			media_kind: reminder.media_kind.map(
+				|kind| kind.as_str().into()
+			),
+			media_file_id: reminder.media_file_id.clone(),
+			creator_id: reminder.creator_id.map(
+				|user_id| user_id.0.into()
+			),
+			creator_name: reminder.creator_name.clone(),
+			creator_username: reminder.creator_username.clone(),
+			mentions: reminder.mentions
+				.as_ref()
+				.map(
+					|mentions| mentions.to_string()
+				),
+			recipient: reminder.recipient.map(
+				|user_id| user_id.0.into()
+			),
+		}
+	}
+}
+
+
+impl TryFrom<ReminderRecord> for Reminder {
+	type Error = ImportError;
+
+	fn try_from(record: ReminderRecord) -> Result<Self, Self::Error> {
+		let id = record.id;
+
+		let recurrying = record.recurrence
+			.map(
+				|recurrence| recurrence.parse::<Recurrence>()
+			)
+			.transpose()
+			.map_err(
+				|error| ImportError::Recurrence(id, error)
+			)?;
+
+		let media_kind = record.media_kind
+			.map(
+				|kind| kind.parse::<MediaKind>()
+			)
+			.transpose()
+			.map_err(
+				|error| ImportError::MediaKind(id, error)
+			)?;
+
+		let mentions = record.mentions
+			.map(
+				|mentions| mentions.parse::<Mentions>()
+			)
+			.transpose()
+			.map_err(
+				|error| ImportError::Mentions(id, error)
+			)?;
+
+		Ok(
+			Reminder {
+				id,
+				due: DueTimestamp(record.due),
+				recurrying,
+				chat: ChatId(
+					bot::ChatId::new(record.chat)
+				),
+				message: record.message,
+				recur_until: record.recur_until.map(DueTimestamp),
+				recur_remaining: record.recur_remaining,
+				recur_anchor: record.recur_anchor,
+				source_message: record.source_message.map(
+					|message_id| MessageId(
+						bot::MessageId::new(message_id)
+					)
+				),
+				media_kind,
+				media_file_id: record.media_file_id,
+				creator_id: record.creator_id.map(
+					|user_id| UserId(
+						bot::UserId::new(user_id)
+					)
+				),
+				creator_name: record.creator_name,
+				creator_username: record.creator_username,
+				mentions,
+				recipient: record.recipient.map(
+					|user_id| UserId(
+						bot::UserId::new(user_id)
+					)
+				),
+			}
+		)
+	}
+}
+
+
+/// The reminders as a JSON array.
+pub fn export(reminders: &[Reminder]) -> String {
+	let records: Vec<ReminderRecord> = reminders
+		.iter()
+		.map(ReminderRecord::from)
+		.collect();
+
+	serde_json
+		::to_string_pretty(&records)
+		.expect("reminders should always serialize")
+}
+
+
+/// The reminders in a JSON array, as exported.
+pub fn import(json: &str) -> Result<Box<[Reminder]>, ImportError> {
+	let records: Vec<ReminderRecord> = serde_json
+		::from_str(json)
+		.map_err(ImportError::Json)?;
+
+	records
+		.into_iter()
+		.map(Reminder::try_from)
+		.collect()
+}
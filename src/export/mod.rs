@@ -0,0 +1,66 @@
+//! Converting reminders to and from other formats, for backups and migrations.
+
+#[cfg(test)]
+mod tests;
+
+pub mod ics;
+pub mod json;
+
+use std::str::FromStr;
+
+use thiserror::Error;
+
+
+/// A format reminders are exported in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Format {
+	/// Every field of the reminders, to be restored exactly.
+	Json,
+	/// An iCalendar file, for calendar applications.
+	Ics,
+}
+
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Error)]
+#[error("unknown format: {0}")]
+pub struct FormatParseError(Box<str>);
+
+
+impl Format {
+	/// Guess the format of an exported file from its contents.
+	pub fn detect(data: &str) -> Option<Self> {
+		let data = data.trim_start_matches('\u{feff}').trim_start();
+
+		if data.starts_with('[') {
+			Some(Format::Json)
+		} else if data.starts_with("BEGIN:VCALENDAR") {
+			Some(Format::Ics)
+		} else {
+			None
+		}
+	}
+
+
+	/// The name files in the format are sent with.
+	pub fn file_name(self) -> &'static str {
+		match self {
+			Format::Json => "reminders.json",
+			Format::Ics => "reminders.ics",
+		}
+	}
+}
+
+
+impl FromStr for Format {
+	type Err = FormatParseError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.to_lowercase().as_str() {
+			"json" => Ok(Format::Json),
+			"ics" | "ical" => Ok(Format::Ics),
+			_ => Err(
+				FormatParseError(s.into())
+			),
+		}
+	}
+}
@@ -0,0 +1,512 @@
+use chrono::{NaiveDate, TimeZone, Utc, Weekday};
+
+use chrono_tz::Tz;
+
+use super::*;
+use crate::{
+	bot,
+	db::{
+		Db,
+		models::reminders::Reminder,
+		types::{
+			ChatId,
+			DueTimestamp,
+			MediaKind,
+			Mentions,
+			MessageId,
+			Nth,
+			Recurrence,
+			RecurrenceUnit,
+			UserId,
+		},
+	},
+};
+
+
+const TIMEZONE: Tz = chrono_tz::America::Sao_Paulo;
+
+
+fn local(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> DueTimestamp {
+	DueTimestamp::from_local(
+		NaiveDate::from_ymd(year, month, day).and_hms(hour, minute, 0),
+		TIMEZONE
+	)
+}
+
+
+fn reminder(id: i32, chat: i64, recurrying: Option<Recurrence>, message: &str) -> Reminder {
+	Reminder {
+		id,
+		due: local(2021, 3, 2, 10, 0),
+		recurrying,
+		chat: ChatId(
+			bot::ChatId::new(chat)
+		),
+		message: message.into(),
+		recur_until: None,
+		recur_remaining: None,
+		recur_anchor: recurrying.map(
+			|_| 2
+		),
+		source_message: None,
+		media_kind: None,
+		media_file_id: None,
+		creator_id: None,
+		creator_name: None,
+		creator_username: None,
+		mentions: None,
+		recipient: None,
+	}
+}
+
+
+fn recurrences() -> [Recurrence; 4] {
+	[
+		Recurrence::Period { ammount: 2, unit: RecurrenceUnit::Days },
+		Recurrence::Period { ammount: 90, unit: RecurrenceUnit::Minutes },
+		Recurrence::Weekdays(
+			[Weekday::Mon, Weekday::Wed, Weekday::Fri]
+				.iter()
+				.copied()
+				.collect()
+		),
+		Recurrence::MonthlyWeekday { nth: Nth::Last, weekday: Weekday::Fri },
+	]
+}
+
+
+#[test]
+fn test_format() {
+	assert_eq!(Format::detect("\n[]"), Some(Format::Json));
+	assert_eq!(Format::detect("BEGIN:VCALENDAR\r\n"), Some(Format::Ics));
+	assert_eq!(Format::detect("id,chat,due"), None);
+
+	assert_eq!("ICS".parse(), Ok(Format::Ics));
+	assert!("csv".parse::<Format>().is_err());
+}
+
+
+#[test]
+fn test_json_round_trip() {
+	let full = Reminder {
+		recur_until: Some(
+			local(2021, 6, 1, 23, 59)
+		),
+		recur_remaining: Some(5),
+		source_message: Some(
+			MessageId(
+				bot::MessageId::new(42)
+			)
+		),
+		media_kind: Some(
+			MediaKind(bot::MediaKind::Photo)
+		),
+		media_file_id: Some("photo".into()),
+		creator_id: Some(
+			UserId(
+				bot::UserId::new(100)
+			)
+		),
+		creator_name: Some("tester".into()),
+		creator_username: Some("tester_bot".into()),
+		mentions: Some(
+			Mentions(
+				vec![
+					bot::Mention::Username("ana".into()),
+					bot::Mention::User { id: bot::UserId::new(7), name: "Bia".into() },
+				]
+					.into()
+			)
+		),
+		recipient: Some(
+			UserId(
+				bot::UserId::new(100)
+			)
+		),
+		..reminder(3, -100, Some(recurrences()[0]), "water plants")
+	};
+
+	let mut reminders = vec![full];
+
+	for (ix, &recurrence) in recurrences().iter().enumerate() {
+		reminders.push(
+			reminder(10 + ix as i32, 1, Some(recurrence), "stretch")
+		);
+	}
+
+	reminders.push(
+		reminder(20, 2, None, "pay the \"boleto\"")
+	);
+
+	let json = json::export(&reminders);
+
+	assert_eq!(
+		json::import(&json).expect("failed to import").as_ref(),
+		&reminders[..]
+	);
+
+	assert!(json::import("[{\"id\": 1}]").is_err());
+
+	// Restoring keeps the ids, without overwriting other reminders unless asked to.
+	let db = Db::open_in_memory();
+
+	db
+		.import_reminders(&reminders, false)
+		.expect("failed to store reminders");
+
+	assert_eq!(db.reminders().expect("failed to list reminders").as_ref(), &reminders[..]);
+
+	assert_eq!(
+		db.taken_reminder_ids(&[1, 3, 20]).expect("failed to look up reminders").as_ref(),
+		&[3, 20][..]
+	);
+
+	assert!(db.import_reminders(&reminders[.. 1], false).is_err());
+
+	db
+		.import_reminders(&reminders[.. 1], true)
+		.expect("failed to replace reminders");
+
+	assert_eq!(db.reminders().expect("failed to list reminders").len(), reminders.len());
+}
+
+
+#[test]
+fn test_ics_export() {
+	let now = Utc.ymd(2021, 3, 1).and_hms(12, 0, 0);
+
+	let reminders = [
+		Reminder {
+			recur_remaining: Some(3),
+			..reminder(1, -100, Some(recurrences()[0]), "water plants; all of them")
+		},
+		Reminder {
+			recur_until: Some(
+				local(2021, 6, 1, 23, 59)
+			),
+			..reminder(2, -100, Some(recurrences()[3]), "pizza")
+		},
+		reminder(3, -100, None, &"long message ".repeat(10)),
+	];
+
+	let ics = ics::export(&reminders, |_| TIMEZONE, now);
+
+	assert!(ics.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"), "{}", ics);
+	assert!(ics.ends_with("END:VCALENDAR\r\n"), "{}", ics);
+
+	assert!(ics.contains("\r\nUID:1.-100@reminder-bot\r\n"), "{}", ics);
+	assert!(ics.contains("\r\nDTSTAMP:20210301T120000Z\r\n"), "{}", ics);
+	assert!(ics.contains("\r\nDTSTART;TZID=America/Sao_Paulo:20210302T100000\r\n"), "{}", ics);
+	assert!(ics.contains("\r\nRRULE:FREQ=DAILY;INTERVAL=2;COUNT=3\r\n"), "{}", ics);
+	assert!(ics.contains("\r\nSUMMARY:water plants\\; all of them\r\n"), "{}", ics);
+	assert!(ics.contains("\r\nRRULE:FREQ=MONTHLY;BYDAY=-1FR;UNTIL=20210602T025900Z\r\n"), "{}", ics);
+
+	for line in ics.split("\r\n") {
+		assert!(line.len() <= 75, "{}", line);
+	}
+
+	let calendar = ics::import(&ics, TIMEZONE, now).expect("failed to import");
+
+	assert!(calendar.unsupported.is_empty());
+	assert_eq!(calendar.events.len(), reminders.len());
+
+	for (event, reminder) in calendar.events.iter().zip(reminders.iter()) {
+		assert_eq!(event.due, reminder.due);
+		assert_eq!(event.recurrence, reminder.recurrying);
+		assert_eq!(event.message, reminder.message);
+		assert_eq!(event.recur_until, reminder.recur_until);
+		assert_eq!(event.recur_remaining, reminder.recur_remaining);
+		assert_eq!(event.recur_anchor, reminder.recur_anchor);
+	}
+}
+
+
+#[test]
+fn test_ics_import() {
+	let ics = "\
+BEGIN:VCALENDAR\r
+PRODID:-//Calendar//EN\r
+VERSION:2.0\r
+BEGIN:VEVENT\r
+DTSTART:20210302T130000Z\r
+SUMMARY:stand-up\\, daily\r
+RRULE:FREQ=WEEKLY;BYDAY=MO,TU,WE,TH,FR;UNTIL=20210401\r
+BEGIN:VALARM\r
+TRIGGER:-PT10M\r
+DESCRIPTION:alarm\r
+END:VALARM\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+DTSTART:20210302T130000Z\r
+RRULE:FREQ=MONTHLY;BYMONTHDAY=1,15\r
+SUMMARY:rent\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+SUMMARY:someday\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+DTSTART;VALUE=DATE:20210315\r
+SUMMARY:a very long summary that was folded by the calendar application th\r
+ at exported it\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+DTSTART;TZID=\"Europe/Lisbon\":20210402T090000\r
+RRULE:FREQ=MONTHLY;BYDAY=2TU;COUNT=4\r
+SUMMARY:bills\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+
+	let now = Utc.ymd(2021, 3, 1).and_hms(12, 0, 0);
+
+	let calendar = ics::import(ics, TIMEZONE, now).expect("failed to import");
+
+	assert_eq!(
+		calendar.events.as_ref(),
+		&[
+			ics::Event {
+				due: local(2021, 3, 2, 10, 0),
+				recurrence: Some(
+					Recurrence::Weekdays(
+						[Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri]
+							.iter()
+							.copied()
+							.collect()
+					)
+				),
+				message: "stand-up, daily".into(),
+				recur_until: Some(
+					DueTimestamp::from_local(
+						NaiveDate::from_ymd(2021, 4, 1).and_hms(23, 59, 59),
+						TIMEZONE
+					)
+				),
+				recur_remaining: None,
+				recur_anchor: Some(2),
+			},
+			ics::Event {
+				due: local(2021, 3, 15, 0, 0),
+				recurrence: None,
+				message: "a very long summary that was folded by the calendar application that exported it".into(),
+				recur_until: None,
+				recur_remaining: None,
+				recur_anchor: None,
+			},
+			ics::Event {
+				due: DueTimestamp(
+					Utc.ymd(2021, 4, 2).and_hms(8, 0, 0)
+				),
+				recurrence: Some(
+					Recurrence::MonthlyWeekday { nth: Nth::Nth(2), weekday: Weekday::Tue }
+				),
+				message: "bills".into(),
+				recur_until: None,
+				recur_remaining: Some(4),
+				recur_anchor: Some(2),
+			},
+		][..]
+	);
+
+	assert_eq!(
+		calendar.unsupported.as_ref(),
+		&[
+			ics::UnsupportedEvent {
+				number: 2,
+				summary: "rent".into(),
+				error: ics::EventError::UnsupportedRecurrence("FREQ=MONTHLY;BYMONTHDAY=1,15".into()),
+			},
+			ics::UnsupportedEvent {
+				number: 3,
+				summary: "someday".into(),
+				error: ics::EventError::MissingStart,
+			},
+		][..]
+	);
+
+	assert_eq!(
+		calendar.unsupported[0].to_string(),
+		"#2 rent: unsupported recurrence: FREQ=MONTHLY;BYMONTHDAY=1,15"
+	);
+
+	assert_eq!(ics::import("id,chat,due", TIMEZONE, now), Err(ics::ImportError::NotCalendar));
+}
+
+
+#[test]
+fn test_ics_import_past() {
+	let ics = "\
+BEGIN:VCALENDAR\r
+BEGIN:VEVENT\r
+DTSTART;TZID=America/Sao_Paulo:20210302T100000\r
+SUMMARY:dentist\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+DTSTART;TZID=America/Sao_Paulo:20210301T100000\r
+RRULE:FREQ=DAILY;COUNT=20\r
+SUMMARY:stretch\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+DTSTART;TZID=America/Sao_Paulo:20210301T100000\r
+RRULE:FREQ=DAILY;COUNT=5\r
+SUMMARY:course\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+DTSTART;TZID=America/Sao_Paulo:20210301T100000\r
+RRULE:FREQ=WEEKLY;UNTIL=20210309\r
+SUMMARY:sprint\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+DTSTART;TZID=America/Sao_Paulo:20210401T100000\r
+RRULE:FREQ=WEEKLY\r
+EXDATE;TZID=America/Sao_Paulo:20210408T100000\r
+SUMMARY:review\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+DTSTART;TZID=America/Sao_Paulo:20210402T110000\r
+RECURRENCE-ID;TZID=America/Sao_Paulo:20210401T100000\r
+SUMMARY:review\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+
+	// 09:00 in the calendar's timezone.
+	let now = Utc.ymd(2021, 3, 10).and_hms(12, 0, 0);
+
+	let calendar = ics::import(ics, TIMEZONE, now).expect("failed to import");
+
+	assert_eq!(
+		calendar.events.as_ref(),
+		&[
+			ics::Event {
+				due: local(2021, 3, 10, 10, 0),
+				recurrence: Some(
+					Recurrence::Period { ammount: 1, unit: RecurrenceUnit::Days }
+				),
+				message: "stretch".into(),
+				recur_until: None,
+				recur_remaining: Some(11),
+				recur_anchor: Some(1),
+			},
+		][..]
+	);
+
+	let errors: Vec<_> = calendar.unsupported
+		.iter()
+		.map(
+			|event| (event.summary.as_str(), event.error.clone())
+		)
+		.collect();
+
+	assert_eq!(
+		errors,
+		vec![
+			("dentist", ics::EventError::Past),
+			("course", ics::EventError::Past),
+			("sprint", ics::EventError::Past),
+			("review", ics::EventError::UnsupportedProperty("EXDATE".into())),
+			("review", ics::EventError::UnsupportedProperty("RECURRENCE-ID".into())),
+		]
+	);
+}
+
+
+#[test]
+fn test_ics_import_non_ascii_day() {
+	let ics = "\
+BEGIN:VCALENDAR\r
+BEGIN:VEVENT\r
+DTSTART;TZID=America/Sao_Paulo:20210302T100000\r
+RRULE:FREQ=WEEKLY;BYDAY=éa\r
+SUMMARY:feira\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+
+	let now = Utc.ymd(2021, 3, 1).and_hms(12, 0, 0);
+
+	let calendar = ics::import(ics, TIMEZONE, now).expect("failed to import");
+
+	assert!(calendar.events.is_empty());
+
+	assert_eq!(
+		calendar.unsupported[0].error,
+		ics::EventError::UnsupportedRecurrence("FREQ=WEEKLY;BYDAY=éa".into())
+	);
+}
+
+
+#[test]
+fn test_ics_import_long_past() {
+	let ics = "\
+BEGIN:VCALENDAR\r
+BEGIN:VEVENT\r
+DTSTART:00010101T000000Z\r
+RRULE:FREQ=MINUTELY\r
+SUMMARY:breathe\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+DTSTART;TZID=America/Sao_Paulo:19000131T100000\r
+RRULE:FREQ=MONTHLY;INTERVAL=2\r
+SUMMARY:backup\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+DTSTART;TZID=America/Sao_Paulo:19000101T100000\r
+RRULE:FREQ=DAILY;COUNT=1000\r
+SUMMARY:diary\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+DTSTART;TZID=America/Sao_Paulo:00010101T100000\r
+RRULE:FREQ=WEEKLY;BYDAY=MO,FR\r
+SUMMARY:gym\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+
+	let now = Utc.ymd(2021, 3, 1).and_hms(12, 0, 0);
+
+	let calendar = ics::import(ics, TIMEZONE, now).expect("failed to import");
+
+	assert_eq!(
+		calendar.events.as_ref(),
+		&[
+			ics::Event {
+				due: DueTimestamp(
+					Utc.ymd(2021, 3, 1).and_hms(12, 1, 0)
+				),
+				recurrence: Some(
+					Recurrence::Period { ammount: 1, unit: RecurrenceUnit::Minutes }
+				),
+				message: "breathe".into(),
+				recur_until: None,
+				recur_remaining: None,
+				// Still the last day of year 0 in the calendar's timezone.
+				recur_anchor: Some(31),
+			},
+			ics::Event {
+				due: local(2021, 3, 31, 10, 0),
+				recurrence: Some(
+					Recurrence::Period { ammount: 2, unit: RecurrenceUnit::Months }
+				),
+				message: "backup".into(),
+				recur_until: None,
+				recur_remaining: None,
+				recur_anchor: Some(31),
+			},
+		][..]
+	);
+
+	let errors: Vec<_> = calendar.unsupported
+		.iter()
+		.map(
+			|event| (event.summary.as_str(), event.error.clone())
+		)
+		.collect();
+
+	assert_eq!(
+		errors,
+		vec![
+			("diary", ics::EventError::Past),
+			("gym", ics::EventError::TooManyOccurrences),
+		]
+	);
+}
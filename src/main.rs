@@ -1,34 +1,56 @@
 #[macro_use] extern crate diesel;
 
 mod bot;
+mod cli;
+mod clock;
 mod config;
 mod controller;
 mod db;
+mod export;
 mod logging;
 
 use std::{fs::File, io::Read};
 
 use anyhow::Context;
 
-use tokio::signal::unix::SignalKind;
+use tokio::{
+	signal::unix::SignalKind,
+	sync::Notify,
+};
 
 use crate::{
 	config::Config,
-	bot::Bot,
+	bot::{Authenticator, Bot, Webhook},
+	cli::{Args, Command},
+	clock::SystemClock,
 	db::Db,
 };
 
 
-const CONFIG_FILE: &'static str = "./config.toml";
-
-
 #[tokio::main(flavor = "current_thread")]
 async fn main() -> anyhow::Result<()> {
-	// One "sending notifications" log batch every 3 hours.
+	// One "sending notifications" log batch every 3 hours when idle.
 	const LOG_BATCH_SIZE: usize = 36;
 	logging::setup(LOG_BATCH_SIZE)?;
 
-	let config = load_config()?;
+	let args = match Args::parse(std::env::args().skip(1)) {
+		Ok(args) => args,
+		Err(error) => {
+			eprintln!("{}\n\n{}", error, cli::USAGE);
+			std::process::exit(2);
+		}
+	};
+
+	if args.command == Command::Help {
+		println!("{}", cli::USAGE);
+		return Ok(());
+	}
+
+	let config = load_config(&args.config)?;
+
+	if args.command == Command::CheckSchema {
+		return check_schema(&config);
+	}
 
 	let db = Db
 		::open(&config.bot.db)
@@ -36,12 +58,32 @@ async fn main() -> anyhow::Result<()> {
 			|| format!("failed to open database: {}", config.bot.db)
 		)?;
 
-	let bot = Bot::new(
-		config.bot.key,
-		&db,
-		&config.bot.username,
-		&config.bot.authentication
-	)?;
+	if args.command != Command::Run {
+		return cli::execute(args.command, &db, &config);
+	}
+
+	let webhook = match &config.bot.webhook {
+		Some(webhook) => {
+			let webhook = Webhook
+				::bind(webhook)
+				.with_context(
+					|| format!("failed to listen for webhooks on: {}", webhook.address)
+				)?;
+
+			log::info!("Listening for webhooks on {}", webhook.address());
+
+			Some(webhook)
+		}
+
+		None => None,
+	};
+
+	let bot = Bot::new(&config.bot.key, &config.bot.username, webhook);
+
+	let authenticator = Authenticator::new(&bot, &db, &SystemClock, &config.bot.authentication)?;
+
+	// Notified when the reminders schedule changes.
+	let reschedule = Notify::new();
 
 	let mut sigint = signal(SignalKind::interrupt())?;
 	let mut sigquit = signal(SignalKind::quit())?;
@@ -66,14 +108,14 @@ async fn main() -> anyhow::Result<()> {
 
 			// The bot future should never finish, and when it does, it should always return an
 			// error.
-			Err(error) = launch_bot(&db, &bot, &config.commands, &config.messages) => {
+			Err(error) = launch_bot(&db, &bot, &authenticator, &config, &reschedule) => {
 				log::error!("Bot future halted: {}", error);
 				break;
 			}
 
 			// The notificator future should never finish, and when it does, it should always
 			// return an error.
-			Err(error) = launch_notificator(&db, &bot) => {
+			Err(error) = launch_notificator(&db, &bot, &config, &reschedule) => {
 				log::error!("Notificator future halted: {}", error);
 				break;
 			}
@@ -84,6 +126,28 @@ async fn main() -> anyhow::Result<()> {
 }
 
 
+/// Report the migrations that would run when starting, without running them.
+fn check_schema(config: &Config) -> anyhow::Result<()> {
+	let pending = Db
+		::check_schema(&config.bot.db)
+		.with_context(
+			|| format!("failed to check database: {}", config.bot.db)
+		)?;
+
+	if pending.is_empty() {
+		println!("The database schema is up to date.");
+	} else {
+		println!("Pending migrations:");
+
+		for migration in pending.iter() {
+			println!("  {}", migration.name);
+		}
+	}
+
+	Ok(())
+}
+
+
 fn signal(signal: SignalKind) -> anyhow::Result<tokio::signal::unix::Signal> {
 	tokio::signal::unix
 		::signal(signal)
@@ -93,11 +157,11 @@ fn signal(signal: SignalKind) -> anyhow::Result<tokio::signal::unix::Signal> {
 }
 
 
-fn load_config() -> anyhow::Result<Config> {
+fn load_config(path: &str) -> anyhow::Result<Config> {
 	let mut config_file = File
-		::open(CONFIG_FILE)
+		::open(path)
 		.with_context(
-			|| format!("failed to open config file: {}", CONFIG_FILE)
+			|| format!("failed to open config file: {}", path)
 		)?;
 
 	let mut data = Vec::new();
@@ -105,13 +169,13 @@ fn load_config() -> anyhow::Result<Config> {
 	config_file
 		.read_to_end(&mut data)
 		.with_context(
-			|| format!("failed to read config file: {}", CONFIG_FILE)
+			|| format!("failed to read config file: {}", path)
 		)?;
 
 	let config = Config
 		::from_toml(&data)
 		.with_context(
-			|| format!("failed to parse config file: {}", CONFIG_FILE)
+			|| format!("failed to parse config file: {}", path)
 		)?;
 
 	Ok(config)
@@ -122,11 +186,22 @@ fn load_config() -> anyhow::Result<Config> {
 async fn launch_bot<'a>(
 	db: &'a Db,
 	bot: &'a Bot<'a>,
-	commands: &'a config::Commands,
-	messages: &'a config::Messages,
+	authenticator: &'a Authenticator<'a, Bot<'a>, SystemClock>,
+	config: &'a Config,
+	reschedule: &'a Notify,
 ) -> anyhow::Result<()> {
 	controller
-		::launch_bot(db, bot, commands, messages)
+		::launch_bot(
+			db,
+			bot,
+			&SystemClock,
+			authenticator,
+			&config.commands,
+			&config.words,
+			&config.messages,
+			config.bot.timezone,
+			reschedule
+		)
 		.await;
 
 	Err(
@@ -136,9 +211,22 @@ async fn launch_bot<'a>(
 
 
 // Should loop forever, or return an error.
-async fn launch_notificator<'a>(db: &'a Db, bot: &'a Bot<'a>) -> anyhow::Result<()> {
+async fn launch_notificator<'a>(
+	db: &'a Db,
+	bot: &'a Bot<'a>,
+	config: &'a Config,
+	reschedule: &'a Notify,
+) -> anyhow::Result<()> {
 	controller
-		::launch_notificator(db, bot)
+		::launch_notificator(
+			db,
+			bot,
+			&SystemClock,
+			&config.messages,
+			config.bot.timezone,
+			config.bot.catch_up,
+			reschedule
+		)
 		.await;
 
 	Err(